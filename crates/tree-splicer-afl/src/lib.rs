@@ -0,0 +1,98 @@
+//! [AFL++ custom mutator][custom] that splices inputs from the fuzzing queue.
+//!
+//! Select the language with the `TREE_SPLICER_LANGUAGE` environment variable:
+//!
+//! ```sh
+//! TREE_SPLICER_LANGUAGE=rust \
+//!   AFL_CUSTOM_MUTATOR_LIBRARY=target/release/libtree_splicer_afl.so \
+//!   afl-fuzz -i seeds -o out -- ./target @@
+//! ```
+//!
+//! [custom]: https://github.com/AFLplusplus/AFLplusplus/blob/stable/docs/custom_mutators.md
+
+use std::os::raw::{c_uint, c_void};
+use std::ptr;
+use std::slice;
+
+use tree_splicer::node_types::NodeTypes;
+use tree_splicer::splice::Config;
+use tree_splicer::Mutator;
+use tree_splicer_languages::language;
+
+struct State {
+    mutator: Mutator,
+    /// Owns the buffer returned by [`afl_custom_fuzz`]
+    out: Vec<u8>,
+}
+
+fn init(seed: u64) -> Result<State, String> {
+    let name = std::env::var("TREE_SPLICER_LANGUAGE")
+        .map_err(|_| "TREE_SPLICER_LANGUAGE is not set".to_string())?;
+    let (language, node_types_json_str) =
+        language(&name).ok_or_else(|| format!("Unknown or disabled language {}", name))?;
+    let node_types = NodeTypes::new(node_types_json_str).map_err(|e| e.to_string())?;
+    let config = Config {
+        seed,
+        ..Config::new(language, node_types)
+    };
+    Ok(State {
+        mutator: Mutator::new(config),
+        out: Vec::new(),
+    })
+}
+
+/// Returns null on failure, which makes AFL++ exit.
+#[no_mangle]
+pub extern "C" fn afl_custom_init(_afl: *mut c_void, seed: c_uint) -> *mut c_void {
+    match init(u64::from(seed)) {
+        Ok(state) => Box::into_raw(Box::new(state)).cast(),
+        Err(e) => {
+            eprintln!("tree-splicer-afl: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Splice `buf`, taking candidates from it and from `add_buf` (another input
+/// from the queue).
+///
+/// # Safety
+///
+/// `data` must come from [`afl_custom_init`], `buf` and `add_buf` must point
+/// to `buf_size` and `add_buf_size` bytes (`add_buf` may be null), and
+/// `out_buf` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_fuzz(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *mut u8,
+    add_buf: *mut u8,
+    add_buf_size: usize,
+    max_size: usize,
+) -> usize {
+    let state = &mut *data.cast::<State>();
+    let input = slice::from_raw_parts(buf, buf_size);
+    let mut donors = Vec::with_capacity(1);
+    if !add_buf.is_null() {
+        donors.push(slice::from_raw_parts(add_buf.cast_const(), add_buf_size));
+    }
+    state.mutator.config.max_size = max_size;
+    state.out = state
+        .mutator
+        .mutate(input, &donors)
+        .unwrap_or_else(|| input.to_vec());
+    state.out.truncate(max_size);
+    *out_buf = state.out.as_mut_ptr();
+    state.out.len()
+}
+
+/// # Safety
+///
+/// `data` must come from [`afl_custom_init`], and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn afl_custom_deinit(data: *mut c_void) {
+    if !data.is_null() {
+        drop(Box::from_raw(data.cast::<State>()));
+    }
+}
@@ -0,0 +1,67 @@
+#ifndef TREE_SPLICER_H
+#define TREE_SPLICER_H
+
+/* Generated by cbindgen from src/lib.rs, don't edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// A mutator and its donors
+typedef struct ts_splicer ts_splicer;
+
+// Make a splicer for `language` (e.g., `"rust"`), with the same defaults as
+// the command-line tools. Returns null if the language is unknown or its
+// feature is disabled, or `language` is null.
+//
+// # Safety
+//
+// `language` must be a null-terminated string, or null.
+struct ts_splicer *ts_splicer_new(const char *language, uint64_t seed);
+
+// Also take splice candidates from a copy of the `size` bytes at `data`.
+// Does nothing if `splicer` is null.
+//
+// # Safety
+//
+// `splicer` must come from [`ts_splicer_new`] or be null. `data` must point
+// to `size` bytes, or be null for no bytes.
+void ts_splicer_add_donor(struct ts_splicer *splicer, const uint8_t *data, size_t size);
+
+// Splice the `size` bytes at `data`, taking candidates from them and from the
+// donors, and write the result to `out`.
+//
+// Returns the size of the result, which is at most `max_size`. If no
+// mutation was possible, copies the input. `out` may be `data`, to mutate in
+// place. Returns 0 and writes nothing if `splicer` or `out` is null.
+//
+// # Safety
+//
+// `splicer` must come from [`ts_splicer_new`] or be null. `data` must point
+// to `size` bytes, or be null for no bytes. `out` must be valid for writes
+// of `max_size` bytes, or be null.
+size_t ts_splicer_mutate(struct ts_splicer *splicer,
+                         const uint8_t *data,
+                         size_t size,
+                         uint8_t *out,
+                         size_t max_size);
+
+// Reset the random number generator, e.g., to the seed a fuzzer passes in.
+// Does nothing if `splicer` is null.
+//
+// # Safety
+//
+// `splicer` must come from [`ts_splicer_new`] or be null.
+void ts_splicer_reseed(struct ts_splicer *splicer, uint64_t seed);
+
+// Free a splicer.
+//
+// # Safety
+//
+// `splicer` must come from [`ts_splicer_new`] (or be null), and not be used
+// afterwards.
+void ts_splicer_free(struct ts_splicer *splicer);
+
+#endif  /* TREE_SPLICER_H */
@@ -0,0 +1,161 @@
+//! C API, for embedding the mutator in fuzzers that aren't written in Rust.
+//!
+//! The header is `include/tree_splicer.h`, which the build regenerates. Link
+//! against `libtree_splicer_capi.so`, or `libtree_splicer_capi.a` and the C++
+//! standard library (some grammars' scanners are C++):
+//!
+//! ```c
+//! #include "tree_splicer.h"
+//!
+//! ts_splicer *splicer = ts_splicer_new("rust", 0);
+//! ts_splicer_add_donor(splicer, donor, donor_size);
+//! size_t size = ts_splicer_mutate(splicer, data, data_size, out, out_size);
+//! ts_splicer_free(splicer);
+//! ```
+
+use std::ffi::{c_char, CStr};
+use std::ptr;
+use std::slice;
+
+use tree_splicer::node_types::NodeTypes;
+use tree_splicer::splice::Config;
+use tree_splicer::Mutator;
+use tree_splicer_languages::language;
+
+/// A mutator and its donors
+pub struct TsSplicer {
+    mutator: Mutator,
+    donors: Vec<Vec<u8>>,
+}
+
+fn new(language: &str, seed: u64) -> Result<TsSplicer, String> {
+    let (language, node_types_json_str) = self::language(language)
+        .ok_or_else(|| format!("Unknown or disabled language {}", language))?;
+    let node_types = NodeTypes::new(node_types_json_str).map_err(|e| e.to_string())?;
+    let config = Config {
+        seed,
+        ..Config::new(language, node_types)
+    };
+    Ok(TsSplicer {
+        mutator: Mutator::new(config),
+        donors: Vec::new(),
+    })
+}
+
+/// The `size` bytes at `data`, or none if `data` is null, which C callers
+/// pass for empty inputs but `slice::from_raw_parts` doesn't allow
+unsafe fn bytes<'a>(data: *const u8, size: usize) -> &'a [u8] {
+    if data.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(data, size)
+    }
+}
+
+/// Make a splicer for `language` (e.g., `"rust"`), with the same defaults as
+/// the command-line tools. Returns null if the language is unknown or its
+/// feature is disabled, or `language` is null.
+///
+/// # Safety
+///
+/// `language` must be a null-terminated string, or null.
+#[no_mangle]
+pub unsafe extern "C" fn ts_splicer_new(language: *const c_char, seed: u64) -> *mut TsSplicer {
+    if language.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(language) = CStr::from_ptr(language).to_str() else {
+        return ptr::null_mut();
+    };
+    match new(language, seed) {
+        Ok(splicer) => Box::into_raw(Box::new(splicer)),
+        Err(e) => {
+            eprintln!("tree-splicer-capi: {}", e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Also take splice candidates from a copy of the `size` bytes at `data`.
+/// Does nothing if `splicer` is null.
+///
+/// # Safety
+///
+/// `splicer` must come from [`ts_splicer_new`] or be null. `data` must point
+/// to `size` bytes, or be null for no bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ts_splicer_add_donor(
+    splicer: *mut TsSplicer,
+    data: *const u8,
+    size: usize,
+) {
+    let Some(splicer) = splicer.as_mut() else {
+        return;
+    };
+    splicer.donors.push(bytes(data, size).to_vec());
+}
+
+/// Splice the `size` bytes at `data`, taking candidates from them and from the
+/// donors, and write the result to `out`.
+///
+/// Returns the size of the result, which is at most `max_size`. If no
+/// mutation was possible, copies the input. `out` may be `data`, to mutate in
+/// place. Returns 0 and writes nothing if `splicer` or `out` is null.
+///
+/// # Safety
+///
+/// `splicer` must come from [`ts_splicer_new`] or be null. `data` must point
+/// to `size` bytes, or be null for no bytes. `out` must be valid for writes
+/// of `max_size` bytes, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn ts_splicer_mutate(
+    splicer: *mut TsSplicer,
+    data: *const u8,
+    size: usize,
+    out: *mut u8,
+    max_size: usize,
+) -> usize {
+    let Some(splicer) = splicer.as_mut() else {
+        return 0;
+    };
+    if out.is_null() {
+        return 0;
+    }
+    let input = bytes(data, size);
+    let donors: Vec<_> = splicer.donors.iter().map(Vec::as_slice).collect();
+    splicer.mutator.config.max_size = max_size;
+    let mutant = splicer
+        .mutator
+        .mutate(input, &donors)
+        .unwrap_or_else(|| input.to_vec());
+    let size = mutant.len().min(max_size);
+    // Not `copy_nonoverlapping`, since `out` may be `data`
+    ptr::copy(mutant.as_ptr(), out, size);
+    size
+}
+
+/// Reset the random number generator, e.g., to the seed a fuzzer passes in.
+/// Does nothing if `splicer` is null.
+///
+/// # Safety
+///
+/// `splicer` must come from [`ts_splicer_new`] or be null.
+#[no_mangle]
+pub unsafe extern "C" fn ts_splicer_reseed(splicer: *mut TsSplicer, seed: u64) {
+    if let Some(splicer) = splicer.as_mut() {
+        splicer.mutator.reseed(seed);
+    }
+}
+
+/// Free a splicer.
+///
+/// # Safety
+///
+/// `splicer` must come from [`ts_splicer_new`] (or be null), and not be used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn ts_splicer_free(splicer: *mut TsSplicer) {
+    if !splicer.is_null() {
+        drop(Box::from_raw(splicer));
+    }
+}
@@ -0,0 +1,8 @@
+use anyhow::Result;
+
+fn main() -> Result<()> {
+    tree_splicer::cli::main(
+        tree_sitter_dockerfile::language(),
+        tree_sitter_dockerfile::NODE_TYPES,
+    )
+}
@@ -0,0 +1,93 @@
+//! Load a tree-sitter grammar from a shared library at runtime.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use libloading::{Library, Symbol};
+use tree_sitter::Language;
+use tree_splicer::cli::Command;
+
+/// Simple grammar-based test case generator for any tree-sitter grammar
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Shared library with the grammar, e.g., `libtree-sitter-foo.so`;
+    /// required except for `replay`
+    #[arg(long, global = true, value_name = "FILE")]
+    grammar: Option<PathBuf>,
+
+    /// The grammar's `node-types.json`, usually `src/node-types.json`;
+    /// required except for `replay`
+    #[arg(long, global = true, value_name = "FILE")]
+    node_types: Option<PathBuf>,
+
+    /// Function in `--grammar` that returns the language; default =
+    /// `tree_sitter_foo` for `libtree-sitter-foo.so` or `foo.so`
+    #[arg(long, global = true, value_name = "NAME")]
+    symbol: Option<String>,
+
+    #[command(flatten)]
+    args: tree_splicer::cli::Args,
+}
+
+/// `tree_sitter_foo` for `libtree-sitter-foo.so` or `foo.so`
+fn default_symbol(grammar: &Path) -> Option<String> {
+    let stem = grammar.file_stem()?.to_str()?;
+    let name = stem.strip_prefix("lib").unwrap_or(stem);
+    let name = name.strip_prefix("tree-sitter-").unwrap_or(name);
+    Some(format!("tree_sitter_{}", name.replace('-', "_")))
+}
+
+fn load(grammar: &Path, symbol: &str) -> Result<Language> {
+    // SAFETY: Loading the library runs its initializers, and the symbol must
+    // be a tree-sitter language function; the user vouches for both.
+    unsafe {
+        let library = Library::new(grammar)
+            .with_context(|| format!("Couldn't load grammar {}", grammar.display()))?;
+        let function: Symbol<unsafe extern "C" fn() -> Language> =
+            library
+                .get(symbol.as_bytes())
+                .with_context(|| format!("Couldn't find {} in {}", symbol, grammar.display()))?;
+        let language = function();
+        // The language points into the library, so never unload it
+        std::mem::forget(library);
+        let version = language.version();
+        if !(tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION..=tree_sitter::LANGUAGE_VERSION)
+            .contains(&version)
+        {
+            bail!(
+                "Grammar {} has ABI version {}, expected {} to {}",
+                grammar.display(),
+                version,
+                tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION,
+                tree_sitter::LANGUAGE_VERSION
+            );
+        }
+        Ok(language)
+    }
+}
+
+fn main() -> Result<()> {
+    let cli: Cli = tree_splicer::cli::parse_args()?;
+    let command = cli.args.into_command();
+    let language = match &cli.grammar {
+        Some(grammar) => {
+            let symbol = match &cli.symbol {
+                Some(symbol) => symbol.clone(),
+                None => default_symbol(grammar).context("Couldn't guess --symbol")?,
+            };
+            Some(load(grammar, &symbol)?)
+        }
+        None => None,
+    };
+    // Only traces of preprocessed inputs need the grammar
+    if let Command::Replay(replay) = &command {
+        return replay.run(language);
+    }
+    let language = language.context("Missing --grammar")?;
+    let node_types_path = cli.node_types.context("Missing --node-types")?;
+    let node_types = std::fs::read_to_string(&node_types_path)
+        .with_context(|| format!("Couldn't read {}", node_types_path.display()))?;
+    tree_splicer::cli::run_command(command, language, &node_types, Vec::new())
+}
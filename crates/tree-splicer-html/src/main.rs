@@ -0,0 +1,25 @@
+use anyhow::Result;
+use tree_splicer::node_types::NodeTypes;
+use tree_splicer::Injection;
+
+fn main() -> Result<()> {
+    let css = Injection {
+        language: tree_sitter_css::language(),
+        node_types: NodeTypes::new(tree_sitter_css::NODE_TYPES)?,
+        parent_kind: "style_element",
+        kind: "raw_text",
+        tag: None,
+    };
+    let javascript = Injection {
+        language: tree_sitter_javascript::language(),
+        node_types: NodeTypes::new(tree_sitter_javascript::NODE_TYPES)?,
+        parent_kind: "script_element",
+        kind: "raw_text",
+        tag: None,
+    };
+    tree_splicer::cli::main_with_injections(
+        tree_sitter_html::language(),
+        tree_sitter_html::NODE_TYPES,
+        vec![css, javascript],
+    )
+}
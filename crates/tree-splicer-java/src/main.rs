@@ -0,0 +1,5 @@
+use anyhow::Result;
+
+fn main() -> Result<()> {
+    tree_splicer::cli::main(tree_sitter_java::language(), tree_sitter_java::NODE_TYPES)
+}
@@ -1,8 +1,18 @@
 use anyhow::Result;
+use tree_splicer::cli::Dialect;
 
 fn main() -> Result<()> {
-    tree_splicer::cli::main(
-        tree_sitter_javascript::language(),
-        tree_sitter_javascript::NODE_TYPES,
-    )
+    // tree-sitter-javascript parses JSX, too
+    tree_splicer::cli::main_with_dialects(&[
+        Dialect {
+            name: "javascript",
+            language: tree_sitter_javascript::language(),
+            node_types_json_str: tree_sitter_javascript::NODE_TYPES,
+        },
+        Dialect {
+            name: "jsx",
+            language: tree_sitter_javascript::language(),
+            node_types_json_str: tree_sitter_javascript::NODE_TYPES,
+        },
+    ])
 }
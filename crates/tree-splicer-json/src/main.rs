@@ -0,0 +1,23 @@
+use anyhow::Result;
+use tree_splicer::cli::LanguageProfile;
+
+// Data formats have few kinds of nodes, so splices mostly make valid
+// documents. Make more invalid and truncated ones for parsers to reject.
+const PROFILE: LanguageProfile = LanguageProfile {
+    name: "JSON",
+    chaos: Some(20),
+    deletions: Some(15),
+    interesting_kinds: &[("array", 2), ("object", 2)],
+    literal_kinds: &[],
+    identifier_kinds: &[],
+    extensions: &["json"],
+    defaults: &[("literals", "10")],
+};
+
+fn main() -> Result<()> {
+    tree_splicer::cli::main_with_profile(
+        tree_sitter_json::language(),
+        tree_sitter_json::NODE_TYPES,
+        &PROFILE,
+    )
+}
@@ -0,0 +1,76 @@
+//! tree-sitter grammars that tree-splicer supports, by name, e.g., for
+//! mutators that pick the language at runtime. Each grammar is behind a
+//! feature of the same name.
+
+use tree_sitter::Language;
+
+/// The grammar and `node-types.json` of a language, if it's known and its
+/// feature is enabled
+pub fn language(name: &str) -> Option<(Language, &'static str)> {
+    match name {
+        #[cfg(feature = "c")]
+        "c" => Some((tree_sitter_c::language(), tree_sitter_c::NODE_TYPES)),
+        #[cfg(feature = "cpp")]
+        "cpp" => Some((tree_sitter_cpp::language(), tree_sitter_cpp::NODE_TYPES)),
+        #[cfg(feature = "css")]
+        "css" => Some((tree_sitter_css::language(), tree_sitter_css::NODE_TYPES)),
+        #[cfg(feature = "dockerfile")]
+        "dockerfile" => Some((
+            tree_sitter_dockerfile::language(),
+            tree_sitter_dockerfile::NODE_TYPES,
+        )),
+        #[cfg(feature = "go")]
+        "go" => Some((tree_sitter_go::language(), tree_sitter_go::NODE_TYPES)),
+        #[cfg(feature = "html")]
+        "html" => Some((tree_sitter_html::language(), tree_sitter_html::NODE_TYPES)),
+        #[cfg(feature = "java")]
+        "java" => Some((tree_sitter_java::language(), tree_sitter_java::NODE_TYPES)),
+        #[cfg(feature = "javascript")]
+        "javascript" => Some((
+            tree_sitter_javascript::language(),
+            tree_sitter_javascript::NODE_TYPES,
+        )),
+        #[cfg(feature = "json")]
+        "json" => Some((tree_sitter_json::language(), tree_sitter_json::NODE_TYPES)),
+        #[cfg(feature = "lua")]
+        "lua" => Some((tree_sitter_lua::language(), tree_sitter_lua::NODE_TYPES)),
+        #[cfg(feature = "markdown")]
+        "markdown" => Some((tree_sitter_md::language(), tree_sitter_md::NODE_TYPES_BLOCK)),
+        #[cfg(feature = "nix")]
+        "nix" => Some((tree_sitter_nix::language(), tree_sitter_nix::NODE_TYPES)),
+        #[cfg(feature = "python")]
+        "python" => Some((
+            tree_sitter_python::language(),
+            tree_sitter_python::NODE_TYPES,
+        )),
+        #[cfg(feature = "rust")]
+        "rust" => Some((tree_sitter_rust::language(), tree_sitter_rust::NODE_TYPES)),
+        #[cfg(feature = "solidity")]
+        "solidity" => Some((
+            tree_sitter_solidity::language(),
+            tree_sitter_solidity::NODE_TYPES,
+        )),
+        #[cfg(feature = "sql")]
+        "sql" => Some((
+            tree_sitter_sql_bigquery::language(),
+            tree_sitter_sql_bigquery::NODE_TYPES,
+        )),
+        #[cfg(feature = "toml")]
+        "toml" => Some((tree_sitter_toml::language(), tree_sitter_toml::NODE_TYPES)),
+        #[cfg(feature = "typescript")]
+        "typescript" => Some((
+            tree_sitter_typescript::language_typescript(),
+            tree_sitter_typescript::TYPESCRIPT_NODE_TYPES,
+        )),
+        #[cfg(feature = "typescript")]
+        "tsx" => Some((
+            tree_sitter_typescript::language_tsx(),
+            tree_sitter_typescript::TSX_NODE_TYPES,
+        )),
+        #[cfg(feature = "wgsl")]
+        "wgsl" => Some((tree_sitter_wgsl::language(), tree_sitter_wgsl::NODE_TYPES)),
+        #[cfg(feature = "yaml")]
+        "yaml" => Some((tree_sitter_yaml::language(), tree_sitter_yaml::NODE_TYPES)),
+        _ => None,
+    }
+}
@@ -0,0 +1,109 @@
+//! Grammar-based custom mutator for [libFuzzer] and [cargo-fuzz].
+//!
+//! Invoke [`mutator!`] in a fuzz target to define `LLVMFuzzerCustomMutator`
+//! and `LLVMFuzzerCustomCrossOver`:
+//!
+//! ```ignore
+//! #![no_main]
+//! use libfuzzer_sys::fuzz_target;
+//!
+//! tree_splicer_libfuzzer::mutator!(tree_sitter_rust::language(), tree_sitter_rust::NODE_TYPES);
+//!
+//! fuzz_target!(|data: &[u8]| {
+//!     // ...
+//! });
+//! ```
+//!
+//! [libFuzzer]: https://llvm.org/docs/LibFuzzer.html
+//! [cargo-fuzz]: https://github.com/rust-fuzz/cargo-fuzz
+
+use tree_sitter::Language;
+use tree_splicer::node_types::NodeTypes;
+use tree_splicer::splice::Config;
+pub use tree_splicer::Mutator;
+
+/// Create a mutator with the same defaults as the command-line tools.
+///
+/// # Panics
+///
+/// If `node_types_json_str` isn't valid `node-types.json`.
+pub fn new_mutator(language: Language, node_types_json_str: &str) -> Mutator {
+    let node_types = NodeTypes::new(node_types_json_str).expect("Invalid node types");
+    Mutator::new(Config::new(language, node_types))
+}
+
+/// Splice `input`, taking candidates from it and from `donors`, and write the
+/// result to `out`.
+///
+/// Returns the size of the result, which is at most `out.len()`. If no
+/// mutation was possible, copies `input`.
+pub fn mutate_into(
+    mutator: &mut Mutator,
+    input: &[u8],
+    donors: &[&[u8]],
+    out: &mut [u8],
+    seed: u32,
+) -> usize {
+    mutator.reseed(u64::from(seed));
+    mutator.config.max_size = out.len();
+    let mutant = mutator
+        .mutate(input, donors)
+        .unwrap_or_else(|| input.to_vec());
+    let size = mutant.len().min(out.len());
+    out[..size].copy_from_slice(&mutant[..size]);
+    size
+}
+
+/// Define `LLVMFuzzerCustomMutator` and `LLVMFuzzerCustomCrossOver` for a
+/// language, given its [`Language`] and `node-types.json`.
+#[macro_export]
+macro_rules! mutator {
+    ($language:expr, $node_types_json_str:expr) => {
+        fn __tree_splicer_mutator() -> ::std::sync::MutexGuard<'static, $crate::Mutator> {
+            static MUTATOR: ::std::sync::OnceLock<::std::sync::Mutex<$crate::Mutator>> =
+                ::std::sync::OnceLock::new();
+            MUTATOR
+                .get_or_init(|| {
+                    ::std::sync::Mutex::new($crate::new_mutator($language, $node_types_json_str))
+                })
+                .lock()
+                .unwrap()
+        }
+
+        /// # Safety
+        ///
+        /// `data` must be valid for reads of `size` bytes and writes of
+        /// `max_size` bytes.
+        #[no_mangle]
+        pub unsafe extern "C" fn LLVMFuzzerCustomMutator(
+            data: *mut u8,
+            size: usize,
+            max_size: usize,
+            seed: ::std::os::raw::c_uint,
+        ) -> usize {
+            let input = ::std::slice::from_raw_parts(data, size).to_vec();
+            let out = ::std::slice::from_raw_parts_mut(data, max_size);
+            $crate::mutate_into(&mut __tree_splicer_mutator(), &input, &[], out, seed)
+        }
+
+        /// # Safety
+        ///
+        /// `data1` and `data2` must be valid for reads of `size1` and `size2`
+        /// bytes, and `out` for writes of `max_out_size` bytes.
+        #[no_mangle]
+        pub unsafe extern "C" fn LLVMFuzzerCustomCrossOver(
+            data1: *const u8,
+            size1: usize,
+            data2: *const u8,
+            size2: usize,
+            out: *mut u8,
+            max_out_size: usize,
+            seed: ::std::os::raw::c_uint,
+        ) -> usize {
+            let input = ::std::slice::from_raw_parts(data1, size1);
+            let donor = ::std::slice::from_raw_parts(data2, size2);
+            let out = ::std::slice::from_raw_parts_mut(out, max_out_size);
+            $crate::mutate_into(&mut __tree_splicer_mutator(), input, &[donor], out, seed)
+        }
+    };
+}
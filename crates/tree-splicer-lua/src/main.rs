@@ -0,0 +1,5 @@
+use anyhow::Result;
+
+fn main() -> Result<()> {
+    tree_splicer::cli::main(tree_sitter_lua::language(), tree_sitter_lua::NODE_TYPES)
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use tree_sitter::Language;
+use tree_splicer::node_types::NodeTypes;
+use tree_splicer::{Injection, Tag};
+
+/// Code in a fenced code block with one of the given info strings
+fn code_block(
+    language: Language,
+    node_types_json_str: &'static str,
+    names: &'static [&'static str],
+) -> Result<Injection> {
+    Ok(Injection {
+        language,
+        node_types: NodeTypes::new(node_types_json_str)?,
+        parent_kind: "fenced_code_block",
+        kind: "code_fence_content",
+        tag: Some(Tag {
+            kind: "language",
+            names,
+        }),
+    })
+}
+
+fn main() -> Result<()> {
+    let injections = vec![
+        code_block(
+            tree_sitter_css::language(),
+            tree_sitter_css::NODE_TYPES,
+            &["css"],
+        )?,
+        code_block(
+            tree_sitter_html::language(),
+            tree_sitter_html::NODE_TYPES,
+            &["html"],
+        )?,
+        code_block(
+            tree_sitter_javascript::language(),
+            tree_sitter_javascript::NODE_TYPES,
+            &["javascript", "js", "jsx"],
+        )?,
+        code_block(
+            tree_sitter_json::language(),
+            tree_sitter_json::NODE_TYPES,
+            &["json"],
+        )?,
+        code_block(
+            tree_sitter_lua::language(),
+            tree_sitter_lua::NODE_TYPES,
+            &["lua"],
+        )?,
+        code_block(
+            tree_sitter_nix::language(),
+            tree_sitter_nix::NODE_TYPES,
+            &["nix"],
+        )?,
+        code_block(
+            tree_sitter_rust::language(),
+            tree_sitter_rust::NODE_TYPES,
+            &["rust", "rs"],
+        )?,
+        code_block(
+            tree_sitter_toml::language(),
+            tree_sitter_toml::NODE_TYPES,
+            &["toml"],
+        )?,
+        code_block(
+            tree_sitter_typescript::language_typescript(),
+            tree_sitter_typescript::TYPESCRIPT_NODE_TYPES,
+            &["typescript", "ts"],
+        )?,
+        code_block(
+            tree_sitter_typescript::language_tsx(),
+            tree_sitter_typescript::TSX_NODE_TYPES,
+            &["tsx"],
+        )?,
+        code_block(
+            tree_sitter_wgsl::language(),
+            tree_sitter_wgsl::NODE_TYPES,
+            &["wgsl"],
+        )?,
+        code_block(
+            tree_sitter_yaml::language(),
+            tree_sitter_yaml::NODE_TYPES,
+            &["yaml", "yml"],
+        )?,
+    ];
+    tree_splicer::cli::main_with_injections(
+        tree_sitter_md::language(),
+        tree_sitter_md::NODE_TYPES_BLOCK,
+        injections,
+    )
+}
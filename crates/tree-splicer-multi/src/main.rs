@@ -0,0 +1,311 @@
+//! All of the supported languages in one binary.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use tree_sitter::Language;
+use tree_splicer::cli::Command;
+use tree_splicer::node_types::NodeTypes;
+use tree_splicer::{Injection, Tag};
+
+/// Simple grammar-based test case generator for many languages
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    /// Language of the input files; default = guess from their extensions.
+    /// Only files with its extensions are taken from directories, unless
+    /// `--include` is given.
+    #[arg(long, global = true, value_name = "LANGUAGE")]
+    language: Option<String>,
+
+    #[command(flatten)]
+    args: tree_splicer::cli::Args,
+}
+
+struct Grammar {
+    /// Value of `--language`, also a Markdown info string
+    name: &'static str,
+    /// Also Markdown info strings
+    extensions: &'static [&'static str],
+    language: fn() -> Language,
+    node_types_json_str: &'static str,
+}
+
+const GRAMMARS: &[Grammar] = &[
+    #[cfg(feature = "c")]
+    Grammar {
+        name: "c",
+        extensions: &["c", "h"],
+        language: tree_sitter_c::language,
+        node_types_json_str: tree_sitter_c::NODE_TYPES,
+    },
+    #[cfg(feature = "cpp")]
+    Grammar {
+        name: "cpp",
+        extensions: &["cc", "cpp", "cxx", "c++", "hh", "hpp", "hxx"],
+        language: tree_sitter_cpp::language,
+        node_types_json_str: tree_sitter_cpp::NODE_TYPES,
+    },
+    #[cfg(feature = "css")]
+    Grammar {
+        name: "css",
+        extensions: &["css"],
+        language: tree_sitter_css::language,
+        node_types_json_str: tree_sitter_css::NODE_TYPES,
+    },
+    #[cfg(feature = "dockerfile")]
+    Grammar {
+        name: "dockerfile",
+        extensions: &["dockerfile"],
+        language: tree_sitter_dockerfile::language,
+        node_types_json_str: tree_sitter_dockerfile::NODE_TYPES,
+    },
+    #[cfg(feature = "go")]
+    Grammar {
+        name: "go",
+        extensions: &["go"],
+        language: tree_sitter_go::language,
+        node_types_json_str: tree_sitter_go::NODE_TYPES,
+    },
+    #[cfg(feature = "html")]
+    Grammar {
+        name: "html",
+        extensions: &["html", "htm"],
+        language: tree_sitter_html::language,
+        node_types_json_str: tree_sitter_html::NODE_TYPES,
+    },
+    #[cfg(feature = "java")]
+    Grammar {
+        name: "java",
+        extensions: &["java"],
+        language: tree_sitter_java::language,
+        node_types_json_str: tree_sitter_java::NODE_TYPES,
+    },
+    #[cfg(feature = "javascript")]
+    Grammar {
+        name: "javascript",
+        extensions: &["js", "cjs", "mjs", "jsx"],
+        language: tree_sitter_javascript::language,
+        node_types_json_str: tree_sitter_javascript::NODE_TYPES,
+    },
+    #[cfg(feature = "json")]
+    Grammar {
+        name: "json",
+        extensions: &["json"],
+        language: tree_sitter_json::language,
+        node_types_json_str: tree_sitter_json::NODE_TYPES,
+    },
+    #[cfg(feature = "lua")]
+    Grammar {
+        name: "lua",
+        extensions: &["lua"],
+        language: tree_sitter_lua::language,
+        node_types_json_str: tree_sitter_lua::NODE_TYPES,
+    },
+    #[cfg(feature = "markdown")]
+    Grammar {
+        name: "markdown",
+        extensions: &["md", "markdown"],
+        language: tree_sitter_md::language,
+        node_types_json_str: tree_sitter_md::NODE_TYPES_BLOCK,
+    },
+    #[cfg(feature = "nix")]
+    Grammar {
+        name: "nix",
+        extensions: &["nix"],
+        language: tree_sitter_nix::language,
+        node_types_json_str: tree_sitter_nix::NODE_TYPES,
+    },
+    #[cfg(feature = "python")]
+    Grammar {
+        name: "python",
+        extensions: &["py", "pyi"],
+        language: tree_sitter_python::language,
+        node_types_json_str: tree_sitter_python::NODE_TYPES,
+    },
+    #[cfg(feature = "rust")]
+    Grammar {
+        name: "rust",
+        extensions: &["rs"],
+        language: tree_sitter_rust::language,
+        node_types_json_str: tree_sitter_rust::NODE_TYPES,
+    },
+    #[cfg(feature = "solidity")]
+    Grammar {
+        name: "solidity",
+        extensions: &["sol"],
+        language: tree_sitter_solidity::language,
+        node_types_json_str: tree_sitter_solidity::NODE_TYPES,
+    },
+    #[cfg(feature = "sql")]
+    Grammar {
+        name: "sql",
+        extensions: &["sql"],
+        language: tree_sitter_sql_bigquery::language,
+        node_types_json_str: tree_sitter_sql_bigquery::NODE_TYPES,
+    },
+    #[cfg(feature = "toml")]
+    Grammar {
+        name: "toml",
+        extensions: &["toml"],
+        language: tree_sitter_toml::language,
+        node_types_json_str: tree_sitter_toml::NODE_TYPES,
+    },
+    #[cfg(feature = "typescript")]
+    Grammar {
+        name: "typescript",
+        extensions: &["ts", "cts", "mts"],
+        language: tree_sitter_typescript::language_typescript,
+        node_types_json_str: tree_sitter_typescript::TYPESCRIPT_NODE_TYPES,
+    },
+    #[cfg(feature = "typescript")]
+    Grammar {
+        name: "tsx",
+        extensions: &["tsx"],
+        language: tree_sitter_typescript::language_tsx,
+        node_types_json_str: tree_sitter_typescript::TSX_NODE_TYPES,
+    },
+    #[cfg(feature = "wgsl")]
+    Grammar {
+        name: "wgsl",
+        extensions: &["wgsl"],
+        language: tree_sitter_wgsl::language,
+        node_types_json_str: tree_sitter_wgsl::NODE_TYPES,
+    },
+    #[cfg(feature = "yaml")]
+    Grammar {
+        name: "yaml",
+        extensions: &["yaml", "yml"],
+        language: tree_sitter_yaml::language,
+        node_types_json_str: tree_sitter_yaml::NODE_TYPES,
+    },
+];
+
+fn grammar(name: &str) -> Option<&'static Grammar> {
+    GRAMMARS.iter().find(|g| g.name == name)
+}
+
+fn detect(path: &Path) -> Option<&'static Grammar> {
+    let file_name = path.file_name()?.to_str()?;
+    if file_name.starts_with("Dockerfile") {
+        return grammar("dockerfile");
+    }
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    GRAMMARS
+        .iter()
+        .find(|g| g.extensions.contains(&extension.as_str()))
+}
+
+/// The grammar for all of `files` with known extensions, which must agree
+fn detect_all(files: &[String]) -> Result<&'static Grammar> {
+    let mut found: Option<&'static Grammar> = None;
+    for file in files.iter().filter(|f| *f != "-") {
+        let Some(grammar) = detect(Path::new(file)) else {
+            continue;
+        };
+        match found {
+            Some(other) if other.name != grammar.name => bail!(
+                "Input files are in different languages ({} and {}), use --language",
+                other.name,
+                grammar.name
+            ),
+            _ => found = Some(grammar),
+        }
+    }
+    found.with_context(|| match files.iter().find(|f| *f != "-") {
+        Some(file) => format!("Couldn't guess the language of {}, use --language", file),
+        None if files.is_empty() => {
+            "Couldn't guess the language without input files, use --language".to_string()
+        }
+        None => "Couldn't guess the language of stdin, use --language".to_string(),
+    })
+}
+
+/// Patterns that match files in the language, see [`detect`]
+fn include(grammar: &Grammar) -> Result<Vec<glob::Pattern>> {
+    let mut patterns: Vec<_> = grammar
+        .extensions
+        .iter()
+        .map(|e| format!("*.{}", e))
+        .collect();
+    if grammar.name == "dockerfile" {
+        patterns.push("Dockerfile*".to_string());
+    }
+    patterns
+        .iter()
+        .map(|p| glob::Pattern::new(p).context("Invalid pattern"))
+        .collect()
+}
+
+fn injections(name: &str) -> Result<Vec<Injection>> {
+    let mut injections = Vec::new();
+    match name {
+        "html" => {
+            let embedded = [("css", "style_element"), ("javascript", "script_element")];
+            for (name, parent_kind) in embedded {
+                if let Some(g) = grammar(name) {
+                    injections.push(Injection {
+                        language: (g.language)(),
+                        node_types: NodeTypes::new(g.node_types_json_str)?,
+                        parent_kind,
+                        kind: "raw_text",
+                        tag: None,
+                    });
+                }
+            }
+        }
+        "markdown" => {
+            for g in GRAMMARS.iter().filter(|g| g.name != "markdown") {
+                let names = std::iter::once(g.name).chain(g.extensions.iter().copied());
+                injections.push(Injection {
+                    language: (g.language)(),
+                    node_types: NodeTypes::new(g.node_types_json_str)?,
+                    parent_kind: "fenced_code_block",
+                    kind: "code_fence_content",
+                    tag: Some(Tag {
+                        kind: "language",
+                        names: Vec::leak(names.collect()),
+                    }),
+                });
+            }
+        }
+        _ => (),
+    }
+    Ok(injections)
+}
+
+fn main() -> Result<()> {
+    let cli: Cli = tree_splicer::cli::parse_args()?;
+    let mut command = cli.args.into_command();
+    let named = match &cli.language {
+        Some(name) => Some(grammar(name).with_context(|| {
+            let names: Vec<_> = GRAMMARS.iter().map(|g| g.name).collect();
+            format!(
+                "Unknown or disabled language {}, expected one of: {}",
+                name,
+                names.join(", ")
+            )
+        })?),
+        None => None,
+    };
+    // Only traces of preprocessed inputs need the language
+    if let Command::Replay(replay) = &command {
+        return replay.run(named.map(|g| (g.language)()));
+    }
+    let grammar = match named {
+        Some(grammar) => grammar,
+        None => detect_all(&command.input_files()?)?,
+    };
+    if let Some(common) = command.common_mut() {
+        if common.include.is_empty() {
+            common.include = include(grammar)?;
+        }
+    }
+    tree_splicer::cli::run_command(
+        command,
+        (grammar.language)(),
+        grammar.node_types_json_str,
+        injections(grammar.name)?,
+    )
+}
@@ -0,0 +1,5 @@
+use anyhow::Result;
+
+fn main() -> Result<()> {
+    tree_splicer::cli::main(tree_sitter_nix::language(), tree_sitter_nix::NODE_TYPES)
+}
@@ -0,0 +1,15 @@
+use anyhow::Result;
+use tree_sitter::Language;
+
+/// tree-sitter-proto is built for later versions of tree-sitter, which take the
+/// function that makes its parser rather than a [`Language`]
+fn language() -> Language {
+    let language = tree_sitter_proto::LANGUAGE.into_raw();
+    // SAFETY: A `Language` is a pointer to the `TSLanguage` this returns,
+    // and its ABI version (14) is one that tree-sitter 0.20 can load
+    unsafe { std::mem::transmute(language()) }
+}
+
+fn main() -> Result<()> {
+    tree_splicer::cli::main(language(), tree_sitter_proto::NODE_TYPES)
+}
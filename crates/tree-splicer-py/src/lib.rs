@@ -0,0 +1,200 @@
+//! Python bindings, for fuzzing harnesses written in Python (e.g., with
+//! [Atheris]) that would otherwise start a process per mutation.
+//!
+//! ```python
+//! import tree_splicer
+//!
+//! config = tree_splicer.Config("rust", seed=0, chaos=10)
+//! splicer = tree_splicer.Splicer(config, donors=[open("lib.rs", "rb").read()])
+//! mutant = splicer.mutate(b"fn main() { let x = 1; }")
+//! ```
+//!
+//! Build and install it with [maturin], e.g., `maturin develop` in this
+//! directory.
+//!
+//! [Atheris]: https://github.com/google/atheris
+//! [maturin]: https://www.maturin.rs/
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use tree_splicer::splice::{Budget, OperatorWeights};
+use tree_splicer::Mutator;
+use tree_splicer_languages::language;
+
+/// What a grammar's `node-types.json` says about each node kind
+#[pyclass(name = "NodeTypes", frozen)]
+#[derive(Clone)]
+struct NodeTypes(tree_splicer::NodeTypes);
+
+#[pymethods]
+impl NodeTypes {
+    /// Parse the contents of `node-types.json`
+    #[new]
+    fn new(json: &str) -> PyResult<Self> {
+        tree_splicer::NodeTypes::new(json)
+            .map(NodeTypes)
+            .map_err(|e| PyValueError::new_err(format!("Invalid node types: {}", e)))
+    }
+
+    /// The node types of a built-in language, e.g., `"rust"`
+    #[staticmethod]
+    fn for_language(name: &str) -> PyResult<Self> {
+        let (_, json) = language(name)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown language {}", name)))?;
+        Self::new(json)
+    }
+}
+
+/// How to mutate, with the same defaults as the command-line tools.
+/// Percentages are of mutations; the rest are splices.
+#[pyclass(name = "Config", frozen)]
+struct Config {
+    language: String,
+    config: tree_splicer::Config,
+}
+
+#[pymethods]
+impl Config {
+    #[new]
+    #[pyo3(signature = (
+        language,
+        node_types = None,
+        *,
+        seed = 0,
+        chaos = 5,
+        deletions = 5,
+        duplications = 0,
+        havoc = 0,
+        insertions = 0,
+        intra = 0,
+        literals = 0,
+        renames = 0,
+        mutations = 16,
+        max_size = 1048576,
+        budget = None,
+        only_kinds = Vec::new(),
+        skip_kinds = Vec::new(),
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        language: String,
+        node_types: Option<NodeTypes>,
+        seed: u64,
+        chaos: u8,
+        deletions: u8,
+        duplications: u8,
+        havoc: u8,
+        insertions: u8,
+        intra: u8,
+        literals: u8,
+        renames: u8,
+        mutations: usize,
+        max_size: usize,
+        budget: Option<&str>,
+        only_kinds: Vec<String>,
+        skip_kinds: Vec<String>,
+    ) -> PyResult<Self> {
+        let (grammar, json) = self::language(&language)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown language {}", language)))?;
+        let node_types = match node_types {
+            Some(node_types) => node_types,
+            None => NodeTypes::new(json)?,
+        };
+        let budget = budget
+            .map(str::parse::<Budget>)
+            .transpose()
+            .map_err(PyValueError::new_err)?;
+        let config = tree_splicer::Config {
+            budget,
+            chaos,
+            inter_splices: mutations,
+            max_size,
+            only_kinds: only_kinds.into_iter().collect(),
+            operators: OperatorWeights {
+                splice: 0,
+                delete: deletions.into(),
+                duplicate: duplications.into(),
+                insert: insertions.into(),
+                swap: 0,
+                rename: renames.into(),
+                literal: literals.into(),
+                havoc: havoc.into(),
+                intra: intra.into(),
+                rule: 0,
+                template: 0,
+                nest: 0,
+                operator: 0,
+            }
+            .splice_rest(),
+            seed,
+            skip_kinds: skip_kinds.into_iter().collect(),
+            ..tree_splicer::Config::new(grammar, node_types.0)
+        };
+        Ok(Config { language, config })
+    }
+
+    #[getter]
+    fn language(&self) -> &str {
+        &self.language
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.config.seed
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Config({:?}, seed={}, chaos={}, mutations={})",
+            self.language, self.config.seed, self.config.chaos, self.config.inter_splices
+        )
+    }
+}
+
+/// Splices inputs one at a time, taking candidates from the input itself
+/// and from the donors
+#[pyclass(name = "Splicer")]
+struct Splicer {
+    mutator: Mutator,
+    donors: Vec<Vec<u8>>,
+}
+
+#[pymethods]
+impl Splicer {
+    #[new]
+    #[pyo3(signature = (config, donors = Vec::new()))]
+    fn new(config: &Config, donors: Vec<Vec<u8>>) -> Self {
+        Splicer {
+            mutator: Mutator::new(config.config.clone()),
+            donors,
+        }
+    }
+
+    /// Also take splice candidates from `donor`
+    fn add_donor(&mut self, donor: Vec<u8>) {
+        self.donors.push(donor);
+    }
+
+    /// Reset the random number generator, e.g., to a seed from the fuzzer
+    fn reseed(&mut self, seed: u64) {
+        self.mutator.reseed(seed);
+    }
+
+    /// Splice `data`. Returns `data` unchanged if no mutation was possible.
+    fn mutate<'py>(&mut self, py: Python<'py>, data: &[u8]) -> Bound<'py, PyBytes> {
+        let donors: Vec<_> = self.donors.iter().map(Vec::as_slice).collect();
+        let mutator = &mut self.mutator;
+        let mutant = py.allow_threads(|| mutator.mutate(data, &donors));
+        PyBytes::new(py, mutant.as_deref().unwrap_or(data))
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "tree_splicer")]
+fn tree_splicer_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Config>()?;
+    m.add_class::<NodeTypes>()?;
+    m.add_class::<Splicer>()?;
+    Ok(())
+}
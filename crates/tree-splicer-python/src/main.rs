@@ -0,0 +1,23 @@
+use anyhow::Result;
+use tree_splicer::cli::LanguageProfile;
+
+// Deleting the only statement of a block is an IndentationError, and so is
+// splicing a block without re-indenting it
+const PROFILE: LanguageProfile = LanguageProfile {
+    name: "Python",
+    chaos: None,
+    deletions: Some(2),
+    interesting_kinds: &[],
+    literal_kinds: &[],
+    identifier_kinds: &[],
+    extensions: &["py", "pyi"],
+    defaults: &[("reindent", "true")],
+};
+
+fn main() -> Result<()> {
+    tree_splicer::cli::main_with_profile(
+        tree_sitter_python::language(),
+        tree_sitter_python::NODE_TYPES,
+        &PROFILE,
+    )
+}
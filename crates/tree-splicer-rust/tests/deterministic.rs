@@ -0,0 +1,58 @@
+//! The same invocation must always produce the same tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SEEDS: &[(&str, &str)] = &[
+    (
+        "a.rs",
+        "fn even(x: usize) -> bool { if x % 2 == 0 { return true; } else { return false; } }\n",
+    ),
+    (
+        "b.rs",
+        "fn main() { let argc = std::env::args().len(); println!(\"{}\", argc); }\n",
+    ),
+    (
+        "c.rs",
+        "struct S { a: u8, b: Vec<u8> }\nimpl S { fn f(&self) -> u8 { self.a + 1 } }\n",
+    ),
+    ("d.rs", "use std::env;\nconst X: [i32; 3] = [1, 2, 3];\n"),
+];
+
+fn generate(dir: &Path, out: &str) -> Vec<(String, String)> {
+    let status = Command::new(env!("CARGO_BIN_EXE_tree-splicer-rust"))
+        .current_dir(dir)
+        .args(["--seed", "7", "--tests", "32", "--output", out])
+        .args(SEEDS.iter().map(|(name, _)| name))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let mut tests: Vec<_> = fs::read_dir(dir.join(out))
+        .unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (
+                e.file_name().to_string_lossy().into_owned(),
+                String::from_utf8_lossy(&fs::read(e.path()).unwrap()).into_owned(),
+            )
+        })
+        .collect();
+    tests.sort();
+    tests
+}
+
+#[test]
+fn deterministic() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("deterministic");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in SEEDS {
+        fs::write(dir.join(name), text).unwrap();
+    }
+    let first = generate(&dir, "out0");
+    assert_eq!(first.len(), 32);
+    for i in 1..4 {
+        assert_eq!(first, generate(&dir, &format!("out{}", i)));
+    }
+}
@@ -0,0 +1,68 @@
+//! `--max-input-bytes` must load the same sample of the inputs for the same
+//! seed, and fail if no input fits.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Inputs of 30 bytes or more, so that at most 3 fit in 100 bytes
+const INPUTS: usize = 8;
+
+fn setup(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for i in 0..INPUTS {
+        let text = format!("fn f{}(x: u32) -> u32 {{ x + {} }}\n", i, i);
+        fs::write(dir.join(format!("in{}.rs", i)), text).unwrap();
+    }
+    dir
+}
+
+fn generate(dir: &Path, out: &str, max: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tree-splicer-rust"))
+        .current_dir(dir)
+        .args(["--seed", "3", "--tests", "24", "--output", out])
+        .args(["--max-input-bytes", max])
+        .args((0..INPUTS).map(|i| format!("in{}.rs", i)))
+        .output()
+        .unwrap()
+}
+
+/// The inputs that the tests in `out` were made from, by their names
+fn sources(dir: &Path, out: &str) -> BTreeSet<String> {
+    fs::read_dir(dir.join(out))
+        .unwrap()
+        .map(|e| {
+            let name = e.unwrap().file_name().to_string_lossy().into_owned();
+            name.split('.').next().unwrap().to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn deterministic_sample() {
+    let dir = setup("max_input_bytes_sample");
+    assert!(generate(&dir, "out0", "100").status.success());
+    let first = sources(&dir, "out0");
+    assert!(!first.is_empty() && first.len() <= 3, "{:?}", first);
+    for i in 1..3 {
+        let out = format!("out{}", i);
+        assert!(generate(&dir, &out, "100").status.success());
+        assert_eq!(first, sources(&dir, &out));
+    }
+}
+
+#[test]
+fn no_input_fits() {
+    let dir = setup("max_input_bytes_none");
+    let output = generate(&dir, "out", "10");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("No input file is smaller than --max-input-bytes"),
+        "{}",
+        stderr
+    );
+}
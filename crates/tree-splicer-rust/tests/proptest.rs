@@ -0,0 +1,29 @@
+//! `SpliceStrategy` must only make programs that parse.
+
+use proptest::prelude::*;
+use tree_splicer::strategy::SpliceStrategy;
+use tree_splicer::{Config, NodeTypes};
+
+const CORPUS: &[&str] = &[
+    "fn even(x: usize) -> bool { if x % 2 == 0 { return true; } else { return false; } }\n",
+    "fn main() { let argc = std::env::args().len(); println!(\"{}\", argc); }\n",
+    "struct S { a: u8, b: Vec<u8> }\nimpl S { fn f(&self) -> u8 { self.a + 1 } }\n",
+];
+
+fn strategy() -> SpliceStrategy {
+    let node_types = NodeTypes::new(tree_sitter_rust::NODE_TYPES).unwrap();
+    SpliceStrategy::new(
+        Config::new(tree_sitter_rust::language(), node_types),
+        CORPUS,
+    )
+}
+
+proptest! {
+    #[test]
+    fn parses(src in strategy()) {
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(tree_sitter_rust::language()).unwrap();
+        let tree = parser.parse(&src, None).unwrap();
+        prop_assert!(!tree.root_node().has_error(), "{}", src);
+    }
+}
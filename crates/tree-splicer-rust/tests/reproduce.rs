@@ -0,0 +1,62 @@
+//! Test number `i` must be the same whether it's made after the tests before
+//! it or alone, with `Splicer::resume`, as `--reproduce SEED:INDEX` does.
+
+use std::collections::HashMap;
+
+use tree_splicer::splice::{parse, test_seed};
+use tree_splicer::{Config, NodeTypes, Splicer};
+
+const CORPUS: &[(&str, &str)] = &[
+    (
+        "a.rs",
+        "fn even(x: usize) -> bool { if x % 2 == 0 { return true; } else { return false; } }\n",
+    ),
+    (
+        "b.rs",
+        "fn main() { let argc = std::env::args().len(); println!(\"{}\", argc); }\n",
+    ),
+    (
+        "c.rs",
+        "struct S { a: u8, b: Vec<u8> }\nimpl S { fn f(&self) -> u8 { self.a + 1 } }\n",
+    ),
+];
+
+const TESTS: usize = 24;
+
+fn config(seed: u64) -> Config {
+    let node_types = NodeTypes::new(tree_sitter_rust::NODE_TYPES).unwrap();
+    Config {
+        seed,
+        ..Config::new(tree_sitter_rust::language(), node_types)
+    }
+}
+
+#[test]
+fn resume() {
+    let files: HashMap<_, _> = CORPUS
+        .iter()
+        .map(|(name, text)| {
+            let tree = parse(tree_sitter_rust::language(), text.as_bytes());
+            (name.to_string(), (text.as_bytes().to_vec(), tree))
+        })
+        .collect();
+    for seed in [0, 7] {
+        let sequential: Vec<_> = Splicer::new(config(seed), &files).take(TESTS).collect();
+        assert_eq!(sequential.len(), TESTS);
+        for (i, test) in sequential.iter().enumerate() {
+            let mut splicer = Splicer::new(config(seed), &files).resume(i);
+            assert_eq!(
+                splicer.next().as_ref(),
+                Some(test),
+                "seed {}, test {}",
+                seed,
+                i
+            );
+        }
+        // Each test has its own random numbers
+        let mut seeds: Vec<_> = (0..TESTS).map(|i| test_seed(seed, i)).collect();
+        seeds.sort_unstable();
+        seeds.dedup();
+        assert_eq!(seeds.len(), TESTS);
+    }
+}
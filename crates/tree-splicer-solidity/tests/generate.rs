@@ -0,0 +1,70 @@
+//! Tests spliced from a few Solidity seeds mix the seeds' contracts, state
+//! variables, and statements, and some of them are still valid Solidity.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const SEEDS: &[(&str, &str)] = &[
+    (
+        "a.sol",
+        "contract A { uint x; function f(uint y) public returns (uint) { return x + y; } }\n",
+    ),
+    (
+        "b.sol",
+        "contract B { mapping(address => uint) m; function g() public { m[msg.sender] = 1; } }\n",
+    ),
+];
+
+/// The tests made from `SEEDS`, by name
+fn generate(name: &str) -> Vec<(String, String)> {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in SEEDS {
+        fs::write(dir.join(name), text).unwrap();
+    }
+    let status = Command::new(env!("CARGO_BIN_EXE_tree-splicer-solidity"))
+        .current_dir(&dir)
+        .args(["--seed", "7", "--tests", "32", "--output", "out"])
+        .args(SEEDS.iter().map(|(name, _)| name))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let mut tests: Vec<_> = fs::read_dir(dir.join("out"))
+        .unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (
+                e.file_name().to_string_lossy().into_owned(),
+                fs::read_to_string(e.path()).unwrap(),
+            )
+        })
+        .collect();
+    tests.sort();
+    tests
+}
+
+#[test]
+fn splices_between_seeds() {
+    let tests = generate("solidity-splice");
+    assert_eq!(tests.len(), 32);
+    // Contract `A` with a mapping or statement of contract `B`
+    assert!(tests.iter().any(|(name, test)| name.starts_with("a.")
+        && test.contains("contract A")
+        && (test.contains("mapping(") || test.contains("msg.sender"))));
+}
+
+#[test]
+fn some_tests_are_valid() {
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(tree_sitter_solidity::language())
+        .unwrap();
+    let valid = generate("solidity-valid")
+        .into_iter()
+        .filter(|(_, test)| SEEDS.iter().all(|(_, seed)| test != seed))
+        .filter(|(_, test)| !parser.parse(test, None).unwrap().root_node().has_error())
+        .count();
+    assert!(valid > 0);
+}
@@ -0,0 +1,12 @@
+use anyhow::Result;
+use tree_splicer::cli::Dialect;
+
+fn main() -> Result<()> {
+    // Other tree-sitter SQL grammars (e.g., tree-sitter-sequel for PostgreSQL
+    // and MySQL) need a newer tree-sitter. Add them here when it's updated.
+    tree_splicer::cli::main_with_dialects(&[Dialect {
+        name: "bigquery",
+        language: tree_sitter_sql_bigquery::language(),
+        node_types_json_str: tree_sitter_sql_bigquery::NODE_TYPES,
+    }])
+}
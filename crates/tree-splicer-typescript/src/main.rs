@@ -1,8 +1,17 @@
 use anyhow::Result;
+use tree_splicer::cli::Dialect;
 
 fn main() -> Result<()> {
-    tree_splicer::cli::main(
-        tree_sitter_typescript::language_typescript(),
-        tree_sitter_typescript::TYPESCRIPT_NODE_TYPES,
-    )
+    tree_splicer::cli::main_with_dialects(&[
+        Dialect {
+            name: "typescript",
+            language: tree_sitter_typescript::language_typescript(),
+            node_types_json_str: tree_sitter_typescript::TYPESCRIPT_NODE_TYPES,
+        },
+        Dialect {
+            name: "tsx",
+            language: tree_sitter_typescript::language_tsx(),
+            node_types_json_str: tree_sitter_typescript::TSX_NODE_TYPES,
+        },
+    ])
 }
@@ -0,0 +1,129 @@
+//! WebAssembly build with a JavaScript API, e.g., for fuzzing in the browser
+//! or from Node.js.
+//!
+//! ```js
+//! import init, { splice } from "./pkg/tree_splicer_wasm.js";
+//!
+//! await init();
+//! const test = splice(["fn main() { let x = 1; }"], { language: "rust", seed: 1 });
+//! ```
+//!
+//! Build it with [wasm-pack], and a C compiler that targets WebAssembly for
+//! the grammars, see the README.
+//!
+//! [wasm-pack]: https://rustwasm.github.io/wasm-pack/
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tree_splicer::node_types::NodeTypes;
+use tree_splicer::splice::{parse, Budget, Config, OperatorWeights, Splicer};
+use tree_splicer_languages::language;
+use wasm_bindgen::prelude::*;
+
+/// The options of [`splice`], with the same defaults as the command-line
+/// tools. Percentages are of mutations; the rest are splices.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+struct Options {
+    /// E.g., `"rust"`
+    language: String,
+    seed: u64,
+    chaos: u8,
+    deletions: u8,
+    duplications: u8,
+    havoc: u8,
+    insertions: u8,
+    intra: u8,
+    literals: u8,
+    renames: u8,
+    mutations: usize,
+    max_size: usize,
+    /// E.g., `"200"` or `"5%"`
+    budget: Option<String>,
+    only_kinds: Vec<String>,
+    skip_kinds: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            language: String::new(),
+            seed: 0,
+            chaos: 5,
+            deletions: 5,
+            duplications: 0,
+            havoc: 0,
+            insertions: 0,
+            intra: 0,
+            literals: 0,
+            renames: 0,
+            mutations: 16,
+            max_size: 1048576,
+            budget: None,
+            only_kinds: Vec::new(),
+            skip_kinds: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    fn config(self) -> Result<Config, String> {
+        let (grammar, node_types_json_str) = language(&self.language)
+            .ok_or_else(|| format!("Unknown or disabled language {:?}", self.language))?;
+        let node_types = NodeTypes::new(node_types_json_str).map_err(|e| e.to_string())?;
+        Ok(Config {
+            budget: self
+                .budget
+                .as_deref()
+                .map(str::parse::<Budget>)
+                .transpose()?,
+            chaos: self.chaos,
+            inter_splices: self.mutations,
+            max_size: self.max_size,
+            only_kinds: self.only_kinds.into_iter().collect(),
+            operators: OperatorWeights {
+                splice: 0,
+                delete: self.deletions.into(),
+                duplicate: self.duplications.into(),
+                insert: self.insertions.into(),
+                swap: 0,
+                rename: self.renames.into(),
+                literal: self.literals.into(),
+                havoc: self.havoc.into(),
+                intra: self.intra.into(),
+                rule: 0,
+                template: 0,
+                nest: 0,
+                operator: 0,
+            }
+            .splice_rest(),
+            seed: self.seed,
+            skip_kinds: self.skip_kinds.into_iter().collect(),
+            ..Config::new(grammar, node_types)
+        })
+    }
+}
+
+/// Make one test by splicing `sources` together. Throws if `options` are
+/// invalid or no mutation was possible.
+#[wasm_bindgen]
+pub fn splice(sources: Vec<String>, options: JsValue) -> Result<String, JsError> {
+    let options: Options = serde_wasm_bindgen::from_value(options)?;
+    let config = options.config().map_err(|e| JsError::new(&e))?;
+    if sources.is_empty() {
+        return Err(JsError::new("No sources"));
+    }
+    let files: HashMap<_, _> = sources
+        .into_iter()
+        .enumerate()
+        .map(|(i, source)| {
+            let tree = parse(config.language, source.as_bytes());
+            (i.to_string(), (source.into_bytes(), tree))
+        })
+        .collect();
+    let test = Splicer::new(config, &files)
+        .next()
+        .ok_or_else(|| JsError::new("No mutation was possible"))?;
+    Ok(String::from_utf8_lossy(&test).into_owned())
+}
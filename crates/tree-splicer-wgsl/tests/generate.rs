@@ -0,0 +1,68 @@
+//! Tests spliced from a few WGSL seeds mix the seeds' declarations,
+//! attributes, and types, and some of them are still valid WGSL.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const SEEDS: &[(&str, &str)] = &[
+    (
+        "a.wgsl",
+        "fn f(x: f32) -> f32 { return x * 2.0; }\n",
+    ),
+    (
+        "b.wgsl",
+        "@fragment\nfn main() -> @location(0) vec4<f32> { let c = vec4<f32>(1.0, 0.0, 0.0, 1.0); return c; }\n",
+    ),
+];
+
+/// The tests made from `SEEDS`, by name
+fn generate(name: &str) -> Vec<(String, String)> {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (name, text) in SEEDS {
+        fs::write(dir.join(name), text).unwrap();
+    }
+    let status = Command::new(env!("CARGO_BIN_EXE_tree-splicer-wgsl"))
+        .current_dir(&dir)
+        .args(["--seed", "7", "--tests", "32", "--output", "out"])
+        .args(SEEDS.iter().map(|(name, _)| name))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let mut tests: Vec<_> = fs::read_dir(dir.join("out"))
+        .unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (
+                e.file_name().to_string_lossy().into_owned(),
+                fs::read_to_string(e.path()).unwrap(),
+            )
+        })
+        .collect();
+    tests.sort();
+    tests
+}
+
+#[test]
+fn splices_between_seeds() {
+    let tests = generate("wgsl-splice");
+    assert_eq!(tests.len(), 32);
+    // A function of `a.wgsl` that got a vector type or attribute of `b.wgsl`
+    assert!(tests.iter().any(|(name, test)| name.starts_with("a.")
+        && test.contains("fn f(")
+        && (test.contains("vec4<f32>") || test.contains('@'))));
+}
+
+#[test]
+fn some_tests_are_valid() {
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(tree_sitter_wgsl::language()).unwrap();
+    let valid = generate("wgsl-valid")
+        .into_iter()
+        .filter(|(_, test)| SEEDS.iter().all(|(_, seed)| test != seed))
+        .filter(|(_, test)| !parser.parse(test, None).unwrap().root_node().has_error())
+        .count();
+    assert!(valid > 0);
+}
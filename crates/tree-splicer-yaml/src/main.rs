@@ -0,0 +1,32 @@
+use anyhow::Result;
+use tree_splicer::cli::LanguageProfile;
+
+// Data formats have few kinds of nodes, so splices mostly make valid
+// documents. Make more invalid and truncated ones for parsers to reject.
+const PROFILE: LanguageProfile = LanguageProfile {
+    name: "YAML",
+    chaos: Some(20),
+    deletions: Some(15),
+    interesting_kinds: &[
+        ("block_mapping", 2),
+        ("block_sequence", 2),
+        ("flow_mapping", 2),
+        ("flow_sequence", 2),
+    ],
+    literal_kinds: &[
+        "double_quote_scalar",
+        "single_quote_scalar",
+        "string_scalar",
+    ],
+    identifier_kinds: &[],
+    extensions: &["yaml", "yml"],
+    defaults: &[("literals", "10"), ("reindent", "true")],
+};
+
+fn main() -> Result<()> {
+    tree_splicer::cli::main_with_profile(
+        tree_sitter_yaml::language(),
+        tree_sitter_yaml::NODE_TYPES,
+        &PROFILE,
+    )
+}
@@ -0,0 +1,189 @@
+//! Make a [`Splicer`] without spelling out a whole [`Config`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tree_sitter::{Language, Tree};
+
+use crate::node_types::NodeTypes;
+use crate::rules::Rules;
+use crate::splice::{Budget, Config, Focus, OperatorWeights, SizeDistribution, Splicer};
+use crate::templates::Templates;
+use crate::type_hint::TypeHint;
+
+/// Builds a [`Splicer`], starting from the same defaults as the command-line
+/// tools. Each method sets the [`Config`] field of the same name.
+#[derive(Clone, Debug)]
+pub struct SplicerBuilder<'a> {
+    config: Config,
+    #[allow(clippy::type_complexity)]
+    donors: Vec<(&'a HashMap<String, (Vec<u8>, Tree)>, u32)>,
+}
+
+impl<'a> SplicerBuilder<'a> {
+    pub fn new(language: Language, node_types: NodeTypes) -> Self {
+        Self::from_config(Config::new(language, node_types))
+    }
+
+    pub fn from_config(config: Config) -> Self {
+        SplicerBuilder {
+            config,
+            donors: Vec::new(),
+        }
+    }
+
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.config.budget = Some(budget);
+        self
+    }
+
+    pub fn chaos(mut self, percent: u8) -> Self {
+        self.config.chaos = percent;
+        self
+    }
+
+    pub fn clusters(mut self, clusters: usize) -> Self {
+        self.config.clusters = clusters;
+        self
+    }
+
+    pub fn crossover(mut self, percent: u8) -> Self {
+        self.config.crossover = percent;
+        self
+    }
+
+    /// Also take splice candidates from `donors`, `weight` times as often
+    /// as from the files, see [`Splicer::new_with_weighted_donors`]. May be
+    /// called several times.
+    pub fn donors(mut self, donors: &'a HashMap<String, (Vec<u8>, Tree)>, weight: u32) -> Self {
+        self.donors.push((donors, weight));
+        self
+    }
+
+    pub fn focus(mut self, focus: Focus) -> Self {
+        self.config.focus = Some(focus);
+        self
+    }
+
+    pub fn harvest(mut self, harvest: bool) -> Self {
+        self.config.harvest = harvest;
+        self
+    }
+
+    pub fn identifier_kinds(mut self, kinds: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.identifier_kinds = kinds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn inter_splices(mut self, splices: usize) -> Self {
+        self.config.inter_splices = splices;
+        self
+    }
+
+    /// Make nodes of `kind` `weight` times as likely to be mutated. May be
+    /// called several times.
+    pub fn kind_weight(mut self, kind: impl Into<String>, weight: u32) -> Self {
+        self.config.kind_weights.insert(kind.into(), weight);
+        self
+    }
+
+    pub fn literal_kinds(mut self, kinds: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.literal_kinds = kinds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn max_size(mut self, bytes: usize) -> Self {
+        self.config.max_size = bytes;
+        self
+    }
+
+    pub fn nest_depth(mut self, depth: usize) -> Self {
+        self.config.nest_depth = depth;
+        self
+    }
+
+    pub fn only_kinds(mut self, kinds: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.only_kinds = kinds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn operators(mut self, operators: OperatorWeights) -> Self {
+        self.config.operators = operators;
+        self
+    }
+
+    pub fn per_seed(mut self, tests: usize) -> Self {
+        self.config.per_seed = tests;
+        self
+    }
+
+    pub fn preserve_leading_trivia(mut self, preserve: bool) -> Self {
+        self.config.preserve_leading_trivia = preserve;
+        self
+    }
+
+    pub fn reindent(mut self, reindent: bool) -> Self {
+        self.config.reindent = reindent;
+        self
+    }
+
+    pub fn reparse(mut self, mutations: usize) -> Self {
+        self.config.reparse = mutations;
+        self
+    }
+
+    pub fn rules(mut self, rules: Arc<Rules>) -> Self {
+        self.config.rules = Some(rules);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = seed;
+        self
+    }
+
+    pub fn size_distribution(mut self, distribution: SizeDistribution) -> Self {
+        self.config.size_distribution = distribution;
+        self
+    }
+
+    pub fn skip_kinds(mut self, kinds: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.skip_kinds = kinds.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn target_size(mut self, bytes: usize) -> Self {
+        self.config.target_size = Some(bytes);
+        self
+    }
+
+    pub fn templates(mut self, templates: Arc<Templates>) -> Self {
+        self.config.templates = Some(templates);
+        self
+    }
+
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.config.trace = trace;
+        self
+    }
+
+    pub fn type_hint(mut self, hint: Arc<dyn TypeHint>) -> Self {
+        self.config.type_hint = Some(hint);
+        self
+    }
+
+    pub fn unique_kinds(mut self, unique: bool) -> Self {
+        self.config.unique_kinds = unique;
+        self
+    }
+
+    /// The configuration so far
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Make a splicer that mutates `files`
+    pub fn build(self, files: &'a HashMap<String, (Vec<u8>, Tree)>) -> Splicer<'a> {
+        Splicer::new_with_weighted_donors(self.config, files, &self.donors)
+    }
+}
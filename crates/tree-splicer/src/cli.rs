@@ -1,20 +1,77 @@
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsString;
 use std::fs;
 use std::io;
 use std::io::Read;
-use std::path::PathBuf;
+use std::net::{SocketAddr, TcpListener};
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
 use clap_verbosity_flag::{InfoLevel, Verbosity};
-use tracing::{error, warn};
+use rand::{seq::SliceRandom, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use tracing::{debug, error, info, warn};
 use tracing_subscriber::fmt::format::FmtSpan;
 use tree_sitter::Tree;
 
-use crate::splice::{Config, Splicer};
+use crate::cluster::cluster;
+use crate::dict;
+use crate::index::{hash, CorpusIndex};
+use crate::injection::Injection;
+use crate::node_types::NodeTypes;
+use crate::preprocess::{self, Preprocess};
+use crate::rules::Rules;
+use crate::splice::{Budget, Config, Focus, OperatorWeights, SizeDistribution, Splicer};
+use crate::templates::Templates;
+use crate::type_hint::{RegexTypeHint, TypeHint, TypeRule};
+pub use analyze::Analyze;
+pub use distill::Distill;
+use events::{Event, EventLog};
+pub use fresh::GenerateFresh;
+use metrics::Metrics;
+pub use mutate::Mutate;
+pub use name::NameTemplate;
+use output::Outputs;
+use progress::Progress;
+pub use rate::Rate;
+pub use refine::Refine;
+pub use replay::Replay;
+use report::Report;
+use resume::Checkpoint;
+pub use sink::OutputFormat;
+use worker::{Embedded, Worker};
 
+mod analyze;
+mod bucket;
+mod dedup;
+mod diff;
+mod distill;
+mod events;
+mod exec;
 mod formatter;
+mod fresh;
+mod metrics;
+mod mutate;
+mod name;
+mod oracle;
+mod output;
+mod progress;
+mod rate;
+mod reduce;
+mod refine;
+mod replay;
+mod report;
+mod resume;
+mod sink;
+mod worker;
 
 #[derive(clap::ValueEnum, Debug, Clone, PartialEq, Eq)]
 pub enum OnParseError {
@@ -40,6 +97,160 @@ impl Default for OnParseError {
     }
 }
 
+#[derive(clap::ValueEnum, Debug, Clone, PartialEq, Eq)]
+pub enum OnInvalidUtf8 {
+    /// Splice the bytes as they are
+    Keep,
+    /// Replace invalid bytes with U+FFFD
+    Lossy,
+    /// Skip the file, with a warning
+    Skip,
+    /// Stop with an error
+    Error,
+}
+
+impl std::fmt::Display for OnInvalidUtf8 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OnInvalidUtf8::Keep => write!(f, "keep"),
+            OnInvalidUtf8::Lossy => write!(f, "lossy"),
+            OnInvalidUtf8::Skip => write!(f, "skip"),
+            OnInvalidUtf8::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for OnInvalidUtf8 {
+    fn default() -> Self {
+        OnInvalidUtf8::Keep
+    }
+}
+
+/// A test, by the seed and the number that its random numbers come from, see
+/// [`test_seed`](crate::splice::test_seed)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TestId {
+    pub seed: u64,
+    pub index: usize,
+}
+
+impl std::str::FromStr for TestId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (seed, index) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Expected SEED:INDEX, found {}", s))?;
+        Ok(TestId {
+            seed: seed
+                .parse()
+                .map_err(|_| format!("Invalid seed: {}", seed))?,
+            index: index
+                .parse()
+                .map_err(|_| format!("Invalid test number: {}", index))?,
+        })
+    }
+}
+
+impl std::fmt::Display for TestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.seed, self.index)
+    }
+}
+
+/// What to do with tests that don't parse
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validate {
+    Keep,
+    Discard,
+    /// Make another test instead, up to `--attempts` times
+    Retry,
+}
+
+impl std::fmt::Display for Validate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Validate::Keep => write!(f, "keep"),
+            Validate::Discard => write!(f, "discard"),
+            Validate::Retry => write!(f, "retry"),
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEndings {
+    Preserve,
+    Lf,
+    Crlf,
+}
+
+impl std::fmt::Display for LineEndings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LineEndings::Preserve => write!(f, "preserve"),
+            LineEndings::Lf => write!(f, "lf"),
+            LineEndings::Crlf => write!(f, "crlf"),
+        }
+    }
+}
+
+#[allow(clippy::derivable_impls)]
+impl Default for LineEndings {
+    fn default() -> Self {
+        LineEndings::Preserve
+    }
+}
+
+fn normalize_line_endings(text: Vec<u8>, line_endings: &LineEndings) -> Vec<u8> {
+    if *line_endings == LineEndings::Preserve {
+        return text;
+    }
+    let mut normalized = Vec::with_capacity(text.len());
+    let mut bytes = text.iter().peekable();
+    while let Some(&b) = bytes.next() {
+        if b == b'\r' && bytes.peek() == Some(&&b'\n') {
+            continue;
+        }
+        if b == b'\n' && *line_endings == LineEndings::Crlf {
+            normalized.push(b'\r');
+        }
+        normalized.push(b);
+    }
+    normalized
+}
+
+/// `text` of the file at `path` after `--on-invalid-utf8`, or `None` to skip
+/// it
+fn handle_invalid_utf8(
+    path: &str,
+    text: Vec<u8>,
+    on_invalid_utf8: &OnInvalidUtf8,
+) -> Result<Option<Vec<u8>>> {
+    let Err(e) = std::str::from_utf8(&text) else {
+        return Ok(Some(text));
+    };
+    match on_invalid_utf8 {
+        OnInvalidUtf8::Keep => Ok(Some(text)),
+        OnInvalidUtf8::Lossy => {
+            debug!(path, "Replacing invalid UTF-8 in {}", path);
+            Ok(Some(
+                String::from_utf8_lossy(&text).into_owned().into_bytes(),
+            ))
+        }
+        OnInvalidUtf8::Skip => {
+            warn!(path, "Skipping {}, invalid UTF-8", path);
+            Ok(None)
+        }
+        OnInvalidUtf8::Error => bail!(
+            "Invalid UTF-8 in {} at byte {}, see --on-invalid-utf8",
+            path,
+            e.valid_up_to()
+        ),
+    }
+}
+
 fn handle_parse_errors(path: &str, tree: &Tree, on_parse_error: &OnParseError) {
     let node = tree.root_node();
     match on_parse_error {
@@ -56,25 +267,309 @@ fn handle_parse_errors(path: &str, tree: &Tree, on_parse_error: &OnParseError) {
     }
 }
 
-/// TODO description
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-pub struct Args {
+/// A variant of a language with its own grammar, e.g., TSX for TypeScript
+#[derive(Clone, Copy, Debug)]
+pub struct Dialect {
+    /// Value of `--dialect` that selects this dialect
+    pub name: &'static str,
+    pub language: tree_sitter::Language,
+    pub node_types_json_str: &'static str,
+}
+
+/// Defaults for a language, e.g., fewer deletions where whitespace is
+/// significant, see [`main_with_profile`]. Flags and the `--config` file
+/// override them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LanguageProfile {
+    /// Name of the language, e.g., `Python`, for errors in the profile
+    pub name: &'static str,
+    /// Default of `--chaos`
+    pub chaos: Option<u8>,
+    /// Default of `--deletions`
+    pub deletions: Option<u8>,
+    /// Default of `--weights`: kinds to mutate more often, and how much more
+    pub interesting_kinds: &'static [(&'static str, u32)],
+    /// Default of `--literal-kinds`
+    pub literal_kinds: &'static [&'static str],
+    /// Default of `--identifier-kinds`
+    pub identifier_kinds: &'static [&'static str],
+    /// Extensions of files in the language, e.g., `rs`. Unless `--include` is
+    /// given, only files with these are taken from directories.
+    pub extensions: &'static [&'static str],
+    /// Defaults of other options, see [`parse_args_with_defaults`]
+    pub defaults: &'static [(&'static str, &'static str)],
+}
+
+impl LanguageProfile {
+    /// Values of options to use as their defaults
+    fn option_defaults(&self) -> Vec<(&'static str, Vec<String>)> {
+        let mut defaults = Vec::new();
+        if let Some(chaos) = self.chaos {
+            defaults.push(("chaos", vec![chaos.to_string()]));
+        }
+        if let Some(deletions) = self.deletions {
+            defaults.push(("deletions", vec![deletions.to_string()]));
+        }
+        let strings =
+            |values: &[&str]| -> Vec<String> { values.iter().map(|v| v.to_string()).collect() };
+        let lists = [
+            ("identifier_kinds", strings(self.identifier_kinds)),
+            ("literal_kinds", strings(self.literal_kinds)),
+            (
+                "weights",
+                self.interesting_kinds
+                    .iter()
+                    .map(|(kind, weight)| format!("{}={}", kind, weight))
+                    .collect(),
+            ),
+        ];
+        defaults.extend(lists.into_iter().filter(|(_, values)| !values.is_empty()));
+        defaults.extend(
+            self.defaults
+                .iter()
+                .map(|(key, value)| (*key, vec![value.to_string()])),
+        );
+        defaults
+    }
+
+    /// Patterns that match files with [`LanguageProfile::extensions`]
+    fn include(&self) -> Result<Vec<glob::Pattern>> {
+        self.extensions
+            .iter()
+            .map(|e| glob::Pattern::new(&format!("*.{}", e)).context("Invalid extension"))
+            .collect()
+    }
+}
+
+/// A directory of donor files and how often to choose candidates from them,
+/// e.g., `corpus=5`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Donors {
+    pub dir: PathBuf,
+    pub weight: u32,
+}
+
+impl std::str::FromStr for Donors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((dir, weight)) = s.rsplit_once('=') {
+            if let Ok(weight) = weight.parse::<u32>() {
+                if weight == 0 {
+                    return Err(format!("Donor weight must be positive: {}", s));
+                }
+                return Ok(Donors {
+                    dir: PathBuf::from(dir),
+                    weight,
+                });
+            }
+        }
+        Ok(Donors {
+            dir: PathBuf::from(s),
+            weight: 1,
+        })
+    }
+}
+
+/// How often to mutate nodes of a kind, e.g., `type_identifier=10`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KindWeight {
+    pub kind: String,
+    pub weight: u32,
+}
+
+impl std::str::FromStr for KindWeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, weight) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected KIND=N, found {}", s))?;
+        let weight = weight
+            .parse()
+            .map_err(|e| format!("Invalid weight {}: {}", weight, e))?;
+        Ok(KindWeight {
+            kind: kind.to_string(),
+            weight,
+        })
+    }
+}
+
+/// Options for reading input files
+#[derive(clap::Args, Debug)]
+pub struct Common {
+    /// Also take binary files, i.e., files with a NUL byte near the start,
+    /// from directories and glob patterns
+    #[arg(long)]
+    pub binary: bool,
+
+    /// Read defaults for these options from a TOML file, e.g., `chaos = 10`
+    /// or `[weights]` with `type_identifier = 3`; flags override the file
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Dialect of the language, e.g., `tsx` for TypeScript
+    #[arg(long, value_name = "DIALECT")]
+    pub dialect: Option<String>,
+
+    /// Skip files in directories and glob patterns that match this glob
+    /// pattern; may be repeated
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<glob::Pattern>,
+
+    /// Only take files from directories and glob patterns that match one of
+    /// these glob patterns, e.g., `*.rs`. Patterns without a `/` match file
+    /// names, others match paths.
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<glob::Pattern>,
+
+    /// Line endings of input files (before splicing) and output files
+    #[arg(long, default_value_t = LineEndings::Preserve, value_name = "CHOICE")]
+    pub line_endings: LineEndings,
+
+    /// Skip files bigger than this (bytes) in directories and glob patterns
+    #[arg(long, default_value_t = 1048576, value_name = "BYTES")]
+    pub max_file_size: u64,
+
+    /// Take files in directories that `.gitignore` and `.ignore` files
+    /// exclude
+    #[arg(long)]
+    pub no_ignore: bool,
+
+    /// Delete trailing whitespace and extra blank lines from input files
+    /// (before splicing), and put one space between tokens on the same line,
+    /// so that candidates don't differ only in whitespace
+    #[arg(long)]
+    pub normalize_whitespace: bool,
+
+    /// Behavior on input files that aren't valid UTF-8
+    #[arg(long, default_value_t = OnInvalidUtf8::Keep, value_name = "CHOICE")]
+    pub on_invalid_utf8: OnInvalidUtf8,
+
+    /// Behavior on parse errors
+    #[arg(long, default_value_t = OnParseError::Warn, value_name = "CHOICE")]
+    pub on_parse_error: OnParseError,
+
+    /// Delete comments from input files (before splicing), so that they
+    /// aren't splice candidates
+    #[arg(long)]
+    pub strip_comments: bool,
+
+    #[clap(flatten)]
+    verbose: Verbosity<InfoLevel>,
+}
+
+impl Common {
+    /// Whether a file from a directory or a glob pattern is an input
+    fn wanted(&self, path: &Path) -> bool {
+        let matches = |pattern: &glob::Pattern| {
+            if pattern.as_str().contains('/') {
+                pattern.matches_path(path)
+            } else {
+                path.file_name()
+                    .is_some_and(|n| pattern.matches(&n.to_string_lossy()))
+            }
+        };
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+
+    pub(super) fn preprocess(&self) -> Preprocess {
+        Preprocess {
+            strip_comments: self.strip_comments,
+            normalize_whitespace: self.normalize_whitespace,
+        }
+    }
+}
+
+/// Options for mutating a file
+#[derive(clap::Args, Debug)]
+pub struct Splicing {
+    /// Instead of `--mutations`, keep mutating each test until about this
+    /// many bytes (e.g., `200`) or this percent of the input (e.g., `5%`)
+    /// are changed
+    #[arg(long, conflicts_with = "mutations", value_name = "SIZE")]
+    pub budget: Option<Budget>,
+
     /// Percent of "chaotic" mutations - may introduce syntax errors
     #[arg(short, long, default_value_t = 5)]
     pub chaos: u8,
 
-    /// Percent of deletion mutations - the rest are splices
+    /// Percent of tests made by crossing two inputs instead of mutating one:
+    /// alternately taking top-level items from each, or replacing a node
+    /// with a node of the same kind from the other
+    #[arg(long, default_value_t = 0)]
+    pub crossover: u8,
+
+    /// Percent of deletion mutations
     #[arg(short, long, default_value_t = 5)]
     pub deletions: u8,
 
-    /// Behavior on parse errors
-    #[arg(long, default_value_t = OnParseError::Warn, value_name = "CHOICE")]
-    on_parse_error: OnParseError,
+    /// AFL-style dictionary of tokens to splice in besides the nodes of the
+    /// inputs, e.g., keywords and attributes; may be repeated
+    #[arg(long, value_name = "FILE")]
+    pub dict: Vec<PathBuf>,
 
-    /// Number of threads
-    #[arg(short, long, default_value_t = num_cpus::get())]
-    pub jobs: usize,
+    /// Node kinds to splice in `--dict` tokens as; default = the identifier
+    /// kinds of the inputs
+    #[arg(long, value_delimiter = ',', value_name = "KINDS")]
+    pub dict_kinds: Vec<String>,
+
+    /// Only mutate nodes of the input file within these bytes, e.g.,
+    /// `120..480`, and keep the rest intact
+    #[arg(long, value_parser = parse_range, value_name = "START..END")]
+    pub focus_range: Option<Range<usize>>,
+
+    /// Only mutate nodes within the nodes that the captures of this
+    /// tree-sitter query match, e.g., `(function_item name: (identifier) @n
+    /// body: (_) @body (#eq? @n "main"))`
+    #[arg(long, conflicts_with = "focus_range", value_name = "QUERY")]
+    pub focus_query: Option<String>,
+
+    /// Directory of files to take splice candidates from, without mutating
+    /// them; may be repeated. `DIR=N` makes its candidates N times as likely.
+    #[arg(long, value_name = "DIR[=WEIGHT]")]
+    pub donors: Vec<Donors>,
+
+    /// Percent of mutations that duplicate a node in a list, e.g., a
+    /// statement or an argument
+    #[arg(long, default_value_t = 0)]
+    pub duplications: u8,
+
+    /// Half of the time, replace a literal with another literal of the same
+    /// kind from the inputs instead of an edge case, see `--dict-out`
+    #[arg(long)]
+    pub harvest: bool,
+
+    /// Percent of mutations that edit the bytes and tokens of a node, e.g.,
+    /// flip bits, duplicate a token, or truncate it
+    #[arg(long, default_value_t = 0)]
+    pub havoc: u8,
+
+    /// Also treat leaf nodes of these kinds as identifiers for `--renames`,
+    /// besides kinds that end in `identifier`, e.g., `bare_key`
+    #[arg(long, value_delimiter = ',', value_name = "KINDS")]
+    pub identifier_kinds: Vec<String>,
+
+    /// Percent of mutations that add a node to a list, e.g., a statement to
+    /// a block
+    #[arg(long, default_value_t = 0)]
+    pub insertions: u8,
+
+    /// Percent of mutations that replace a literal with an edge case, e.g.,
+    /// `0`, `-1`, `NaN`, or a long string
+    #[arg(long, default_value_t = 0)]
+    pub literals: u8,
+
+    /// Also treat nodes of these kinds as literals for `--literals`, besides
+    /// kinds that look like literals (e.g., `integer_literal`)
+    #[arg(long, value_delimiter = ',', value_name = "KINDS")]
+    pub literal_kinds: Vec<String>,
+
+    /// Percent of mutations that splice in a node from the file being
+    /// mutated, rather than from any input
+    #[arg(long, default_value_t = 0)]
+    pub intra_mutations: u8,
 
     /// Approximate maximum file size to produce (bytes); default = 1MiB
     #[arg(long, default_value_t = 1048576)]
@@ -84,35 +579,737 @@ pub struct Args {
     #[arg(short, long, default_value_t = 16)]
     pub mutations: usize,
 
-    /// Directory to output to
-    #[arg(short, long, default_value_os = "tree-splicer.out")]
-    pub output: PathBuf,
+    /// Percent of mutations that nest a node in copies of the text around a
+    /// node of the same kind in an input, e.g., `((((x))))`, to stress
+    /// recursion in parsers and type checkers
+    #[arg(long, default_value_t = 0)]
+    pub nestings: u8,
+
+    /// Nest a node at most this many times deep with `--nestings`
+    #[arg(long, default_value_t = 16, value_name = "N")]
+    pub nest_depth: usize,
+
+    /// Percent of mutations that replace an operator with another that can be
+    /// in the same place, e.g., `a + b` with `a << b`, keeping the operands
+    #[arg(long, default_value_t = 0)]
+    pub operator_mutations: u8,
 
-    /// Re-parse the file after this many mutations; higher is faster
+    /// Only mutate nodes of these kinds, e.g., `type_identifier,primitive_type`
+    #[arg(long, value_delimiter = ',', value_name = "KINDS")]
+    pub only_kinds: Vec<String>,
+
+    /// Percent of mutations that rename an identifier everywhere in the
+    /// file - the rest (after the other kinds of mutations) are splices
+    #[arg(long, default_value_t = 0)]
+    pub renames: u8,
+
+    /// Delete and duplicate the comments and attributes on the lines right
+    /// before a node along with it
+    #[arg(long)]
+    pub preserve_leading_trivia: bool,
+
+    /// Re-indent spliced and inserted nodes to match the line they go into,
+    /// e.g., for Python or YAML; `--reindent=false` turns it off
+    #[arg(
+        long,
+        action = clap::ArgAction::Set,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value_t = false,
+        default_missing_value = "true",
+        value_name = "BOOL"
+    )]
+    pub reindent: bool,
+
+    /// Re-parse the file after this many mutations; higher is faster, and 0
+    /// never re-parses
     #[arg(short, long, default_value_t = 1)]
     pub reparse: usize,
 
+    /// Percent of mutations that replace the nodes that `--rules` captures;
+    /// default = 25 with `--rules`, else 0
+    #[arg(long, value_name = "PERCENT")]
+    pub rule_mutations: Option<u8>,
+
+    /// File of tree-sitter queries whose captures are nodes to mutate, e.g.,
+    /// `((binary_expression operator: _ @op) (#replace! @op "+" "-"))`; see
+    /// the README
+    #[arg(long, value_name = "FILE")]
+    pub rules: Option<PathBuf>,
+
     /// Seed
     #[arg(short, long, default_value_t = 0)]
     pub seed: u64,
 
+    /// How to pick the size each test is grown or shrunk toward, up to
+    /// `--target-size`: `uniform`, `log` (mostly small), or `fixed`
+    #[arg(long, default_value_t = SizeDistribution::Uniform, value_name = "DIST")]
+    pub size_distribution: SizeDistribution,
+
+    /// Never mutate nodes of these kinds, e.g., `line_comment,string_literal`
+    #[arg(long, value_delimiter = ',', value_name = "KINDS")]
+    pub skip_kinds: Vec<String>,
+
+    /// Percent of mutations that swap two elements of a list, e.g., two
+    /// statements in a block or two arguments
+    #[arg(long, default_value_t = 0)]
+    pub swaps: u8,
+
+    /// Instead of `--mutations`, grow or shrink each test toward a size up to
+    /// this many bytes, favoring duplications and insertions while it's
+    /// smaller and deletions while it's bigger, e.g., to make large tests
+    #[arg(long, conflicts_with_all = ["budget", "mutations"], value_name = "BYTES")]
+    pub target_size: Option<usize>,
+
+    /// Percent of mutations that replace a node with one of `--templates`;
+    /// default = 25 with `--templates`, else 0
+    #[arg(long, value_name = "PERCENT")]
+    pub template_mutations: Option<u8>,
+
+    /// TOML file of snippets to splice in by node kind, with holes filled
+    /// from the inputs, e.g., `expression_statement = ["$IDENT += $EXPR;"]`;
+    /// see the README
+    #[arg(long, value_name = "FILE")]
+    pub templates: Option<PathBuf>,
+
+    /// Only splice candidates of the same type into a node of a known type,
+    /// where a node has type TYPE if its text matches REGEX, e.g.,
+    /// `int=^-?[0-9]+$`; may be repeated, and the first match wins
+    #[arg(long, value_name = "TYPE=REGEX")]
+    pub type_hints: Vec<TypeRule>,
+
+    /// Make nodes of a kind N times as likely to be mutated, e.g.,
+    /// `type_identifier=10,primitive_type=5`
+    #[arg(long, value_delimiter = ',', value_name = "KIND=N")]
+    pub weights: Vec<KindWeight>,
+}
+
+impl Splicing {
+    /// See [`Config::focus`], which isn't part of [`Splicing::config`]
+    /// because queries are for one language
+    fn focus(&self, language: tree_sitter::Language) -> Result<Option<Focus>> {
+        if let Some(range) = &self.focus_range {
+            return Ok(Some(Focus::Range(range.clone())));
+        }
+        self.focus_query
+            .as_deref()
+            .map(|query| {
+                tree_sitter::Query::new(language, query)
+                    .map(|q| Focus::Query(Arc::new(q)))
+                    .with_context(|| format!("Invalid --focus-query {}", query))
+            })
+            .transpose()
+    }
+
+    /// See [`Config::rules`], which isn't part of [`Splicing::config`] for the
+    /// same reason as [`Splicing::focus`]
+    fn rules(&self, language: tree_sitter::Language) -> Result<Option<Arc<Rules>>> {
+        self.rules
+            .as_deref()
+            .map(|path| {
+                let source = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read rules {}", path.display()))?;
+                Rules::new(language, &source)
+                    .map(Arc::new)
+                    .map_err(|e| anyhow!("Invalid rules in {}: {}", path.display(), e))
+            })
+            .transpose()
+    }
+
+    /// See [`Config::templates`], which isn't part of [`Splicing::config`]
+    /// for the same reason as [`Splicing::focus`]
+    fn templates(&self, node_types: &NodeTypes) -> Result<Option<Arc<Templates>>> {
+        let Some(path) = &self.templates else {
+            return Ok(None);
+        };
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read templates {}", path.display()))?;
+        let table: toml::Table = text
+            .parse()
+            .with_context(|| format!("Invalid templates {}", path.display()))?;
+        let templates = table
+            .into_iter()
+            .map(|(kind, value)| {
+                let snippets = match value {
+                    toml::Value::String(s) => vec![s],
+                    toml::Value::Array(values) => values
+                        .into_iter()
+                        .map(|v| match v {
+                            toml::Value::String(s) => Ok(s),
+                            _ => bail!("Expected strings for {}", kind),
+                        })
+                        .collect::<Result<_>>()?,
+                    _ => bail!("Expected strings for {}", kind),
+                };
+                Ok((kind, snippets))
+            })
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Invalid templates {}", path.display()))?;
+        Templates::new(node_types, templates)
+            .map(|t| Some(Arc::new(t)))
+            .map_err(|e| anyhow!("Invalid templates in {}: {}", path.display(), e))
+    }
+
+    /// The tokens of the `--dict` files
+    fn dictionary(&self) -> Result<Vec<Vec<u8>>> {
+        let mut tokens = Vec::new();
+        for path in &self.dict {
+            let text = fs::read(path)
+                .with_context(|| format!("Failed to read dictionary {}", path.display()))?;
+            tokens.extend(
+                dict::parse(&text)
+                    .map_err(|e| anyhow!("Invalid dictionary {}: {}", path.display(), e))?,
+            );
+        }
+        Ok(tokens)
+    }
+
+    /// Add `tokens` from [`Splicing::dictionary`] to `splicer`
+    fn add_dictionary<'a>(&self, splicer: &mut Splicer<'a>, tokens: &'a [Vec<u8>]) -> Result<()> {
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        let tokens: Vec<_> = tokens.iter().map(Vec::as_slice).collect();
+        splicer
+            .add_tokens(&self.dict_kinds, &tokens)
+            .map_err(|e| anyhow!("Invalid --dict-kinds: {}", e))
+    }
+
+    fn operators(&self) -> OperatorWeights {
+        OperatorWeights {
+            splice: 0,
+            delete: self.deletions.into(),
+            duplicate: self.duplications.into(),
+            insert: self.insertions.into(),
+            swap: self.swaps.into(),
+            rename: self.renames.into(),
+            literal: self.literals.into(),
+            havoc: self.havoc.into(),
+            intra: self.intra_mutations.into(),
+            rule: self
+                .rule_mutations
+                .unwrap_or(if self.rules.is_some() { 25 } else { 0 })
+                .into(),
+            nest: self.nestings.into(),
+            operator: self.operator_mutations.into(),
+            template: self
+                .template_mutations
+                .unwrap_or(if self.templates.is_some() { 25 } else { 0 })
+                .into(),
+        }
+        .splice_rest()
+    }
+
+    fn config(&self, language: tree_sitter::Language, node_types: NodeTypes) -> Config {
+        Config {
+            budget: self.budget,
+            chaos: self.chaos,
+            crossover: self.crossover,
+            harvest: self.harvest,
+            identifier_kinds: self.identifier_kinds.iter().cloned().collect(),
+            inter_splices: self.mutations,
+            kind_weights: self
+                .weights
+                .iter()
+                .map(|w| (w.kind.clone(), w.weight))
+                .collect(),
+            literal_kinds: self.literal_kinds.iter().cloned().collect(),
+            max_size: self.max_size,
+            nest_depth: self.nest_depth,
+            only_kinds: self.only_kinds.iter().cloned().collect(),
+            operators: self.operators(),
+            preserve_leading_trivia: self.preserve_leading_trivia,
+            reindent: self.reindent,
+            reparse: self.reparse,
+            seed: self.seed,
+            size_distribution: self.size_distribution,
+            skip_kinds: self.skip_kinds.iter().cloned().collect(),
+            target_size: self.target_size,
+            type_hint: (!self.type_hints.is_empty()).then(|| {
+                Arc::new(RegexTypeHint {
+                    rules: self.type_hints.clone(),
+                }) as Arc<dyn TypeHint>
+            }),
+            ..Config::new(language, node_types)
+        }
+    }
+}
+
+/// Make tests by splicing the input files (the default)
+#[derive(clap::Args, Debug)]
+pub struct Generate {
+    /// With `--continuous`, pause while the output directory has N or more
+    /// tests, e.g., until a fuzzer consumes them
+    #[arg(long, requires = "continuous", value_name = "N")]
+    pub backlog: Option<usize>,
+
+    /// Group inputs into N clusters of structurally similar files, and choose
+    /// inputs to mutate evenly from each cluster
+    #[arg(long, default_value_t = 0, value_name = "N")]
+    pub clusters: usize,
+
+    /// Make tests until interrupted, ignoring `--tests`
+    #[arg(long, conflicts_with = "report")]
+    pub continuous: bool,
+
+    /// With `--exec`, also keep tests that reach new code in the command, and
+    /// splice them like inputs. The command must be built with
+    /// SanitizerCoverage, e.g., `-fsanitize=address -fsanitize-coverage=trace-pc-guard`.
+    #[arg(long, requires = "exec")]
+    pub coverage_guided: bool,
+
+    /// With `--exec-a`, ignore text that matches this regex in stdout and
+    /// stderr, e.g., version numbers
+    #[arg(long, requires = "exec_a", value_name = "REGEX")]
+    pub diff_ignore: Option<String>,
+
+    /// Write the identifiers, field names, and literals of the inputs,
+    /// donors, and `--dict` to this AFL-style dictionary, e.g., for
+    /// `afl-fuzz -x` or a later `--dict`
+    #[arg(long, value_name = "FILE")]
+    pub dict_out: Option<PathBuf>,
+
+    /// Run this command on each test and only keep tests that make it crash
+    /// (or match `--regex`); `@@` is replaced by the path to the test,
+    /// otherwise the test is passed on stdin. A crash is a signal, or a
+    /// failing exit with a sanitizer report, Rust panic, or internal compiler
+    /// error on stderr
+    #[arg(long, value_name = "CMD")]
+    pub exec: Option<String>,
+
+    /// Run this command and `--exec-b` on each test (like `--exec`), and only
+    /// keep tests where their exit statuses, stdout, or stderr differ, e.g.,
+    /// to compare two versions of a compiler
+    #[arg(long, requires = "exec_b", conflicts_with = "exec", value_name = "CMD")]
+    pub exec_a: Option<String>,
+
+    /// See `--exec-a`
+    #[arg(long, requires = "exec_a", value_name = "CMD")]
+    pub exec_b: Option<String>,
+
+    /// Check this code formatter (e.g., `rustfmt`) on each test: it should
+    /// be idempotent. Reads code on stdin, prints formatted code on stdout.
+    #[arg(long, value_name = "CMD")]
+    pub formatter: Option<String>,
+
+    /// With `--formatter`, also check that formatted code parses
+    #[arg(long, requires = "formatter")]
+    pub formatter_reparse: bool,
+
+    /// Take splice candidates from this index, written by `--index-out` in an
+    /// earlier run on the same inputs and donors, instead of finding them
+    #[arg(long, value_name = "FILE")]
+    pub index_in: Option<PathBuf>,
+
+    /// Write the splice candidates of the inputs and donors to this index,
+    /// see `--index-in`
+    #[arg(long, value_name = "FILE")]
+    pub index_out: Option<PathBuf>,
+
+    /// Number of threads
+    #[arg(short, long, default_value_t = num_cpus::get())]
+    pub jobs: usize,
+
+    /// Write a JSON object to this file for each test, crash, hang, and
+    /// discarded test, one per line, e.g., to compute statistics of a long run
+    #[arg(long, value_name = "FILE")]
+    pub log_json: Option<PathBuf>,
+
+    /// With `--exec` or `--exec-a`, limit the memory of the commands to this
+    /// many MiB, so that tests that make them allocate without bound make
+    /// them crash instead (only on Unix)
+    #[arg(long, value_name = "MIB")]
+    pub memory_limit: Option<u64>,
+
+    /// Only load a random sample of the input files with at most N bytes in
+    /// total, for corpora that don't fit in memory. Only the text of the
+    /// inputs counts: their trees take several times as much memory, and
+    /// donors aren't sampled.
+    #[arg(long, value_name = "N")]
+    pub max_input_bytes: Option<u64>,
+
+    /// Serve counters of tests, crashes, mutations, and memory use for
+    /// Prometheus over HTTP on this address, e.g., `0.0.0.0:9184`, to watch a
+    /// `--continuous` run
+    #[arg(long, value_name = "ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Name tests after the input they were made from: `{stem}` and `{ext}`
+    /// are its name and extension (with a `.`), `{i}` is the test number,
+    /// and `{i:0N}` pads it to N digits
+    #[arg(long, default_value = "{stem}.{i:04}{ext}", value_name = "TEMPLATE")]
+    pub name_template: NameTemplate,
+
+    /// Directory to output to
+    #[arg(short, long, default_value_os = "tree-splicer.out")]
+    pub output: PathBuf,
+
+    /// Save tests as files in the output directory, in `tests.tar` or
+    /// `tests.zip` there, or print them to stdout as JSON Lines,
+    /// `{"name": ..., "content_base64": ...}`, e.g., to pipe them to a fuzzer
+    #[arg(long, default_value_t = OutputFormat::Dir, value_name = "CHOICE")]
+    pub output_format: OutputFormat,
+
+    /// Make at least this many tests from each input file
+    #[arg(long, default_value_t = 0, value_name = "N")]
+    pub per_seed: usize,
+
+    /// Show the number of tests, tests per second, the percent of duplicate
+    /// and invalid tests, and crashes on stderr, even if it isn't a terminal;
+    /// by default, they're only shown on a terminal and not with `--quiet`
+    #[arg(long)]
+    pub progress: bool,
+
+    /// Make at most this many tests per second (`N/s`), minute (`N/m`), or hour (`N/h`)
+    #[arg(long, value_name = "RATE")]
+    pub rate: Option<Rate>,
+
+    /// Write an HTML summary of the run to this file
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Reuse N output file names, overwriting older tests; the test number
+    /// in `--name-template` counts up to N
+    #[arg(long, value_name = "N")]
+    pub rotate: Option<NonZeroUsize>,
+
+    /// Make only the test with this seed and number, as printed with
+    /// `--verbose` and in `--log-json`, e.g., to make it again for a bug
+    /// report; overrides `--seed` and `--tests`
+    #[arg(
+        long,
+        value_name = "SEED:INDEX",
+        conflicts_with_all = ["continuous", "dedup", "resume"]
+    )]
+    pub reproduce: Option<TestId>,
+
+    /// Save how far the run got in the output directory, and continue from
+    /// there if it was interrupted, numbering tests where it left off; needs
+    /// the same `--seed` and `--jobs`. With `--dedup`, also keep the hashes of
+    /// the tests there, unless `--dedup-index` is given.
+    #[arg(long)]
+    pub resume: bool,
+
+    /// With `--exec`, reduce tests that make the command crash (or match
+    /// `--regex`) by deleting and replacing nodes while they still do, and
+    /// save them next to the originals, e.g., `foo.0007.min.rs`
+    #[arg(long, requires = "exec")]
+    pub reduce: bool,
+
+    /// With `--exec`, also keep tests where stdout or stderr matches this regex
+    #[arg(long, requires = "exec", value_name = "REGEX")]
+    pub regex: Option<String>,
+
+    /// With `--validate retry` or `--dedup`, give up on a test after this
+    /// many tries
+    #[arg(long, default_value = "16", value_name = "N")]
+    pub attempts: NonZeroUsize,
+
+    /// Remake tests that are identical to an input or an earlier test
+    #[arg(long)]
+    pub dedup: bool,
+
+    /// With `--dedup`, also skip tests in this index of earlier runs, and add
+    /// new tests to it
+    #[arg(long, requires = "dedup", value_name = "FILE")]
+    pub dedup_index: Option<PathBuf>,
+
+    /// Also save the input that each test was made from to this directory,
+    /// under the name of the test, and a unified diff from it to the test as
+    /// `NAME.diff`
+    #[arg(long, value_name = "DIR")]
+    pub emit_diff: Option<PathBuf>,
+
+    /// With `--exec` or `--exec-a`, kill the commands after this many seconds
+    #[arg(long, default_value_t = 10, value_name = "SECS")]
+    pub timeout: u64,
+
     /// How many tests to make
     #[arg(long, default_value_t = 4)]
     pub tests: usize,
 
-    #[clap(flatten)]
-    verbose: Verbosity<InfoLevel>,
+    /// Write a JSON trace of the mutations that made each test to this
+    /// directory, see the `replay` subcommand
+    #[arg(long, value_name = "DIR")]
+    pub trace_out: Option<PathBuf>,
+
+    /// Prefer to mutate a different node kind in each test
+    #[arg(long)]
+    pub unique_kinds: bool,
+
+    /// Re-parse each test, and keep, discard, or remake it if it has a parse
+    /// error
+    #[arg(long, default_value_t = Validate::Keep, value_name = "CHOICE")]
+    pub validate: Validate,
 
-    /// Input files, use `-` to pass a single file on stdin
+    /// Input files or directories, or quoted glob patterns like
+    /// `'src/**/*.rs'`; use `-` to pass a single file on stdin
     #[arg(value_name = "FILE", required = true, num_args = 1..)]
     pub files: Vec<String>,
+
+    #[command(flatten, next_help_heading = "Input options")]
+    pub common: Common,
+
+    #[command(flatten, next_help_heading = "Mutation options")]
+    pub splicing: Splicing,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    Generate(Generate),
+    GenerateFresh(GenerateFresh),
+    Mutate(Mutate),
+    Analyze(Analyze),
+    Distill(Distill),
+    Refine(Refine),
+    Replay(Replay),
+}
+
+impl Command {
+    /// Options for reading input files, if the command reads any
+    pub fn common(&self) -> Option<&Common> {
+        match self {
+            Command::Generate(args) => Some(&args.common),
+            Command::GenerateFresh(args) => Some(&args.common),
+            Command::Mutate(args) => Some(&args.common),
+            Command::Analyze(args) => Some(&args.common),
+            Command::Distill(args) => Some(&args.common),
+            Command::Refine(args) => Some(&args.common),
+            Command::Replay(_) => None,
+        }
+    }
+
+    pub fn common_mut(&mut self) -> Option<&mut Common> {
+        match self {
+            Command::Generate(args) => Some(&mut args.common),
+            Command::GenerateFresh(args) => Some(&mut args.common),
+            Command::Mutate(args) => Some(&mut args.common),
+            Command::Analyze(args) => Some(&mut args.common),
+            Command::Distill(args) => Some(&mut args.common),
+            Command::Refine(args) => Some(&mut args.common),
+            Command::Replay(_) => None,
+        }
+    }
+
+    /// The input files, with directories and glob patterns expanded, e.g.,
+    /// to guess their language
+    pub fn input_files(&self) -> Result<Vec<String>> {
+        match self.common() {
+            Some(common) => expand_files(common, self.files()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// The input files as given, see [`Command::input_files`]
+    pub fn files(&self) -> &[String] {
+        match self {
+            Command::Generate(args) => &args.files,
+            Command::GenerateFresh(_) => &[],
+            Command::Mutate(args) => std::slice::from_ref(&args.file),
+            Command::Analyze(args) => &args.files,
+            Command::Distill(args) => &args.files,
+            Command::Refine(args) => std::slice::from_ref(&args.corpus),
+            Command::Replay(_) => &[],
+        }
+    }
+}
+
+/// Simple grammar-based test case generator
+///
+/// Without a subcommand, runs `generate`.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub generate: Generate,
+}
+
+impl Args {
+    /// The subcommand, `generate` if none was given
+    pub fn into_command(self) -> Command {
+        self.command.unwrap_or(Command::Generate(self.generate))
+    }
+}
+
+fn read_file(file: &str) -> Result<Vec<u8>> {
+    fs::read(file).with_context(|| format!("Failed to read file {}", file))
+}
+
+/// All files under `dir` except hidden ones (e.g., `.git`) and, without
+/// `--no-ignore`, those that `.gitignore` and `.ignore` files exclude, sorted
+fn read_dir_files(common: &Common, dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let walk = ignore::WalkBuilder::new(dir)
+        .standard_filters(!common.no_ignore)
+        .hidden(true)
+        .require_git(false)
+        .follow_links(true)
+        .build();
+    for entry in walk {
+        match entry {
+            Ok(entry) if entry.file_type().is_some_and(|t| t.is_file()) => {
+                files.push(entry.into_path());
+            }
+            Ok(_) => (),
+            Err(e) => warn!("Skipping part of {}: {}", dir.display(), e),
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Why to skip a file from a directory or a glob pattern, if it's too big or
+/// binary
+fn skip_reason(common: &Common, path: &Path) -> Result<Option<&'static str>> {
+    let size = fs::metadata(path)
+        .with_context(|| format!("Failed to read file {}", path.display()))?
+        .len();
+    if size > common.max_file_size {
+        return Ok(Some("bigger than --max-file-size"));
+    }
+    if !common.binary {
+        // Like Git, look for a NUL byte in the first 8KiB
+        let mut start = Vec::with_capacity(8192);
+        fs::File::open(path)
+            .and_then(|f| f.take(8192).read_to_end(&mut start))
+            .with_context(|| format!("Failed to read file {}", path.display()))?;
+        if start.contains(&0) {
+            return Ok(Some("binary"));
+        }
+    }
+    Ok(None)
 }
 
-fn read_file(file: &str) -> Result<String> {
-    fs::read_to_string(file).with_context(|| format!("Failed to read file {}", file))
+/// Replace directories in `files` with the files under them, and glob
+/// patterns (e.g., `src/**/*.rs`) with the files they match, keeping those
+/// that match `--include` and not `--exclude`, and that aren't binary or
+/// bigger than `--max-file-size`. Other files are kept as-is.
+pub fn expand_files(common: &Common, files: &[String]) -> Result<Vec<String>> {
+    let mut expanded = Vec::with_capacity(files.len());
+    for file in files {
+        let path = Path::new(file);
+        let found: Vec<_> = if path.is_dir() {
+            read_dir_files(common, path)
+        } else if file.contains(['*', '?', '[']) && !path.exists() {
+            let options = glob::MatchOptions {
+                require_literal_leading_dot: true,
+                ..Default::default()
+            };
+            glob::glob_with(file, options)
+                .with_context(|| format!("Invalid glob pattern {}", file))?
+                .filter_map(Result::ok)
+                .filter(|p| p.is_file())
+                .collect()
+        } else {
+            expanded.push(file.clone());
+            continue;
+        };
+        let before = expanded.len();
+        for p in found.into_iter().filter(|p| common.wanted(p)) {
+            match skip_reason(common, &p)? {
+                Some(reason) => debug!("Skipping {}, {}", p.display(), reason),
+                None => expanded.push(p.display().to_string()),
+            }
+        }
+        if expanded.len() == before {
+            warn!("No input files in {}", file);
+        }
+    }
+    Ok(expanded)
+}
+
+/// A random sample of `files` with at most `max_bytes` bytes of text in
+/// total, the same for the same `seed`. Stdin is always in the sample.
+fn sample_files(files: Vec<String>, max_bytes: u64, seed: u64) -> Result<Vec<String>> {
+    let mut sized = Vec::with_capacity(files.len());
+    for file in files {
+        let size = if file == "-" {
+            0
+        } else {
+            fs::metadata(&file)
+                .with_context(|| format!("Failed to read file {}", file))?
+                .len()
+        };
+        sized.push((file, size));
+    }
+    let total = sized.len();
+    sized.shuffle(&mut ChaCha12Rng::seed_from_u64(seed));
+    let mut bytes = 0;
+    let mut sample = Vec::new();
+    for (file, size) in sized {
+        if bytes + size <= max_bytes {
+            bytes += size;
+            sample.push(file);
+        }
+    }
+    sample.sort();
+    if sample.is_empty() && total > 0 {
+        bail!("No input file is smaller than --max-input-bytes");
+    }
+    if sample.len() < total {
+        info!(
+            "Loading {} of {} input files ({} bytes) to stay under --max-input-bytes",
+            sample.len(),
+            total,
+            bytes
+        );
+    }
+    Ok(sample)
+}
+
+#[allow(clippy::type_complexity)]
+fn by_ref(
+    donors: &[(HashMap<String, (Vec<u8>, Tree)>, u32)],
+) -> Vec<(&HashMap<String, (Vec<u8>, Tree)>, u32)> {
+    donors.iter().map(|(corpus, w)| (corpus, *w)).collect()
+}
+
+/// Write to a temporary file first so that readers never see partial tests
+fn save(dir: &Path, name: &str, contents: &[u8]) -> io::Result<()> {
+    let tmp = dir.join(format!(".{}.tmp", name));
+    fs::write(&tmp, contents)?;
+    fs::rename(tmp, dir.join(name))
+}
+
+/// A range of bytes, e.g., `120..480`
+fn parse_range(s: &str) -> Result<Range<usize>, String> {
+    let (start, end) = s
+        .split_once("..")
+        .ok_or_else(|| format!("Expected START..END, found {}", s))?;
+    let start = start
+        .parse()
+        .map_err(|_| format!("Invalid start of range: {}", start))?;
+    let end = end
+        .parse()
+        .map_err(|_| format!("Invalid end of range: {}", end))?;
+    if end < start {
+        return Err(format!("Range ends before it starts: {}", s));
+    }
+    Ok(start..end)
+}
+
+/// Sleep until `dir` contains fewer than `backlog` tests
+fn wait_for_backlog(dir: &Path, backlog: usize) -> Result<()> {
+    loop {
+        let tests = fs::read_dir(dir)
+            .context("Couldn't read output directory")?
+            .filter_map(|e| e.ok())
+            .filter(|e| !e.file_name().to_string_lossy().starts_with('.'))
+            .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+            .count();
+        if tests < backlog {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
 }
 
-fn parse(language: tree_sitter::Language, code: &str) -> Result<tree_sitter::Tree> {
+fn parse(language: tree_sitter::Language, code: &[u8]) -> Result<tree_sitter::Tree> {
     let mut parser = tree_sitter::Parser::new();
     parser
         .set_language(language)
@@ -121,10 +1318,10 @@ fn parse(language: tree_sitter::Language, code: &str) -> Result<tree_sitter::Tre
 }
 
 #[inline]
-fn stdin_string() -> Result<String> {
-    let mut stdin_str: String = String::new();
-    io::stdin().read_to_string(&mut stdin_str)?;
-    Ok(stdin_str)
+fn stdin_bytes() -> Result<Vec<u8>> {
+    let mut stdin_bytes = Vec::new();
+    io::stdin().read_to_end(&mut stdin_bytes)?;
+    Ok(stdin_bytes)
 }
 
 #[inline]
@@ -139,58 +1336,611 @@ fn log_tracing_level(level: &log::Level) -> tracing::Level {
 }
 
 #[inline]
-fn init_tracing(args: &Args) {
+fn init_tracing(common: &Common) {
     let builder = tracing_subscriber::fmt::fmt()
         .with_span_events(FmtSpan::ENTER | FmtSpan::CLOSE)
         .with_target(false)
+        .with_writer(|| progress::Stderr)
         .with_max_level(log_tracing_level(
-            &args.verbose.log_level().unwrap_or(log::Level::Info),
+            &common.verbose.log_level().unwrap_or(log::Level::Info),
         ));
     builder.event_format(formatter::TerseFormatter).init();
 }
 
-pub fn main(language: tree_sitter::Language, node_types_json_str: &'static str) -> Result<()> {
-    let args = Args::parse();
+/// The value of `--config`, if any, found before clap parses the arguments
+fn config_path(args: impl Iterator<Item = OsString>) -> Option<PathBuf> {
+    let mut args = args.skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|a| a.strip_prefix("--config=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
 
-    init_tracing(&args);
+fn config_scalar(value: toml::Value) -> Result<String> {
+    Ok(match value {
+        toml::Value::String(s) => s,
+        toml::Value::Integer(i) => i.to_string(),
+        toml::Value::Float(f) => f.to_string(),
+        toml::Value::Boolean(b) => b.to_string(),
+        toml::Value::Datetime(d) => d.to_string(),
+        toml::Value::Array(_) | toml::Value::Table(_) => bail!("Expected a single value"),
+    })
+}
 
-    let mut files = HashMap::new();
-    for f in args.files {
-        if f == "-" {
-            let path = "<stdin>".to_string();
-            let s = stdin_string()?;
-            let tree = parse(language, &s)?;
-            handle_parse_errors(&path, &tree, &args.on_parse_error);
-            files.insert(path, (s.into_bytes(), tree));
-        } else {
-            let path = f;
-            let s = read_file(&path)?;
-            let tree = parse(language, &s)?;
-            handle_parse_errors(&path, &tree, &args.on_parse_error);
-            files.insert(path, (s.into_bytes(), tree));
+/// Command-line values for a config file entry: tables become `KEY=VALUE`
+fn config_values(value: toml::Value) -> Result<Vec<String>> {
+    match value {
+        toml::Value::Array(values) => values.into_iter().map(config_scalar).collect(),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(k, v)| Ok(format!("{}={}", k, config_scalar(v)?)))
+            .collect(),
+        value => Ok(vec![config_scalar(value)?]),
+    }
+}
+
+/// Make `values` the default of the option `id` of `command` and of each of
+/// its subcommands that has it. Returns `None` if none has it.
+fn set_default(mut command: clap::Command, id: &str, values: &[String]) -> Option<clap::Command> {
+    let values = || values.iter().map(OsString::from);
+    let has_arg = |c: &clap::Command| c.get_arguments().any(|a| a.get_id() == id);
+    let subcommands: Vec<_> = command
+        .get_subcommands()
+        .filter(|c| has_arg(c))
+        .map(|c| c.get_name().to_string())
+        .collect();
+    if id == "config" || (!has_arg(&command) && subcommands.is_empty()) {
+        return None;
+    }
+    for name in subcommands {
+        command =
+            command.mut_subcommand(name, |c| c.mut_arg(id, |arg| arg.default_values(values())));
+    }
+    if has_arg(&command) {
+        command = command.mut_arg(id, |arg| arg.default_values(values()));
+    }
+    Some(command)
+}
+
+/// Parse the command-line arguments, taking defaults from the `--config` file.
+///
+/// Works for any parser that flattens [`Args`]. Values apply to every
+/// subcommand that has the option.
+pub fn parse_args<P: Parser>() -> Result<P> {
+    parse_args_with_defaults(&[])
+}
+
+/// Like [`parse_args`], but first replace the defaults of some options, e.g.,
+/// `("chaos", "20")`. The `--config` file overrides these.
+///
+/// # Errors
+///
+/// If there's no such option.
+pub fn parse_args_with_defaults<P: Parser>(defaults: &[(&str, &'static str)]) -> Result<P> {
+    let defaults: Vec<_> = defaults
+        .iter()
+        .map(|(key, value)| (*key, vec![value.to_string()]))
+        .collect();
+    parse_args_with_lists("", &defaults)
+}
+
+/// Like [`parse_args_with_defaults`], for options that take several values.
+/// `profile` names the [`LanguageProfile`] they come from, if any.
+fn parse_args_with_lists<P: Parser>(profile: &str, defaults: &[(&str, Vec<String>)]) -> Result<P> {
+    let mut command = P::command();
+    for (key, values) in defaults {
+        let id = key.replace('-', "_");
+        command = set_default(command, &id, values).with_context(|| {
+            if profile.is_empty() {
+                format!("Unknown option {} in defaults", key)
+            } else {
+                format!("Unknown option {} in the {} profile", key, profile)
+            }
+        })?;
+    }
+    if let Some(path) = config_path(std::env::args_os()) {
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Couldn't read config file {}", path.display()))?;
+        let table: toml::Table = text
+            .parse()
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        for (key, value) in table {
+            let id = key.replace('-', "_");
+            let values = config_values(value)
+                .with_context(|| format!("Invalid {} in {}", key, path.display()))?;
+            command = set_default(command, &id, &values)
+                .with_context(|| format!("Unknown option {} in {}", key, path.display()))?;
+        }
+    }
+    Ok(P::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit()))
+}
+
+pub fn main(language: tree_sitter::Language, node_types_json_str: &str) -> Result<()> {
+    main_with_injections(language, node_types_json_str, Vec::new())
+}
+
+/// Like [`main`], but with defaults for the language.
+pub fn main_with_profile(
+    language: tree_sitter::Language,
+    node_types_json_str: &str,
+    profile: &LanguageProfile,
+) -> Result<()> {
+    let mut command =
+        parse_args_with_lists::<Args>(profile.name, &profile.option_defaults())?.into_command();
+    if let Some(common) = command.common_mut() {
+        if common.include.is_empty() {
+            common.include = profile.include()?;
         }
     }
+    run_command(command, language, node_types_json_str, Vec::new())
+}
+
+/// Like [`main`], but with other defaults for some options, see
+/// [`parse_args_with_defaults`].
+pub fn main_with_defaults(
+    language: tree_sitter::Language,
+    node_types_json_str: &str,
+    defaults: &'static [(&'static str, &'static str)],
+) -> Result<()> {
+    main_with_profile(
+        language,
+        node_types_json_str,
+        &LanguageProfile {
+            defaults,
+            ..LanguageProfile::default()
+        },
+    )
+}
+
+/// Like [`main`], but also mutate code embedded in the input files.
+pub fn main_with_injections(
+    language: tree_sitter::Language,
+    node_types_json_str: &str,
+    injections: Vec<Injection>,
+) -> Result<()> {
+    main_with_args(parse_args()?, language, node_types_json_str, injections)
+}
 
-    let node_types = crate::node_types::NodeTypes::new(node_types_json_str)?;
-    let config = Config {
-        chaos: args.chaos,
-        deletions: args.deletions,
+/// Like [`main`], but with arguments that were already parsed, e.g., as part
+/// of a tool's own arguments.
+pub fn main_with_args(
+    args: Args,
+    language: tree_sitter::Language,
+    node_types_json_str: &str,
+    injections: Vec<Injection>,
+) -> Result<()> {
+    run_command(
+        args.into_command(),
         language,
-        // intra_splices: 10,
-        inter_splices: args.mutations,
-        max_size: args.max_size,
-        node_types,
-        reparse: args.reparse,
-        seed: args.seed,
+        node_types_json_str,
+        injections,
+    )
+}
+
+/// Run a subcommand on files in `language`. Injections only apply to
+/// `generate`.
+pub fn run_command(
+    command: Command,
+    language: tree_sitter::Language,
+    node_types_json_str: &str,
+    injections: Vec<Injection>,
+) -> Result<()> {
+    if let Some(dialect) = command.common().and_then(|c| c.dialect.as_ref()) {
+        bail!("Unknown dialect {}, this language has no dialects", dialect);
+    }
+    dispatch(command, language, node_types_json_str, injections)
+}
+
+fn dispatch(
+    command: Command,
+    language: tree_sitter::Language,
+    node_types_json_str: &str,
+    injections: Vec<Injection>,
+) -> Result<()> {
+    match command {
+        Command::Generate(args) => run(args, language, node_types_json_str, injections),
+        Command::GenerateFresh(args) => args.run(language, node_types_json_str),
+        Command::Mutate(args) => args.run(language, node_types_json_str),
+        Command::Analyze(args) => args.run(language),
+        Command::Distill(args) => args.run(language),
+        Command::Refine(args) => args.run(language, node_types_json_str),
+        Command::Replay(args) => args.run(Some(language)),
+    }
+}
+
+/// Like [`main`], but select the grammar with `--dialect`.
+///
+/// The first dialect is the default.
+pub fn main_with_dialects(dialects: &[Dialect]) -> Result<()> {
+    let command = parse_args::<Args>()?.into_command();
+    let dialect = match command.common().and_then(|c| c.dialect.as_ref()) {
+        None => dialects.first().context("No dialects")?,
+        Some(name) => dialects.iter().find(|d| d.name == name).with_context(|| {
+            let names: Vec<_> = dialects.iter().map(|d| d.name).collect();
+            format!(
+                "Unknown dialect {}, expected one of: {}",
+                name,
+                names.join(", ")
+            )
+        })?,
     };
-    std::fs::create_dir_all(&args.output).context("Couldn't create output directory")?;
-    for (i, out) in Splicer::new(config, &files).enumerate() {
-        if i == args.tests {
-            break;
+    dispatch(
+        command,
+        dialect.language,
+        dialect.node_types_json_str,
+        Vec::new(),
+    )
+}
+
+/// Read, normalize, and parse an input file, or stdin if `file` is `-`.
+/// `None` if it's skipped by `--on-invalid-utf8`.
+fn load(
+    common: &Common,
+    language: tree_sitter::Language,
+    file: &str,
+) -> Result<Option<(String, Vec<u8>, Tree)>> {
+    let (path, bytes) = if file == "-" {
+        ("<stdin>".to_string(), stdin_bytes()?)
+    } else {
+        (file.to_string(), read_file(file)?)
+    };
+    let Some(bytes) = handle_invalid_utf8(&path, bytes, &common.on_invalid_utf8)? else {
+        return Ok(None);
+    };
+    let s = normalize_line_endings(bytes, &common.line_endings);
+    let tree = parse(language, &s)?;
+    let (s, tree) = preprocess(common.preprocess(), s, tree);
+    handle_parse_errors(&path, &tree, &common.on_parse_error);
+    Ok(Some((path, s, tree)))
+}
+
+/// [`load`] each file once, by path. Skip symlinks and other paths to files
+/// that are already loaded, and files with the same contents as one that is,
+/// which would be chosen more often. Warn about files that only differ from
+/// one that's loaded in whitespace.
+#[allow(clippy::type_complexity)]
+fn load_corpus(
+    common: &Common,
+    language: tree_sitter::Language,
+    files: &[String],
+) -> Result<HashMap<String, (Vec<u8>, Tree)>> {
+    let mut corpus: HashMap<String, (Vec<u8>, Tree)> = HashMap::with_capacity(files.len());
+    let mut real_paths = HashSet::with_capacity(files.len());
+    let mut by_hash: HashMap<u64, String> = HashMap::with_capacity(files.len());
+    let mut by_spaceless_hash: HashMap<u64, String> = HashMap::with_capacity(files.len());
+    let mut duplicates = 0;
+    for file in files {
+        if file != "-" {
+            let real_path =
+                fs::canonicalize(file).with_context(|| format!("Failed to read file {}", file))?;
+            if !real_paths.insert(real_path) {
+                debug!("Skipping {}, it's already loaded", file);
+                duplicates += 1;
+                continue;
+            }
+        }
+        let Some((path, text, tree)) = load(common, language, file)? else {
+            continue;
+        };
+        let h = hash(&text);
+        if let Some(first) = by_hash.get(&h) {
+            if corpus[first].0 == text {
+                debug!("Skipping {}, it's the same as {}", path, first);
+                duplicates += 1;
+                continue;
+            }
+        }
+        let spaceless: Vec<u8> = text
+            .iter()
+            .copied()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        match by_spaceless_hash.entry(hash(&spaceless)) {
+            Entry::Occupied(first) => warn!(
+                "{} is the same as {} except for whitespace, see --normalize-whitespace",
+                path,
+                first.get()
+            ),
+            Entry::Vacant(e) => {
+                e.insert(path.clone());
+            }
+        }
+        by_hash.entry(h).or_insert_with(|| path.clone());
+        corpus.insert(path, (text, tree));
+    }
+    if duplicates > 0 {
+        info!("Skipped {} duplicate input files", duplicates);
+    }
+    Ok(corpus)
+}
+
+/// Apply `--strip-comments` and `--normalize-whitespace`
+fn preprocess(options: Preprocess, text: Vec<u8>, tree: Tree) -> (Vec<u8>, Tree) {
+    if options.is_noop() {
+        return (text, tree);
+    }
+    preprocess::preprocess(&text, &tree, options)
+}
+
+#[allow(clippy::type_complexity)]
+fn load_donors(
+    common: &Common,
+    splicing: &Splicing,
+    language: tree_sitter::Language,
+) -> Result<Vec<(HashMap<String, (Vec<u8>, Tree)>, u32)>> {
+    let mut donors = Vec::with_capacity(splicing.donors.len());
+    for Donors { dir, weight } in &splicing.donors {
+        let corpus = load_corpus(
+            common,
+            language,
+            &expand_files(common, &[dir.display().to_string()])?,
+        )?;
+        info!(
+            "Loaded {} donor files from {} with weight {}",
+            corpus.len(),
+            dir.display(),
+            weight
+        );
+        donors.push((corpus, *weight));
+    }
+    Ok(donors)
+}
+
+/// The `--index-in`, or else a new index of `files` and `donors`, which is
+/// saved to `--index-out`
+#[allow(clippy::type_complexity)]
+fn load_index(
+    args: &Generate,
+    files: &HashMap<String, (Vec<u8>, Tree)>,
+    donors: &[(HashMap<String, (Vec<u8>, Tree)>, u32)],
+) -> Result<CorpusIndex> {
+    let index = match &args.index_in {
+        Some(path) => {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Couldn't read index {}", path.display()))?;
+            let index: CorpusIndex = serde_json::from_str(&text)
+                .with_context(|| format!("Invalid index {}", path.display()))?;
+            if !index.describes(files, &by_ref(donors)) {
+                bail!(
+                    "Index {} is of other files than the inputs and donors, remake it with --index-out",
+                    path.display()
+                );
+            }
+            index
+        }
+        None => CorpusIndex::new(files, &by_ref(donors)),
+    };
+    if let Some(path) = &args.index_out {
+        fs::write(path, serde_json::to_string(&index)?)
+            .with_context(|| format!("Couldn't write index {}", path.display()))?;
+        info!(
+            "Wrote {} splice candidates to {}",
+            index.len(),
+            path.display()
+        );
+    }
+    Ok(index)
+}
+
+fn run(
+    args: Generate,
+    language: tree_sitter::Language,
+    node_types_json_str: &str,
+    injections: Vec<Injection>,
+) -> Result<()> {
+    init_tracing(&args.common);
+    let started = Instant::now();
+    let events = EventLog::new(args.log_json.as_deref())?;
+    if args.output_format != OutputFormat::Dir && args.backlog.is_some() {
+        bail!("--backlog counts files in the output directory, it needs --output-format dir");
+    }
+    if matches!(args.output_format, OutputFormat::Tar | OutputFormat::Zip) && args.resume {
+        bail!(
+            "--resume can't add to {}, use --output-format dir",
+            args.output_format
+        );
+    }
+    if args.output_format == OutputFormat::Zip && args.continuous {
+        bail!("zip archives are only complete at the end of a run, use --output-format tar");
+    }
+
+    let mut inputs = expand_files(&args.common, &args.files)?;
+    if let Some(max) = args.max_input_bytes {
+        inputs = sample_files(inputs, max, args.splicing.seed)?;
+    }
+    let files = load_corpus(&args.common, language, &inputs)?;
+    if files.is_empty() {
+        bail!("No input files");
+    }
+    if files
+        .values()
+        .all(|(text, _)| text.len() > args.splicing.max_size)
+    {
+        bail!("No input files are at most --max-size bytes");
+    }
+    let donors = load_donors(&args.common, &args.splicing, language)?;
+    let dictionary = args.splicing.dictionary()?;
+
+    let index = load_index(&args, &files, &donors)?;
+
+    let node_types = NodeTypes::new(node_types_json_str)?;
+    let focus = args.splicing.focus(language)?;
+    let rules = args.splicing.rules(language)?;
+    let templates = args.splicing.templates(&node_types)?;
+    let config = |language, node_types| Config {
+        clusters: args.clusters,
+        per_seed: args.per_seed,
+        trace: args.trace_out.is_some() || args.metrics_addr.is_some(),
+        unique_kinds: args.unique_kinds,
+        ..args.splicing.config(language, node_types)
+    };
+    if let Some(path) = &args.dict_out {
+        let mut splicer = Splicer::new_with_index(
+            config(language, node_types.clone()),
+            &files,
+            &by_ref(&donors),
+            &index,
+        )
+        .context("Index is of files in another language")?;
+        args.splicing.add_dictionary(&mut splicer, &dictionary)?;
+        let mut out = String::new();
+        let mut tokens = 0;
+        for (kind, pool) in splicer.harvest() {
+            for (i, token) in pool.iter().enumerate() {
+                out.push_str(&dict::entry(&format!("{}_{}", kind, i), token));
+                out.push('\n');
+                tokens += 1;
+            }
         }
-        std::fs::write(args.output.join(i.to_string()), out)
-            .context("Couldn't save generated test case")?;
+        fs::write(path, out)
+            .with_context(|| format!("Couldn't write dictionary {}", path.display()))?;
+        info!("Wrote {} tokens to {}", tokens, path.display());
+    }
+
+    let mut report = args
+        .report
+        .as_ref()
+        .map(|_| Report::new(files.values().map(|(txt, tree)| (txt.as_slice(), tree))));
+    if let (Some(report), true) = (&mut report, args.clusters > 0) {
+        let mut paths: Vec<_> = files.keys().collect();
+        paths.sort();
+        let trees: Vec<_> = paths.iter().map(|p| &files[*p].1).collect();
+        let clusters = cluster(&trees, args.clusters)
+            .into_iter()
+            .map(|c| c.into_iter().map(|i| paths[i].clone()).collect())
+            .collect();
+        report.clusters(clusters);
+    }
+
+    let tests = if args.continuous {
+        usize::MAX
+    } else if let Some(id) = args.reproduce {
+        id.index.saturating_add(1)
+    } else {
+        args.tests.max(args.per_seed * files.len())
+    };
+    if tests > args.tests && !args.continuous && args.reproduce.is_none() {
+        info!("Making {} tests to satisfy --per-seed", tests);
     }
 
-    Ok(())
+    // With --reproduce, make just the one test, on one thread and at the
+    // first attempt
+    let seed = args.reproduce.map_or(args.splicing.seed, |id| id.seed);
+    let jobs = if args.reproduce.is_some() {
+        1
+    } else {
+        args.jobs.clamp(1, tests.max(1))
+    };
+    let max_attempts = if args.reproduce.is_some() {
+        1
+    } else {
+        args.attempts.get()
+    };
+    let checkpoint = if args.resume {
+        std::fs::create_dir_all(&args.output).context("Couldn't create output directory")?;
+        Some(Checkpoint::load(&args.output, seed, jobs)?)
+    } else {
+        None
+    };
+    events.emit(Event::Start {
+        inputs: files.len(),
+        jobs,
+        seed,
+        tests: (!args.continuous).then_some(tests),
+    })?;
+
+    let outputs = Outputs::new(&args, language, &node_types, &files, seed, &events, report)?;
+    outputs.cover_inputs()?;
+
+    let quiet = args
+        .common
+        .verbose
+        .log_level()
+        .map_or(true, |level| level < log::Level::Info);
+    let progress = Progress::new(
+        args.progress,
+        quiet || args.reproduce.is_some(),
+        (!args.continuous).then_some(tests),
+    );
+    let metrics = Metrics::default();
+    let metrics_listener = args
+        .metrics_addr
+        .map(|addr| {
+            TcpListener::bind(addr).with_context(|| format!("Couldn't serve metrics on {}", addr))
+        })
+        .transpose()?;
+
+    let embedded = injections
+        .iter()
+        .map(|inj| Embedded {
+            injection: inj,
+            config: Config {
+                seed,
+                trace: false,
+                ..config(inj.language, inj.node_types.clone())
+            },
+            files: inj.extract(&files),
+            donors: donors
+                .iter()
+                .map(|(corpus, w)| (inj.extract(corpus), *w))
+                .collect(),
+        })
+        .collect();
+    let worker = Worker {
+        args: &args,
+        language,
+        config: Config {
+            seed,
+            focus,
+            rules,
+            templates,
+            ..config(language, node_types.clone())
+        },
+        files: &files,
+        donors: &donors,
+        index: &index,
+        dictionary: &dictionary,
+        embedded,
+        checkpoint: checkpoint.as_ref(),
+        jobs,
+        tests,
+        max_attempts,
+        progress: &progress,
+        metrics: metrics_listener.as_ref().map(|_| &metrics),
+        outputs: &outputs,
+    };
+
+    let stop_metrics = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        if let Some(listener) = &metrics_listener {
+            let render = || {
+                metrics.render(
+                    outputs.counts(),
+                    outputs.saved(),
+                    outputs.hangs(),
+                    started.elapsed().as_secs_f64(),
+                )
+            };
+            let stop = &stop_metrics;
+            scope.spawn(move || metrics::serve(listener, stop, render));
+        }
+        let made = worker.run_all();
+        stop_metrics.store(true, Ordering::Relaxed);
+        made
+    })?;
+    progress.update(outputs.counts());
+    drop(progress);
+    outputs.finish()?;
+
+    events.emit(Event::Finish {
+        seconds: started.elapsed().as_secs_f64(),
+    })
 }
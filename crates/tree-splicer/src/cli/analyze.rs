@@ -0,0 +1,214 @@
+//! Statistics about a corpus of input files.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use tree_sitter::Language;
+
+use super::report::kinds;
+use super::{expand_files, init_tracing, load, Common};
+use crate::splice::CandidateStats;
+
+/// How many of the most common node kinds to show
+const TOP_KINDS: usize = 10;
+
+/// Width of the longest bar of the size histogram
+const BAR_WIDTH: usize = 40;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Format::Text => write!(f, "text"),
+            Format::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// Print statistics about the input files and their splice candidates, e.g.,
+/// to tell if they're diverse enough
+#[derive(clap::Args, Debug)]
+pub struct Analyze {
+    /// Output format
+    #[arg(long, default_value_t = Format::Text, value_name = "FORMAT")]
+    pub format: Format,
+
+    /// Input files or directories, or quoted glob patterns like
+    /// `'src/**/*.rs'`; use `-` to pass a single file on stdin
+    #[arg(value_name = "FILE", required = true, num_args = 1..)]
+    pub files: Vec<String>,
+
+    #[command(flatten, next_help_heading = "Input options")]
+    pub common: Common,
+}
+
+/// Files with sizes in `min..=max`
+#[derive(Debug, Serialize)]
+struct Bucket {
+    min: usize,
+    max: usize,
+    files: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    files: usize,
+    bytes: usize,
+    smallest: usize,
+    median: usize,
+    largest: usize,
+    /// Number of files of each size, in powers of two
+    sizes: Vec<Bucket>,
+    parse_errors: usize,
+    named_nodes: usize,
+    /// Named node kinds in the grammar
+    grammar_kinds: usize,
+    /// Number of named nodes of each kind
+    kinds: BTreeMap<&'static str, usize>,
+    #[serde(flatten)]
+    candidates: CandidateStats,
+}
+
+/// Buckets from the smallest to the largest of `sizes`, which are sorted
+fn histogram(sizes: &[usize]) -> Vec<Bucket> {
+    // Bucket `b` holds sizes with `b` significant bits
+    let bucket = |size: usize| (usize::BITS - size.leading_zeros()) as usize;
+    let (Some(first), Some(last)) = (sizes.first(), sizes.last()) else {
+        return Vec::new();
+    };
+    (bucket(*first)..=bucket(*last))
+        .map(|b| Bucket {
+            min: if b == 0 { 0 } else { 1 << (b - 1) },
+            max: (1 << b) - 1,
+            files: sizes.iter().filter(|s| bucket(**s) == b).count(),
+        })
+        .collect()
+}
+
+fn human_size(bytes: usize) -> String {
+    if bytes >= 1 << 20 && bytes % (1 << 20) == 0 {
+        format!("{} MiB", bytes >> 20)
+    } else if bytes >= 1 << 10 && bytes % (1 << 10) == 0 {
+        format!("{} KiB", bytes >> 10)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+fn print_text(summary: &Summary) {
+    println!(
+        "Files: {} ({} bytes; smallest {}, median {}, largest {})",
+        summary.files, summary.bytes, summary.smallest, summary.median, summary.largest,
+    );
+    let most = summary.sizes.iter().map(|b| b.files).max().unwrap_or(1);
+    for bucket in &summary.sizes {
+        let range = format!(
+            "{} to {}",
+            human_size(bucket.min),
+            human_size(bucket.max + 1)
+        );
+        let bar = "#".repeat((bucket.files * BAR_WIDTH).div_ceil(most));
+        println!(
+            "  {:<20} {:<w$} {}",
+            range,
+            bar,
+            bucket.files,
+            w = BAR_WIDTH
+        );
+    }
+    println!("Files with parse errors: {}", summary.parse_errors);
+    println!("Named nodes: {}", summary.named_nodes);
+    println!(
+        "Node kinds: {} of {} in the grammar",
+        summary.kinds.len(),
+        summary.grammar_kinds
+    );
+    let mut counts: Vec<_> = summary.kinds.iter().collect();
+    counts.sort_by(|(k1, n1), (k2, n2)| n2.cmp(n1).then(k1.cmp(k2)));
+    println!("Most common kinds:");
+    for (kind, n) in counts.iter().take(TOP_KINDS) {
+        println!("  {:<30} {}", kind, n);
+    }
+
+    let candidates = &summary.candidates;
+    println!(
+        "Splice candidates: {} ({} possible replacements)",
+        candidates.candidates.values().sum::<usize>(),
+        candidates.possible
+    );
+    println!(
+        "Estimated distinct single-splice mutants: {}",
+        candidates.mutants
+    );
+    let mut counts: Vec<_> = candidates
+        .candidates
+        .iter()
+        .filter(|(kind, _)| summary.kinds.contains_key(kind.as_str()))
+        .collect();
+    counts.sort_by(|(k1, n1), (k2, n2)| n2.cmp(n1).then(k1.cmp(k2)));
+    println!("Kinds with the most candidates:");
+    for (kind, n) in counts.iter().take(TOP_KINDS) {
+        println!("  {:<30} {}", kind, n);
+    }
+    println!(
+        "Kinds with only one candidate, which are never spliced: {}",
+        counts.iter().filter(|(_, n)| **n == 1).count()
+    );
+}
+
+impl Analyze {
+    pub(super) fn run(&self, language: Language) -> Result<()> {
+        init_tracing(&self.common);
+        let mut files = Vec::with_capacity(self.files.len());
+        for f in expand_files(&self.common, &self.files)? {
+            if let Some((_, text, tree)) = load(&self.common, language, &f)? {
+                files.push((text, tree));
+            }
+        }
+        if files.is_empty() {
+            bail!("No input files");
+        }
+        let mut sizes: Vec<_> = files.iter().map(|(text, _)| text.len()).collect();
+        sizes.sort_unstable();
+        let mut counts = BTreeMap::new();
+        for (_, tree) in &files {
+            kinds(tree, |kind| *counts.entry(kind).or_default() += 1);
+        }
+        let grammar_kinds = (0..language.node_kind_count())
+            .filter_map(|id| u16::try_from(id).ok())
+            .filter(|id| language.node_kind_is_named(*id) && language.node_kind_is_visible(*id))
+            .filter_map(|id| language.node_kind_for_id(id))
+            .collect::<HashSet<_>>()
+            .len();
+
+        let summary = Summary {
+            files: sizes.len(),
+            bytes: sizes.iter().sum(),
+            smallest: sizes.first().copied().unwrap_or_default(),
+            median: sizes.get(sizes.len() / 2).copied().unwrap_or_default(),
+            largest: sizes.last().copied().unwrap_or_default(),
+            sizes: histogram(&sizes),
+            parse_errors: files
+                .iter()
+                .filter(|(_, tree)| tree.root_node().has_error())
+                .count(),
+            named_nodes: counts.values().sum(),
+            grammar_kinds,
+            kinds: counts,
+            candidates: CandidateStats::new(
+                files.iter().map(|(text, tree)| (text.as_slice(), tree)),
+            ),
+        };
+        match self.format {
+            Format::Text => print_text(&summary),
+            Format::Json => println!("{}", serde_json::to_string_pretty(&summary)?),
+        }
+        Ok(())
+    }
+}
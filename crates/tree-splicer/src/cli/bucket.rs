@@ -0,0 +1,120 @@
+//! Group crashes by where they happened, so that many tests that hit the same
+//! bug don't bury the others.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::index::hash;
+
+/// How many stack frames identify a crash
+const FRAMES: usize = 3;
+
+struct Patterns {
+    /// A frame of a sanitizer report, e.g., `#0 0x4f3a in foo bar.c:3`
+    sanitizer_frame: Regex,
+    /// The kind of bug in a sanitizer report, e.g., `heap-buffer-overflow`
+    sanitizer_error: Regex,
+    /// A report from UBSan, which doesn't say `ERROR:`
+    ubsan: Regex,
+    /// A frame of a Rust backtrace, e.g., `3: foo::bar`
+    rust_frame: Regex,
+    /// E.g., `thread 'main' panicked at src/main.rs:2:5:`
+    rust_panic: Regex,
+    /// E.g., `error: internal compiler error: ...`
+    ice: Regex,
+    /// Addresses, line numbers, symbol hashes, and other numbers that differ
+    /// between runs of the same bug
+    numbers: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        sanitizer_frame: Regex::new(r"(?m)^\s*#\d+ 0x[0-9a-fA-F]+ in (\S+)").unwrap(),
+        sanitizer_error: Regex::new(r"(?m)ERROR: \w+Sanitizer: ([\w-]+)").unwrap(),
+        ubsan: Regex::new(r"(?m): runtime error: |^SUMMARY: UndefinedBehaviorSanitizer").unwrap(),
+        rust_frame: Regex::new(r"(?m)^\s*\d+: (\S+)").unwrap(),
+        rust_panic: Regex::new(r"(?m)panicked at (?:'.*', )?(\S+?):\d+:\d+:?$").unwrap(),
+        ice: Regex::new(r"(?m)^error: internal compiler error: (.*)$").unwrap(),
+        numbers: Regex::new(r"::h[0-9a-f]{16}\b|0x[0-9a-fA-F]+|\d+").unwrap(),
+    })
+}
+
+/// Frames that are about reporting the crash rather than where it happened
+fn is_noise(frame: &str) -> bool {
+    [
+        "__asan",
+        "__sanitizer",
+        "__ubsan",
+        "__msan",
+        "std::panicking",
+        "std::sys",
+        "std::backtrace",
+        "core::panicking",
+        "rust_begin_unwind",
+        "__rust",
+        "abort",
+        "raise",
+    ]
+    .iter()
+    .any(|n| frame.contains(n))
+}
+
+/// Whether `stderr` has a sanitizer report, a Rust panic, or an internal
+/// compiler error, i.e., the target crashed even if it exited normally (as
+/// sanitizers and panics do, with status 1 and 101)
+pub(super) fn is_crash(stderr: &[u8]) -> bool {
+    let stderr = String::from_utf8_lossy(stderr);
+    let p = patterns();
+    p.sanitizer_error.is_match(&stderr)
+        || p.ubsan.is_match(&stderr)
+        || p.rust_panic.is_match(&stderr)
+        || p.ice.is_match(&stderr)
+}
+
+/// What identifies a crash that ended with `outcome` (e.g., `killed by
+/// signal 11`) and `stderr`: the kind of error and the top frames of the
+/// stack, or else the last line of `stderr`, without numbers
+pub(super) fn signature(outcome: &str, stderr: &[u8]) -> String {
+    let stderr = String::from_utf8_lossy(stderr);
+    let p = patterns();
+    let mut parts = vec![outcome.to_string()];
+    if let Some(c) = p.sanitizer_error.captures(&stderr) {
+        parts.push(c[1].to_string());
+    }
+    if let Some(c) = p.ice.captures(&stderr) {
+        parts.push(c[1].to_string());
+    }
+    if let Some(c) = p.rust_panic.captures(&stderr) {
+        parts.push(c[1].to_string());
+    }
+    let frames = if p.sanitizer_frame.is_match(&stderr) {
+        &p.sanitizer_frame
+    } else {
+        &p.rust_frame
+    };
+    parts.extend(
+        frames
+            .captures_iter(&stderr)
+            .map(|c| c[1].to_string())
+            .filter(|f| !is_noise(f))
+            .take(FRAMES),
+    );
+    if parts.len() == 1 {
+        parts.extend(
+            stderr
+                .lines()
+                .rev()
+                .find(|l| !l.trim().is_empty())
+                .map(str::to_string),
+        );
+    }
+    p.numbers.replace_all(&parts.join("\n"), "N").into_owned()
+}
+
+/// A short name for the bucket of crashes with `signature`, the same in every
+/// build so that later runs add to the buckets of earlier ones
+pub(super) fn bucket(signature: &str) -> String {
+    format!("{:016x}", hash(signature.as_bytes()))
+}
@@ -0,0 +1,69 @@
+//! Skip tests that were already made.
+
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+
+use crate::index::hash;
+
+/// Hashes of the tests made so far, and of the inputs
+pub(super) struct Dedup {
+    seen: Mutex<HashSet<u64>>,
+    /// One hash per line, in hex
+    index: Option<Mutex<File>>,
+}
+
+impl Dedup {
+    /// Also read the hashes in `index`, if it exists, and add new ones to it.
+    pub(super) fn new<'a>(
+        index: Option<&Path>,
+        inputs: impl IntoIterator<Item = &'a [u8]>,
+    ) -> Result<Self> {
+        let mut seen: HashSet<_> = inputs.into_iter().map(hash).collect();
+        let index = match index {
+            None => None,
+            Some(path) => {
+                match fs::read_to_string(path) {
+                    Ok(text) => {
+                        for line in text.lines().filter(|l| !l.is_empty()) {
+                            let h = u64::from_str_radix(line, 16).with_context(|| {
+                                format!("Invalid hash {} in {}", line, path.display())
+                            })?;
+                            seen.insert(h);
+                        }
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+                    Err(e) => {
+                        return Err(e).with_context(|| format!("Couldn't read {}", path.display()))
+                    }
+                }
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Couldn't open {}", path.display()))?;
+                Some(Mutex::new(file))
+            }
+        };
+        Ok(Dedup {
+            seen: Mutex::new(seen),
+            index,
+        })
+    }
+
+    /// Whether `test` is new; if so, remember it
+    pub(super) fn insert(&self, test: &[u8]) -> Result<bool> {
+        let h = hash(test);
+        if !self.seen.lock().unwrap().insert(h) {
+            return Ok(false);
+        }
+        if let Some(index) = &self.index {
+            writeln!(index.lock().unwrap(), "{:016x}", h).context("Couldn't update index")?;
+        }
+        Ok(true)
+    }
+}
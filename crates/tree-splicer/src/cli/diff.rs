@@ -0,0 +1,21 @@
+//! Inputs and diffs of tests, to see what changed.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use similar::TextDiff;
+
+/// Save `input`, the text of the file `path` that `test` was made from, to
+/// `dir/name`, and a unified diff from it to the test to `dir/name.diff`.
+pub(super) fn save(dir: &Path, name: &str, path: &str, input: &[u8], test: &[u8]) -> Result<()> {
+    fs::create_dir_all(dir).context("Couldn't create diff directory")?;
+    fs::write(dir.join(name), input).context("Couldn't save input")?;
+    let mut diff = Vec::new();
+    TextDiff::from_lines(input, test)
+        .unified_diff()
+        .header(&format!("a/{}", path), &format!("b/{}", name))
+        .to_writer(&mut diff)
+        .context("Couldn't make diff")?;
+    fs::write(dir.join(format!("{}.diff", name)), diff).context("Couldn't save diff")
+}
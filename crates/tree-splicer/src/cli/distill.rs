@@ -0,0 +1,87 @@
+//! Minimize a corpus of input files.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use tracing::info;
+use tree_sitter::Language;
+
+use super::{expand_files, init_tracing, load, save, Common};
+use crate::distill::distill;
+
+/// Copy a small subset of the input files with (nearly) all of their splice
+/// candidates to a directory, e.g., to make `generate` start faster on a
+/// large corpus
+#[derive(clap::Args, Debug)]
+pub struct Distill {
+    /// Keep files until they have this percent of the splice candidates of
+    /// all of the input files
+    #[arg(long, default_value_t = 100.0, value_name = "PERCENT")]
+    pub coverage: f64,
+
+    /// Directory to copy the files to
+    #[arg(short, long, default_value_os = "tree-splicer.distilled")]
+    pub output: PathBuf,
+
+    /// Input files or directories, or quoted glob patterns like
+    /// `'src/**/*.rs'`
+    #[arg(value_name = "FILE", required = true, num_args = 1..)]
+    pub files: Vec<String>,
+
+    #[command(flatten, next_help_heading = "Input options")]
+    pub common: Common,
+}
+
+impl Distill {
+    pub(super) fn run(&self, language: Language) -> Result<()> {
+        init_tracing(&self.common);
+        if !(0.0..=100.0).contains(&self.coverage) {
+            bail!("--coverage must be between 0 and 100");
+        }
+        let mut files = Vec::with_capacity(self.files.len());
+        for f in expand_files(&self.common, &self.files)? {
+            files.extend(load(&self.common, language, &f)?);
+        }
+        if files.is_empty() {
+            bail!("No input files");
+        }
+        let distilled = distill(
+            &files
+                .iter()
+                .map(|(_, text, tree)| (text.as_slice(), tree))
+                .collect::<Vec<_>>(),
+            self.coverage / 100.0,
+        );
+
+        fs::create_dir_all(&self.output).context("Couldn't create output directory")?;
+        let mut names = HashSet::with_capacity(distilled.files.len());
+        let mut bytes = 0;
+        for (n, i) in distilled.files.iter().enumerate() {
+            let (path, text, _) = &files[*i];
+            let name = Path::new(path)
+                .file_name()
+                .map_or_else(|| "stdin".to_string(), |s| s.to_string_lossy().into_owned());
+            // Files from different directories may have the same name
+            let name = if names.contains(&name) {
+                format!("{:04}.{}", n, name)
+            } else {
+                name
+            };
+            save(&self.output, &name, text).with_context(|| format!("Couldn't save {}", path))?;
+            names.insert(name);
+            bytes += text.len();
+        }
+        info!(
+            "Kept {} of {} files ({} of {} bytes), with {} of {} splice candidates",
+            distilled.files.len(),
+            files.len(),
+            bytes,
+            files.iter().map(|(_, text, _)| text.len()).sum::<usize>(),
+            distilled.covered,
+            distilled.total,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! A JSON Lines log of what happened to each test, see `--log-json`.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+/// Something that happened during a run. `test` is the test number, and
+/// `name` is the file it was saved to, if any.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(super) enum Event<'a> {
+    Start {
+        inputs: usize,
+        jobs: usize,
+        seed: u64,
+        /// `None` with `--continuous`
+        tests: Option<usize>,
+    },
+    /// A test was saved
+    Test {
+        test: usize,
+        /// `SEED:INDEX`, see `--reproduce`
+        id: String,
+        name: &'a str,
+        input: Option<&'a str>,
+        bytes: usize,
+    },
+    /// A test was discarded because it has a parse error
+    Invalid {
+        test: usize,
+    },
+    /// A test was discarded because it was the same as an earlier one
+    Duplicate {
+        test: usize,
+    },
+    /// A test reached new code in the `--exec` command
+    Coverage {
+        test: usize,
+        new_pcs: usize,
+    },
+    /// A test made the `--exec` command crash or match `--regex`
+    Crash {
+        test: usize,
+        name: &'a str,
+        outcome: String,
+        bucket: &'a str,
+        /// Whether it's the first crash in its bucket
+        new: bool,
+    },
+    /// A test made a command time out
+    Hang {
+        test: usize,
+        name: &'a str,
+    },
+    /// The `--exec-a` and `--exec-b` commands behaved differently
+    Difference {
+        test: usize,
+        name: &'a str,
+        a: String,
+        b: String,
+    },
+    /// The `--formatter` has a bug
+    Formatter {
+        test: usize,
+        name: &'a str,
+        finding: String,
+    },
+    /// A crash was reduced, see `--reduce`
+    Reduced {
+        test: usize,
+        name: &'a str,
+        from: usize,
+        to: usize,
+    },
+    Finish {
+        seconds: f64,
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    /// Seconds since the Unix epoch
+    time: f64,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+/// Writes events to a file, or does nothing without one
+#[derive(Default)]
+pub(super) struct EventLog(Option<Mutex<BufWriter<File>>>);
+
+impl EventLog {
+    pub(super) fn new(path: Option<&Path>) -> Result<Self> {
+        let Some(path) = path else {
+            return Ok(EventLog(None));
+        };
+        let file = File::create(path)
+            .with_context(|| format!("Couldn't create event log {}", path.display()))?;
+        Ok(EventLog(Some(Mutex::new(BufWriter::new(file)))))
+    }
+
+    /// Write one line, and flush it so the log is complete if the run is
+    /// interrupted
+    pub(super) fn emit(&self, event: Event<'_>) -> Result<()> {
+        let Some(file) = &self.0 else {
+            return Ok(());
+        };
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let mut line = serde_json::to_vec(&Line { time, event })?;
+        line.push(b'\n');
+        let mut file = file.lock().unwrap();
+        file.write_all(&line)
+            .and_then(|()| file.flush())
+            .context("Couldn't write event log")
+    }
+}
@@ -0,0 +1,403 @@
+//! Run tests against a target program and keep the interesting ones.
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use regex::bytes::Regex;
+
+use super::bucket;
+
+/// How the target finished
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Outcome {
+    /// Exited with a status code
+    Exit(i32),
+    /// Killed by a signal
+    Signal(i32),
+    /// Killed after running too long
+    Timeout,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Outcome::Exit(code) => write!(f, "exited with status {}", code),
+            Outcome::Signal(signal) => write!(f, "killed by signal {}", signal),
+            Outcome::Timeout => write!(f, "timed out"),
+        }
+    }
+}
+
+fn outcome(status: ExitStatus) -> Outcome {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return Outcome::Signal(signal);
+        }
+    }
+    Outcome::Exit(status.code().unwrap_or(-1))
+}
+
+/// Magic numbers at the start of 64- and 32-bit `.sancov` files
+const SANCOV_64: u64 = 0xC0BF_FFFF_FFFF_FF64;
+const SANCOV_32: u64 = 0xC0BF_FFFF_FFFF_FF32;
+
+/// Make sanitizers write `.sancov` files to `dir`, keeping other options.
+fn coverage_env(dir: &Path) -> Vec<(&'static str, String)> {
+    ["ASAN_OPTIONS", "MSAN_OPTIONS", "UBSAN_OPTIONS"]
+        .into_iter()
+        .map(|var| {
+            let mut opts = std::env::var(var).unwrap_or_default();
+            if !opts.is_empty() {
+                opts.push(':');
+            }
+            let _ = write!(opts, "coverage=1:coverage_dir={}", dir.display());
+            (var, opts)
+        })
+        .collect()
+}
+
+/// Read and delete the `.sancov` files in `dir`.
+fn read_sancov(dir: &Path) -> Result<HashSet<u64>> {
+    let mut pcs = HashSet::new();
+    for entry in fs::read_dir(dir).context("Couldn't read coverage directory")? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |e| e != "sancov") {
+            continue;
+        }
+        let bytes = fs::read(&path).context("Couldn't read .sancov file")?;
+        fs::remove_file(&path).context("Couldn't remove .sancov file")?;
+        let (Some(magic), Some(rest)) = (bytes.get(..8), bytes.get(8..)) else {
+            continue;
+        };
+        match u64::from_le_bytes(magic.try_into().unwrap()) {
+            SANCOV_64 => pcs.extend(
+                rest.chunks_exact(8)
+                    .map(|c| u64::from_le_bytes(c.try_into().unwrap())),
+            ),
+            SANCOV_32 => pcs.extend(
+                rest.chunks_exact(4)
+                    .map(|c| u64::from(u32::from_le_bytes(c.try_into().unwrap()))),
+            ),
+            _ => bail!("Not a .sancov file: {}", path.display()),
+        }
+    }
+    Ok(pcs)
+}
+
+/// Kill the process group of `child`, which leads its own group
+fn kill_group(child: &std::process::Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: Sending a signal has no memory-safety preconditions.
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = child;
+}
+
+/// One execution of the target
+pub(super) struct Run {
+    pub(super) outcome: Outcome,
+    /// Program counters reached, if coverage was collected
+    pub(super) coverage: HashSet<u64>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl Run {
+    /// The bucket of crashes that this one is in, see [`bucket::signature`]
+    pub(super) fn bucket(&self) -> String {
+        bucket::bucket(&bucket::signature(&self.outcome.to_string(), &self.stderr))
+    }
+
+    /// Summary to save next to an interesting test
+    pub(super) fn log(&self) -> Vec<u8> {
+        let mut log = String::new();
+        let _ = writeln!(log, "{}", self.outcome);
+        let _ = writeln!(log, "--- stdout");
+        let mut log = log.into_bytes();
+        log.extend(&self.stdout);
+        log.extend(b"\n--- stderr\n");
+        log.extend(&self.stderr);
+        log
+    }
+}
+
+/// A program to run on each test, like an interestingness test
+pub(super) struct Target {
+    program: String,
+    args: Vec<String>,
+    regex: Option<Regex>,
+    timeout: Duration,
+    /// Maximum size of the address space, in bytes
+    memory_limit: Option<u64>,
+}
+
+impl Target {
+    /// `cmd` is split on whitespace. If an argument is `@@`, it's replaced
+    /// with the path to the test, otherwise the test is passed on stdin.
+    pub(super) fn new(cmd: &str, regex: Option<&str>, timeout: Duration) -> Result<Self> {
+        let mut words = cmd.split_whitespace().map(String::from);
+        let Some(program) = words.next() else {
+            bail!("Empty --exec command");
+        };
+        let regex = regex
+            .map(Regex::new)
+            .transpose()
+            .context("Invalid --regex")?;
+        Ok(Target {
+            program,
+            args: words.collect(),
+            regex,
+            timeout,
+            memory_limit: None,
+        })
+    }
+
+    /// Limit the size of the address space of the program to `bytes`, so that
+    /// a test that makes it allocate without bound makes it crash instead.
+    /// Only on Unix.
+    pub(super) fn memory_limit(mut self, bytes: Option<u64>) -> Self {
+        self.memory_limit = bytes;
+        self
+    }
+
+    /// Whether the target takes the test as a file rather than on stdin
+    pub(super) fn takes_file(&self) -> bool {
+        self.args.iter().any(|a| a == "@@")
+    }
+
+    /// Run the target on `test`, which is saved at `path` if [`Target::takes_file`].
+    ///
+    /// With a `coverage_dir`, collect coverage from SanitizerCoverage.
+    pub(super) fn run(&self, test: &[u8], path: &Path, coverage_dir: Option<&Path>) -> Result<Run> {
+        let path = path.display().to_string();
+        let args = self
+            .args
+            .iter()
+            .map(|a| if a == "@@" { path.as_str() } else { a.as_str() });
+        let stdin = if self.takes_file() {
+            Stdio::null()
+        } else {
+            Stdio::piped()
+        };
+        let mut command = Command::new(&self.program);
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // Its own process group, to kill anything it starts on timeout
+            command.process_group(0);
+            if let Some(limit) = self.memory_limit {
+                let limit = libc::rlim_t::try_from(limit).unwrap_or(libc::RLIM_INFINITY);
+                // SAFETY: `setrlimit` is async-signal-safe.
+                unsafe {
+                    command.pre_exec(move || {
+                        let rlimit = libc::rlimit {
+                            rlim_cur: limit,
+                            rlim_max: limit,
+                        };
+                        if libc::setrlimit(libc::RLIMIT_AS, &rlimit) != 0 {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                        Ok(())
+                    });
+                }
+            }
+        }
+        if let Some(dir) = coverage_dir {
+            fs::create_dir_all(dir).context("Couldn't create coverage directory")?;
+            command.envs(coverage_env(dir));
+        }
+        let mut child = command
+            .args(args)
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Couldn't run {}", self.program))?;
+
+        // Use threads so that full pipes can't deadlock
+        let writer = child.stdin.take().map(|mut stdin| {
+            let test = test.to_vec();
+            thread::spawn(move || stdin.write_all(&test))
+        });
+        let read = |pipe: Option<Box<dyn Read + Send>>| {
+            thread::spawn(move || {
+                let mut bytes = Vec::new();
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut bytes);
+                }
+                bytes
+            })
+        };
+        let stdout = read(child.stdout.take().map(|p| Box::new(p) as _));
+        let stderr = read(child.stderr.take().map(|p| Box::new(p) as _));
+
+        let start = Instant::now();
+        let outcome = loop {
+            if let Some(status) = child.try_wait().context("Couldn't wait for target")? {
+                break outcome(status);
+            }
+            if start.elapsed() > self.timeout {
+                kill_group(&child);
+                let _ = child.kill();
+                let _ = child.wait();
+                break Outcome::Timeout;
+            }
+            thread::sleep(Duration::from_millis(5));
+        };
+        // Processes that it started and left running would keep its stdout
+        // and stderr open
+        kill_group(&child);
+        if let Some(writer) = writer {
+            // The target may exit without reading all of its input
+            let _ = writer.join();
+        }
+        let coverage = match coverage_dir {
+            Some(dir) => read_sancov(dir)?,
+            None => HashSet::new(),
+        };
+        Ok(Run {
+            outcome,
+            coverage,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+
+    /// A run is interesting if the target crashed, or if its output matches
+    /// the regex. Besides signals, a crash is a failing exit with a sanitizer
+    /// report, Rust panic, or ICE on stderr, see [`bucket::is_crash`].
+    pub(super) fn interesting(&self, run: &Run) -> bool {
+        match run.outcome {
+            Outcome::Signal(_) => return true,
+            Outcome::Exit(code) if code != 0 && bucket::is_crash(&run.stderr) => return true,
+            _ => (),
+        }
+        self.regex
+            .as_ref()
+            .is_some_and(|r| r.is_match(&run.stdout) || r.is_match(&run.stderr))
+    }
+}
+
+/// Two programs that should behave the same on each test, e.g., two versions
+/// of a compiler
+pub(super) struct Differential {
+    a: Target,
+    b: Target,
+    /// Text to ignore in stdout and stderr
+    ignore: Option<Regex>,
+}
+
+impl Differential {
+    pub(super) fn new(
+        a: &str,
+        b: &str,
+        ignore: Option<&str>,
+        timeout: Duration,
+        memory_limit: Option<u64>,
+    ) -> Result<Self> {
+        let ignore = ignore
+            .map(Regex::new)
+            .transpose()
+            .context("Invalid --diff-ignore")?;
+        Ok(Differential {
+            a: Target::new(a, None, timeout)?.memory_limit(memory_limit),
+            b: Target::new(b, None, timeout)?.memory_limit(memory_limit),
+            ignore,
+        })
+    }
+
+    /// Run both programs on `test`, which is saved at `path` if either takes
+    /// a file.
+    pub(super) fn run(&self, test: &[u8], path: &Path) -> Result<(Run, Run)> {
+        let takes_file = self.a.takes_file() || self.b.takes_file();
+        if takes_file {
+            fs::write(path, test).context("Couldn't save test for --exec-a")?;
+        }
+        let runs = self
+            .a
+            .run(test, path, None)
+            .and_then(|a| Ok((a, self.b.run(test, path, None)?)));
+        if takes_file {
+            let _ = fs::remove_file(path);
+        }
+        runs
+    }
+
+    fn normalize<'o>(&self, output: &'o [u8]) -> std::borrow::Cow<'o, [u8]> {
+        match &self.ignore {
+            Some(regex) => regex.replace_all(output, &b""[..]),
+            None => output.into(),
+        }
+    }
+
+    /// Whether the programs finished differently or printed different output
+    pub(super) fn differ(&self, a: &Run, b: &Run) -> bool {
+        a.outcome != b.outcome
+            || self.normalize(&a.stdout) != self.normalize(&b.stdout)
+            || self.normalize(&a.stderr) != self.normalize(&b.stderr)
+    }
+
+    /// Summary of both runs to save next to a test where they differ
+    pub(super) fn log(a: &Run, b: &Run) -> Vec<u8> {
+        let mut log = b"=== A: ".to_vec();
+        log.extend(a.log());
+        log.extend(b"\n=== B: ");
+        log.extend(b.log());
+        log
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::index::hash;
+
+    /// Run a shell script that reads the test from stdin as the target
+    fn run(script: &str) -> (Target, Run) {
+        let dir = std::env::temp_dir().join(format!("tree-splicer-exec-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.sh", hash(script.as_bytes())));
+        fs::write(&path, script).unwrap();
+        let target = Target::new(
+            &format!("sh {}", path.display()),
+            None,
+            Duration::from_secs(10),
+        )
+        .unwrap();
+        let run = target.run(b"test", &dir.join("test"), None).unwrap();
+        let _ = fs::remove_file(path);
+        (target, run)
+    }
+
+    #[test]
+    fn panic_is_interesting() {
+        let (target, run) = run(concat!(
+            "cat >/dev/null\n",
+            "echo \"thread 'main' panicked at src/main.rs:2:5:\" >&2\n",
+            "echo 'index out of bounds' >&2\n",
+            "exit 101\n",
+        ));
+        assert_eq!(run.outcome, Outcome::Exit(101));
+        assert!(target.interesting(&run));
+    }
+
+    #[test]
+    fn failure_is_not_interesting() {
+        let (target, run) = run("cat >/dev/null\necho 'error: expected `;`' >&2\nexit 1\n");
+        assert_eq!(run.outcome, Outcome::Exit(1));
+        assert!(!target.interesting(&run));
+    }
+}
@@ -0,0 +1,93 @@
+//! Make tests without input files.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use tracing::info;
+use tree_sitter::Language;
+
+use super::{config_values, init_tracing, normalize_line_endings, parse, save, Common};
+use crate::fresh::Generator;
+
+/// Make tests from scratch, using only the grammar's `node-types.json`.
+///
+/// Most of them won't parse, but they can start a corpus for `generate`.
+#[derive(clap::Args, Debug)]
+pub struct GenerateFresh {
+    /// Only expand optional fields and children this many levels deep
+    #[arg(long, default_value_t = 4, value_name = "N")]
+    pub depth: usize,
+
+    /// Directory to output to
+    #[arg(short, long, default_value_os = "tree-splicer.out")]
+    pub output: PathBuf,
+
+    /// Kind of node to make; default = the kind of whole files, e.g.,
+    /// `source_file`
+    #[arg(long, value_name = "KIND")]
+    pub root: Option<String>,
+
+    /// Seed
+    #[arg(short, long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// How many tests to make
+    #[arg(long, default_value_t = 4)]
+    pub tests: usize,
+
+    /// TOML file with text for leaf node kinds, e.g.,
+    /// `identifier = ["x", "y"]`; default = the name of the kind
+    #[arg(long, value_name = "FILE")]
+    pub tokens: Option<PathBuf>,
+
+    #[command(flatten, next_help_heading = "Input options")]
+    pub common: Common,
+}
+
+fn read_tokens(path: &PathBuf) -> Result<HashMap<String, Vec<String>>> {
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Couldn't read tokens {}", path.display()))?;
+    let table: toml::Table = text
+        .parse()
+        .with_context(|| format!("Invalid tokens {}", path.display()))?;
+    table
+        .into_iter()
+        .map(|(kind, value)| {
+            let tokens = config_values(value)
+                .with_context(|| format!("Invalid tokens for {} in {}", kind, path.display()))?;
+            Ok((kind, tokens))
+        })
+        .collect()
+}
+
+impl GenerateFresh {
+    pub(super) fn run(&self, language: Language, node_types_json_str: &str) -> Result<()> {
+        init_tracing(&self.common);
+        let tokens = match &self.tokens {
+            Some(path) => read_tokens(path)?,
+            None => HashMap::new(),
+        };
+        let mut generator = Generator::new(node_types_json_str, tokens, self.depth, self.seed)?;
+        let root = match &self.root {
+            Some(root) => root.clone(),
+            None => generator
+                .root()
+                .context("Couldn't find the kind of whole files, use --root")?
+                .to_string(),
+        };
+        fs::create_dir_all(&self.output).context("Couldn't create output directory")?;
+        let mut parsed = 0;
+        for i in 0..self.tests {
+            let test = normalize_line_endings(generator.generate(&root), &self.common.line_endings);
+            if !parse(language, &test)?.root_node().has_error() {
+                parsed += 1;
+            }
+            save(&self.output, &i.to_string(), &test)
+                .context("Couldn't save generated test case")?;
+        }
+        info!("{} of {} tests parse without errors", parsed, self.tests);
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+//! Counters of a run for Prometheus, see `--metrics-addr`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tracing::debug;
+
+use super::progress::Counts;
+use crate::trace::Trace;
+
+/// What the metrics say, besides the [`Counts`]
+#[derive(Debug, Default)]
+pub(super) struct Metrics {
+    /// Mutations of each [`crate::trace::Operation`], in tests that were
+    /// saved or discarded
+    mutations: Mutex<BTreeMap<String, u64>>,
+}
+
+impl Metrics {
+    /// Count the mutations that made a test
+    pub(super) fn record(&self, trace: &Trace) {
+        let mut mutations = self.mutations.lock().unwrap();
+        for step in &trace.steps {
+            if let Some(edit) = step.first() {
+                *mutations.entry(edit.operation.to_string()).or_default() += 1;
+            }
+        }
+    }
+
+    /// The metrics in Prometheus' text format
+    pub(super) fn render(
+        &self,
+        counts: Counts,
+        saved: usize,
+        hangs: usize,
+        seconds: f64,
+    ) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, f64)]| {
+            let _ = writeln!(out, "# HELP tree_splicer_{} {}", name, help);
+            let _ = writeln!(out, "# TYPE tree_splicer_{} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "tree_splicer_{}{} {}", name, labels, value);
+            }
+        };
+        let one = |n: usize| [(String::new(), n as f64)];
+        metric(
+            "tests_total",
+            "counter",
+            "Tests made, including discarded ones",
+            &one(counts.done),
+        );
+        metric("saved_tests_total", "counter", "Tests saved", &one(saved));
+        metric(
+            "invalid_tests_total",
+            "counter",
+            "Tests discarded for parse errors",
+            &one(counts.invalid),
+        );
+        metric(
+            "duplicate_tests_total",
+            "counter",
+            "Tests discarded as duplicates",
+            &one(counts.duplicates),
+        );
+        if let Some(crashes) = counts.crashes {
+            metric(
+                "crashes",
+                "gauge",
+                "Distinct crashes of the --exec command",
+                &one(crashes),
+            );
+        }
+        metric(
+            "hangs_total",
+            "counter",
+            "Tests that made a command time out",
+            &one(hangs),
+        );
+        let mutations: Vec<_> = self
+            .mutations
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(op, n)| (format!("{{operation=\"{}\"}}", op), *n as f64))
+            .collect();
+        metric(
+            "mutations_total",
+            "counter",
+            "Mutations by operation",
+            &mutations,
+        );
+        metric(
+            "elapsed_seconds",
+            "gauge",
+            "Seconds since the run started",
+            &[(String::new(), seconds)],
+        );
+        if let Some(bytes) = resident_memory() {
+            metric(
+                "resident_memory_bytes",
+                "gauge",
+                "Resident memory",
+                &one(bytes),
+            );
+        }
+        out
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<usize> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: `sysconf` has no memory-safety preconditions.
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * usize::try_from(page_size).ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<usize> {
+    None
+}
+
+/// Answer every HTTP request on `listener` with `render()` until `stop`
+pub(super) fn serve(listener: &TcpListener, stop: &AtomicBool, render: impl Fn() -> String) {
+    if let Err(e) = listener.set_nonblocking(true) {
+        debug!("Not serving metrics: {}", e);
+        return;
+    }
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = respond(stream, &render()) {
+                    debug!("Couldn't serve metrics: {}", e);
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+            Err(e) => debug!("Couldn't accept metrics connection: {}", e),
+        }
+    }
+}
+
+fn respond(mut stream: TcpStream, body: &str) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    // Read the request up to the blank line, whatever it is
+    let mut request: Vec<u8> = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let n = stream.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        request.extend(&buf[..n]);
+    }
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
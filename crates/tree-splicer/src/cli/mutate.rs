@@ -0,0 +1,72 @@
+//! Mutate a single file.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use tree_sitter::Language;
+
+use super::{by_ref, init_tracing, load, load_donors, normalize_line_endings, Common, Splicing};
+use crate::node_types::NodeTypes;
+use crate::splice::{Config, Splicer};
+
+/// How many times to try to change the file before giving up
+const ATTEMPTS: usize = 16;
+
+/// Mutate one file, taking splice candidates from it and from `--donors`.
+///
+/// Fails if the file couldn't be changed, e.g., if it's empty.
+#[derive(clap::Args, Debug)]
+pub struct Mutate {
+    /// Write the mutant to this file; default = stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// File to mutate; default = stdin
+    #[arg(value_name = "FILE", default_value = "-")]
+    pub file: String,
+
+    #[command(flatten, next_help_heading = "Input options")]
+    pub common: Common,
+
+    #[command(flatten, next_help_heading = "Mutation options")]
+    pub splicing: Splicing,
+}
+
+impl Mutate {
+    pub(super) fn run(&self, language: Language, node_types_json_str: &str) -> Result<()> {
+        init_tracing(&self.common);
+        let (path, text, tree) =
+            load(&self.common, language, &self.file)?.context("No input, it was skipped")?;
+        let donors = load_donors(&self.common, &self.splicing, language)?;
+        let dictionary = self.splicing.dictionary()?;
+        let node_types = NodeTypes::new(node_types_json_str)?;
+        let config = Config {
+            focus: self.splicing.focus(language)?,
+            rules: self.splicing.rules(language)?,
+            templates: self.splicing.templates(&node_types)?,
+            ..self.splicing.config(language, node_types)
+        };
+        let mut files = HashMap::with_capacity(1);
+        files.insert(path.clone(), (text, tree));
+        let (text, tree) = files.values().next().unwrap();
+        let mut splicer = Splicer::new_with_weighted_donors(config, &files, &by_ref(&donors));
+        self.splicing.add_dictionary(&mut splicer, &dictionary)?;
+        // Splices often replace a node with identical text, so try again
+        let Some(mutant) = (0..ATTEMPTS)
+            .filter_map(|_| splicer.splice_tree(text, tree.clone()))
+            .find(|mutant| mutant != text)
+        else {
+            bail!("No mutation was possible for {}", path);
+        };
+        let mutant = normalize_line_endings(mutant, &self.common.line_endings);
+        match &self.output {
+            Some(path) => fs::write(path, mutant).context("Couldn't write mutant"),
+            None => std::io::stdout()
+                .write_all(&mutant)
+                .context("Couldn't write mutant"),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Names of output files.
+
+use std::path::Path;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    /// Test number, zero-padded to this width
+    Number(usize),
+    Stem,
+    Extension,
+}
+
+/// How to name tests, e.g., `{stem}.{i:04}{ext}` for `foo.0007.rs`.
+///
+/// `{i}` is the test number, `{stem}` is the name of the input file without
+/// its extension, and `{ext}` is its extension with a leading `.`, if it has
+/// one. `{i:0N}` pads the test number with zeros to N digits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTemplate {
+    parts: Vec<Part>,
+}
+
+impl std::str::FromStr for NameTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("Unclosed {{ in {}", s))?;
+            let part = match &rest[start + 1..start + end] {
+                "i" => Part::Number(0),
+                "stem" => Part::Stem,
+                "ext" => Part::Extension,
+                p => match p.strip_prefix("i:0").map(str::parse) {
+                    Some(Ok(width)) => Part::Number(width),
+                    _ => return Err(format!("Unknown placeholder {{{}}} in {}", p, s)),
+                },
+            };
+            parts.push(part);
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        if !parts.iter().any(|p| matches!(p, Part::Number(_))) {
+            return Err(format!("Template needs a test number {{i}}: {}", s));
+        }
+        Ok(NameTemplate { parts })
+    }
+}
+
+impl std::fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => write!(f, "{}", text)?,
+                Part::Number(0) => write!(f, "{{i}}")?,
+                Part::Number(width) => write!(f, "{{i:0{}}}", width)?,
+                Part::Stem => write!(f, "{{stem}}")?,
+                Part::Extension => write!(f, "{{ext}}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NameTemplate {
+    /// Name test number `i`, made from `input` if it's known
+    pub(super) fn name(&self, i: usize, input: Option<&str>) -> String {
+        let path = input.filter(|p| *p != "<stdin>").map(Path::new);
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => name.push_str(text),
+                Part::Number(width) => name.push_str(&format!("{:0w$}", i, w = width)),
+                Part::Stem => name.push_str(
+                    &path
+                        .and_then(Path::file_stem)
+                        .map_or("test".into(), |s| s.to_string_lossy()),
+                ),
+                Part::Extension => {
+                    if let Some(ext) = path.and_then(Path::extension) {
+                        name.push('.');
+                        name.push_str(&ext.to_string_lossy());
+                    }
+                }
+            }
+        }
+        name
+    }
+}
@@ -0,0 +1,101 @@
+//! Check that a code formatter is idempotent and produces code that parses.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use tree_sitter::Language;
+
+/// A bug in a formatter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Finding {
+    /// Formatting the formatted code changed it again
+    NotIdempotent,
+    /// The formatted code doesn't parse
+    ParseError,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Finding::NotIdempotent => write!(f, "formatter is not idempotent"),
+            Finding::ParseError => write!(f, "formatted code doesn't parse"),
+        }
+    }
+}
+
+/// A formatter that reads code on stdin and prints the formatted code
+pub(super) struct Formatter {
+    program: String,
+    args: Vec<String>,
+    language: Language,
+    reparse: bool,
+}
+
+impl Formatter {
+    /// `cmd` is split on whitespace, e.g., `rustfmt --edition 2021`.
+    pub(super) fn new(cmd: &str, language: Language, reparse: bool) -> Result<Self> {
+        let mut words = cmd.split_whitespace().map(String::from);
+        let Some(program) = words.next() else {
+            bail!("Empty formatter command");
+        };
+        Ok(Formatter {
+            program,
+            args: words.collect(),
+            language,
+            reparse,
+        })
+    }
+
+    /// `None` if the formatter rejects the code
+    fn format(&self, code: &[u8]) -> Result<Option<Vec<u8>>> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("Couldn't run formatter {}", self.program))?;
+        let mut stdin = child
+            .stdin
+            .take()
+            .context("Couldn't open formatter stdin")?;
+        // Write from another thread so that a full stdout pipe can't deadlock
+        let code = code.to_vec();
+        let writer = std::thread::spawn(move || stdin.write_all(&code));
+        let output = child
+            .wait_with_output()
+            .context("Couldn't wait for formatter")?;
+        // The formatter may exit without reading all of its input
+        let _ = writer.join();
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(Some(output.stdout))
+    }
+
+    fn has_error(&self, code: &[u8]) -> Result<bool> {
+        Ok(super::parse(self.language, code)?.root_node().has_error())
+    }
+
+    /// Format `code`, then format the result, and check both.
+    ///
+    /// Code that the formatter rejects is not a finding.
+    pub(super) fn check(&self, code: &[u8]) -> Result<Option<Finding>> {
+        let Some(once) = self.format(code)? else {
+            return Ok(None);
+        };
+        // Only blame the formatter for parse errors that weren't in its input
+        if self.reparse && !self.has_error(code)? && self.has_error(&once)? {
+            return Ok(Some(Finding::ParseError));
+        }
+        let Some(twice) = self.format(&once)? else {
+            // Rejecting its own output is a bug, too
+            return Ok(Some(Finding::NotIdempotent));
+        };
+        if once != twice {
+            return Ok(Some(Finding::NotIdempotent));
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,584 @@
+//! What `generate` does with each test it makes: check it, run it with
+//! `--exec` and the other oracles, and save it.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+use tree_sitter::{Language, Tree};
+
+use super::dedup::Dedup;
+use super::events::{Event, EventLog};
+use super::exec::{Differential, Outcome, Run, Target};
+use super::oracle::Formatter;
+use super::progress::Counts;
+use super::rate::Limiter;
+use super::reduce::{reduced_name, Reducer};
+use super::replay::TraceFile;
+use super::report::Report;
+use super::resume::DEDUP_INDEX;
+use super::sink::Sink;
+use super::{diff, parse, save, wait_for_backlog, Generate, OnInvalidUtf8, TestId, Validate};
+use crate::node_types::NodeTypes;
+use crate::trace::Trace;
+
+/// A test that a splicer made, and where it came from
+pub(super) struct Made<'a> {
+    pub(super) out: Vec<u8>,
+    pub(super) trace: Option<Trace>,
+    /// The input file it was made from, see [`crate::Splicer::input`]
+    pub(super) input: Option<&'a str>,
+    /// Its index in the splicer, for `--reproduce`
+    pub(super) index: usize,
+    pub(super) valid: bool,
+    pub(super) duplicate: bool,
+}
+
+/// The oracles and destinations of tests, shared by the jobs, and what they
+/// found so far
+pub(super) struct Outputs<'a> {
+    args: &'a Generate,
+    language: Language,
+    node_types: &'a NodeTypes,
+    files: &'a HashMap<String, (Vec<u8>, Tree)>,
+    seed: u64,
+    events: &'a EventLog,
+    limiter: Mutex<Option<Limiter>>,
+    report: Mutex<Option<Report>>,
+    formatter: Option<Formatter>,
+    target: Option<Target>,
+    differential: Option<Differential>,
+    dedup: Option<Dedup>,
+    sink: Sink,
+    findings_dir: PathBuf,
+    logs_dir: PathBuf,
+    hangs_dir: PathBuf,
+    crashes_dir: PathBuf,
+    /// One test per crash bucket, including those from earlier runs
+    buckets: Mutex<HashSet<String>>,
+    /// PCs that the tests reached so far, with `--coverage-guided`
+    coverage: Mutex<HashSet<u64>>,
+    /// Tests that found new coverage, for the splicers of all jobs
+    #[allow(clippy::type_complexity)]
+    pub(super) discovered: Mutex<Vec<Arc<(Vec<u8>, Tree)>>>,
+    done: AtomicUsize,
+    invalid: AtomicUsize,
+    duplicates: AtomicUsize,
+    findings: AtomicUsize,
+    hangs: AtomicUsize,
+    kept: AtomicUsize,
+    duplicate_crashes: AtomicUsize,
+    saved: AtomicUsize,
+}
+
+impl<'a> Outputs<'a> {
+    pub(super) fn new(
+        args: &'a Generate,
+        language: Language,
+        node_types: &'a NodeTypes,
+        files: &'a HashMap<String, (Vec<u8>, Tree)>,
+        seed: u64,
+        events: &'a EventLog,
+        report: Option<Report>,
+    ) -> Result<Self> {
+        let formatter = args
+            .formatter
+            .as_deref()
+            .map(|cmd| Formatter::new(cmd, language, args.formatter_reparse))
+            .transpose()?;
+        let memory_limit = args.memory_limit.map(|mib| mib.saturating_mul(1 << 20));
+        let target = args
+            .exec
+            .as_deref()
+            .map(|cmd| {
+                Target::new(
+                    cmd,
+                    args.regex.as_deref(),
+                    Duration::from_secs(args.timeout),
+                )
+                .map(|target| target.memory_limit(memory_limit))
+            })
+            .transpose()?;
+        let differential = match (&args.exec_a, &args.exec_b) {
+            (Some(a), Some(b)) => Some(Differential::new(
+                a,
+                b,
+                args.diff_ignore.as_deref(),
+                Duration::from_secs(args.timeout),
+                memory_limit,
+            )?),
+            _ => None,
+        };
+        let crashes_dir = args.output.join("crashes");
+        let buckets = std::fs::read_dir(&crashes_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        let dedup = args
+            .dedup
+            .then(|| {
+                let index = args
+                    .dedup_index
+                    .clone()
+                    .or_else(|| args.resume.then(|| args.output.join(DEDUP_INDEX)));
+                Dedup::new(
+                    index.as_deref(),
+                    files.values().map(|(text, _)| text.as_slice()),
+                )
+            })
+            .transpose()?;
+        std::fs::create_dir_all(&args.output).context("Couldn't create output directory")?;
+        let sink = Sink::new(args.output_format, &args.output)?;
+        Ok(Outputs {
+            args,
+            language,
+            node_types,
+            files,
+            seed,
+            events,
+            limiter: Mutex::new(args.rate.map(Limiter::new)),
+            report: Mutex::new(report),
+            formatter,
+            target,
+            differential,
+            dedup,
+            sink,
+            findings_dir: args.output.join("findings"),
+            logs_dir: args.output.join("logs"),
+            hangs_dir: args.output.join("hangs"),
+            crashes_dir,
+            buckets: Mutex::new(buckets),
+            coverage: Mutex::new(HashSet::new()),
+            discovered: Mutex::new(Vec::new()),
+            done: AtomicUsize::new(0),
+            invalid: AtomicUsize::new(0),
+            duplicates: AtomicUsize::new(0),
+            findings: AtomicUsize::new(0),
+            hangs: AtomicUsize::new(0),
+            kept: AtomicUsize::new(0),
+            duplicate_crashes: AtomicUsize::new(0),
+            saved: AtomicUsize::new(0),
+        })
+    }
+
+    /// With `--coverage-guided`, start from the coverage of the inputs
+    pub(super) fn cover_inputs(&self) -> Result<()> {
+        let (Some(target), true) = (&self.target, self.args.coverage_guided) else {
+            return Ok(());
+        };
+        let dir = self.args.output.join(".coverage");
+        let mut paths: Vec<_> = self.files.keys().collect();
+        paths.sort();
+        let mut covered = self.coverage.lock().unwrap();
+        for path in paths {
+            let test = &self.files[path].0;
+            let exec_path = self.args.output.join(".seed.exec");
+            if target.takes_file() {
+                std::fs::write(&exec_path, test).context("Couldn't save test for --exec")?;
+            }
+            let run = target.run(test, &exec_path, Some(&dir));
+            if target.takes_file() {
+                let _ = std::fs::remove_file(&exec_path);
+            }
+            covered.extend(run?.coverage);
+        }
+        if covered.is_empty() {
+            warn!("No coverage from inputs, was the command built with SanitizerCoverage?");
+        } else {
+            info!("Inputs cover {} PCs", covered.len());
+        }
+        Ok(())
+    }
+
+    pub(super) fn counts(&self) -> Counts {
+        Counts {
+            done: self.done.load(Ordering::Relaxed),
+            invalid: self.invalid.load(Ordering::Relaxed),
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            crashes: self
+                .target
+                .as_ref()
+                .map(|_| self.buckets.lock().unwrap().len()),
+        }
+    }
+
+    pub(super) fn saved(&self) -> usize {
+        self.saved.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn hangs(&self) -> usize {
+        self.hangs.load(Ordering::Relaxed)
+    }
+
+    /// Whether `out` parses, if that matters for `--validate`, and whether
+    /// it's a duplicate, with `--dedup`
+    pub(super) fn check(&self, out: &[u8]) -> Result<(bool, bool)> {
+        let valid = self.args.validate == Validate::Keep
+            || !parse(self.language, out)?.root_node().has_error();
+        let duplicate = match &self.dedup {
+            Some(dedup) if valid => !dedup.insert(out)?,
+            _ => false,
+        };
+        Ok((valid, duplicate))
+    }
+
+    /// Run the oracles on test number `i`, and save it unless `--exec` or
+    /// `--exec-a` find it uninteresting. With `--coverage-guided`, the
+    /// target writes its coverage to `coverage_dir`.
+    pub(super) fn handle(
+        &self,
+        i: usize,
+        made: Made<'_>,
+        coverage_dir: Option<&Path>,
+    ) -> Result<()> {
+        let Made {
+            out,
+            trace,
+            input,
+            index,
+            valid,
+            duplicate,
+        } = made;
+        self.done.fetch_add(1, Ordering::Relaxed);
+        if !valid {
+            debug!("Test {}: discarded, parse error", i);
+            self.invalid.fetch_add(1, Ordering::Relaxed);
+            return self.events.emit(Event::Invalid { test: i });
+        }
+        if duplicate {
+            debug!("Test {}: discarded, duplicate", i);
+            self.duplicates.fetch_add(1, Ordering::Relaxed);
+            return self.events.emit(Event::Duplicate { test: i });
+        }
+        // Sleep after releasing the lock, so that other jobs can take their
+        // turns in the meantime
+        let delay = self.limiter.lock().unwrap().as_mut().map(Limiter::delay);
+        if let Some(delay) = delay {
+            std::thread::sleep(delay);
+        }
+        let args = self.args;
+        let name = args
+            .name_template
+            .name(args.rotate.map_or(i, |n| i % n.get()), input);
+        let id = TestId {
+            seed: self.seed,
+            index,
+        };
+        debug!("Test {}: {}, see --reproduce", i, id);
+        if let Some(report) = self.report.lock().unwrap().as_mut() {
+            report.record(&out, &parse(self.language, &out)?);
+        }
+        self.check_formatter(i, &name, &out)?;
+        if !self.run_target(i, &name, id, &out, coverage_dir)? {
+            return Ok(());
+        }
+        if !self.run_differential(i, &name, &out)? {
+            return Ok(());
+        }
+        self.save(i, &name, id, &out, input, trace)
+    }
+
+    /// With `--formatter`, save `out` to the findings if formatting it is
+    /// buggy
+    fn check_formatter(&self, i: usize, name: &str, out: &[u8]) -> Result<()> {
+        let Some(formatter) = &self.formatter else {
+            return Ok(());
+        };
+        if let Some(finding) = formatter.check(out)? {
+            warn!("Test {}: {}", i, finding);
+            self.findings.fetch_add(1, Ordering::Relaxed);
+            self.events.emit(Event::Formatter {
+                test: i,
+                name,
+                finding: finding.to_string(),
+            })?;
+            std::fs::create_dir_all(&self.findings_dir)
+                .context("Couldn't create findings directory")?;
+            std::fs::write(self.findings_dir.join(name), out).context("Couldn't save finding")?;
+        }
+        Ok(())
+    }
+
+    /// Tests that time out go in their own directory, with their logs
+    fn save_hang(&self, i: usize, name: &str, out: &[u8], log: Vec<u8>) -> Result<()> {
+        warn!("Test {}: timed out", i);
+        self.hangs.fetch_add(1, Ordering::Relaxed);
+        self.events.emit(Event::Hang { test: i, name })?;
+        std::fs::create_dir_all(&self.hangs_dir).context("Couldn't create hangs directory")?;
+        save(&self.hangs_dir, name, out).context("Couldn't save hang")?;
+        self.save_log(name, log)
+    }
+
+    fn save_log(&self, name: &str, log: Vec<u8>) -> Result<()> {
+        std::fs::create_dir_all(&self.logs_dir).context("Couldn't create logs directory")?;
+        std::fs::write(self.logs_dir.join(name), log).context("Couldn't save log")
+    }
+
+    /// With `--exec`, run the target on `out`, keep track of its coverage,
+    /// and save a new crash to its bucket. Whether to keep the test.
+    fn run_target(
+        &self,
+        i: usize,
+        name: &str,
+        id: TestId,
+        out: &[u8],
+        coverage_dir: Option<&Path>,
+    ) -> Result<bool> {
+        let Some(target) = &self.target else {
+            return Ok(true);
+        };
+        let path = self.args.output.join(format!(".{}.exec", i));
+        if target.takes_file() {
+            std::fs::write(&path, out).context("Couldn't save test for --exec")?;
+        }
+        let run = target.run(out, &path, coverage_dir);
+        if target.takes_file() {
+            let _ = std::fs::remove_file(&path);
+        }
+        let run = run?;
+        let new_pcs = {
+            let mut covered = self.coverage.lock().unwrap();
+            let before = covered.len();
+            covered.extend(&run.coverage);
+            covered.len() - before
+        };
+        if new_pcs > 0 {
+            info!("Test {}: {} new PCs", i, new_pcs);
+            self.events.emit(Event::Coverage { test: i, new_pcs })?;
+            let tree = parse(self.language, out)?;
+            self.discovered
+                .lock()
+                .unwrap()
+                .push(Arc::new((out.to_vec(), tree)));
+        }
+        if run.outcome == Outcome::Timeout {
+            self.save_hang(i, name, out, run.log())?;
+            return Ok(false);
+        }
+        let interesting = target.interesting(&run);
+        if !interesting && new_pcs == 0 {
+            debug!("Test {}: {}", i, run.outcome);
+            return Ok(false);
+        }
+        let bucket = interesting.then(|| run.bucket());
+        let new_crash = bucket
+            .as_ref()
+            .is_some_and(|b| self.buckets.lock().unwrap().insert(b.clone()));
+        if let Some(bucket) = &bucket {
+            self.events.emit(Event::Crash {
+                test: i,
+                name,
+                outcome: run.outcome.to_string(),
+                bucket,
+                new: new_crash,
+            })?;
+        }
+        if interesting && !new_crash && new_pcs == 0 {
+            debug!(
+                "Test {}: {}, like crash {}",
+                i,
+                run.outcome,
+                bucket.unwrap_or_default()
+            );
+            self.duplicate_crashes.fetch_add(1, Ordering::Relaxed);
+            return Ok(false);
+        }
+        info!("Test {}: {}", i, run.outcome);
+        self.kept.fetch_add(1, Ordering::Relaxed);
+        self.save_log(name, run.log())?;
+        if let (Some(bucket), true) = (&bucket, new_crash) {
+            info!("Test {}: new crash {}, --reproduce {}", i, bucket, id);
+            let dir = self.crashes_dir.join(bucket);
+            std::fs::create_dir_all(&dir).context("Couldn't create crash directory")?;
+            save(&dir, name, out).context("Couldn't save crash")?;
+            std::fs::write(dir.join(format!("{}.log", name)), run.log())
+                .context("Couldn't save log")?;
+        }
+        if self.args.reduce && new_crash {
+            self.reduce(i, name, out, target, &run)?;
+        }
+        Ok(true)
+    }
+
+    /// With `--reduce`, save a smaller test that still crashes `target` next
+    /// to `out`
+    fn reduce(&self, i: usize, name: &str, out: &[u8], target: &Target, run: &Run) -> Result<()> {
+        let reducer = Reducer {
+            language: self.language,
+            node_types: self.node_types,
+            target,
+            path: self.args.output.join(format!(".{}.reduce", i)),
+        };
+        let reduced = reducer.reduce(out, run)?;
+        info!(
+            "Test {}: reduced from {} to {} bytes",
+            i,
+            out.len(),
+            reduced.len()
+        );
+        let reduced_name = reduced_name(name);
+        self.sink
+            .save(&reduced_name, &reduced)
+            .context("Couldn't save reduced test")?;
+        self.events.emit(Event::Reduced {
+            test: i,
+            name: &reduced_name,
+            from: out.len(),
+            to: reduced.len(),
+        })
+    }
+
+    /// With `--exec-a`, run both commands on `out`. Whether to keep the
+    /// test, i.e., whether they differ.
+    fn run_differential(&self, i: usize, name: &str, out: &[u8]) -> Result<bool> {
+        let Some(differential) = &self.differential else {
+            return Ok(true);
+        };
+        let (a, b) = differential.run(out, &self.args.output.join(format!(".{}.exec", i)))?;
+        if a.outcome == Outcome::Timeout || b.outcome == Outcome::Timeout {
+            self.save_hang(i, name, out, Differential::log(&a, &b))?;
+            return Ok(false);
+        }
+        if !differential.differ(&a, &b) {
+            debug!("Test {}: no difference", i);
+            return Ok(false);
+        }
+        info!("Test {}: A {}, B {}", i, a.outcome, b.outcome);
+        self.events.emit(Event::Difference {
+            test: i,
+            name,
+            a: a.outcome.to_string(),
+            b: b.outcome.to_string(),
+        })?;
+        self.kept.fetch_add(1, Ordering::Relaxed);
+        self.save_log(name, Differential::log(&a, &b))?;
+        Ok(true)
+    }
+
+    /// Save `out` to the output, with its diff and trace if asked
+    fn save(
+        &self,
+        i: usize,
+        name: &str,
+        id: TestId,
+        out: &[u8],
+        input: Option<&str>,
+        trace: Option<Trace>,
+    ) -> Result<()> {
+        let args = self.args;
+        if let Some(backlog) = args.backlog {
+            wait_for_backlog(&args.output, backlog)?;
+        }
+        self.sink
+            .save(name, out)
+            .context("Couldn't save generated test case")?;
+        self.saved.fetch_add(1, Ordering::Relaxed);
+        self.events.emit(Event::Test {
+            test: i,
+            id: id.to_string(),
+            name,
+            input,
+            bytes: out.len(),
+        })?;
+        if let Some(dir) = &args.emit_diff {
+            match input.and_then(|path| self.files.get(path).map(|(text, _)| (path, text))) {
+                Some((path, text)) => diff::save(dir, name, path, text, out)?,
+                None => debug!("Test {}: no diff, not made from an input file", i),
+            }
+        }
+        if let (Some(dir), Some(trace)) = (&args.trace_out, trace) {
+            let file = TraceFile {
+                line_endings: args.common.line_endings.clone(),
+                lossy_utf8: args.common.on_invalid_utf8 == OnInvalidUtf8::Lossy,
+                normalize_whitespace: args.common.normalize_whitespace,
+                strip_comments: args.common.strip_comments,
+                trace,
+            };
+            file.save(dir, name)?;
+        }
+        Ok(())
+    }
+
+    /// Finish the output, and log what the oracles found
+    pub(super) fn finish(self) -> Result<()> {
+        self.sink.finish()?;
+        let args = self.args;
+        if args.coverage_guided {
+            for entry in
+                std::fs::read_dir(&args.output).context("Couldn't read output directory")?
+            {
+                let path = entry?.path();
+                if path.is_dir()
+                    && path
+                        .file_name()
+                        .is_some_and(|n| n.to_string_lossy().starts_with(".coverage"))
+                {
+                    let _ = std::fs::remove_dir_all(path);
+                }
+            }
+            info!(
+                "Covered {} PCs, added {} tests to the inputs",
+                self.coverage.into_inner().unwrap().len(),
+                self.discovered.into_inner().unwrap().len()
+            );
+        }
+
+        if self.formatter.is_some() {
+            info!(
+                "Found {} formatter bugs, see {}",
+                self.findings.into_inner(),
+                self.findings_dir.display()
+            );
+        }
+
+        if args.validate != Validate::Keep {
+            info!(
+                "Discarded {} tests with parse errors",
+                self.invalid.into_inner()
+            );
+        }
+
+        if args.dedup {
+            info!(
+                "Discarded {} tests that were still duplicates after {} attempts",
+                self.duplicates.into_inner(),
+                args.attempts
+            );
+        }
+
+        if self.target.is_some() {
+            info!(
+                "Kept {} interesting tests, see {}",
+                self.kept.into_inner(),
+                self.logs_dir.display()
+            );
+            info!(
+                "Found {} distinct crashes, see {}; discarded {} more like them",
+                self.buckets.into_inner().unwrap().len(),
+                self.crashes_dir.display(),
+                self.duplicate_crashes.into_inner()
+            );
+        } else if self.differential.is_some() {
+            info!(
+                "Kept {} tests where the commands differ, see {}",
+                self.kept.into_inner(),
+                self.logs_dir.display()
+            );
+        }
+        let hangs = self.hangs.into_inner();
+        if hangs > 0 {
+            info!("Found {} hangs, see {}", hangs, self.hangs_dir.display());
+        }
+
+        if let (Some(report), Some(path)) = (self.report.into_inner().unwrap(), &args.report) {
+            report.write(path, args, self.language)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+//! Progress of `generate` on stderr, see `--progress`.
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use tracing::info;
+
+/// How often to log progress when stderr isn't a terminal
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The bar being drawn, if any, so that [`Stderr`] can write around it
+static BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Stderr for logs, which hides the progress bar while writing to it
+pub(super) struct Stderr;
+
+impl Write for Stderr {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let bar = BAR.lock().unwrap().clone();
+        match bar {
+            Some(bar) => bar.suspend(|| io::stderr().write(buf)),
+            None => io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// What happened to the tests so far
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct Counts {
+    /// Tests made, including discarded ones
+    pub(super) done: usize,
+    pub(super) invalid: usize,
+    pub(super) duplicates: usize,
+    /// Distinct crashes, with `--exec`
+    pub(super) crashes: Option<usize>,
+}
+
+impl std::fmt::Display for Counts {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let percent = |n: usize| 100.0 * n as f64 / self.done.max(1) as f64;
+        write!(
+            f,
+            "{:.1}% duplicates, {:.1}% invalid",
+            percent(self.duplicates),
+            percent(self.invalid)
+        )?;
+        if let Some(crashes) = self.crashes {
+            write!(f, ", {} crashes", crashes)?;
+        }
+        Ok(())
+    }
+}
+
+pub(super) enum Progress {
+    Hidden,
+    /// A bar on a terminal, redrawn in place
+    Bar(ProgressBar),
+    /// Lines in the log, e.g., of a CI job
+    Log {
+        started: Instant,
+        last: Mutex<Instant>,
+    },
+}
+
+impl Progress {
+    /// `total` is `None` with `--continuous`. By default, progress is only
+    /// shown on a terminal and not with `--quiet`, `force` shows it anyway.
+    pub(super) fn new(force: bool, quiet: bool, total: Option<usize>) -> Self {
+        let terminal = io::stderr().is_terminal();
+        if !force && (quiet || !terminal) {
+            return Progress::Hidden;
+        }
+        if !terminal {
+            let now = Instant::now();
+            return Progress::Log {
+                started: now,
+                last: Mutex::new(now),
+            };
+        }
+        let (bar, template) = match total {
+            Some(total) => (
+                ProgressBar::new(total as u64),
+                "{elapsed_precise} [{bar:30}] {human_pos}/{human_len} tests ({per_sec}), {msg}",
+            ),
+            None => (
+                ProgressBar::new_spinner(),
+                "{elapsed_precise} {spinner} {human_pos} tests ({per_sec}), {msg}",
+            ),
+        };
+        bar.set_draw_target(ProgressDrawTarget::stderr());
+        bar.set_style(
+            ProgressStyle::with_template(template)
+                .expect("Invalid progress template")
+                .progress_chars("=> "),
+        );
+        *BAR.lock().unwrap() = Some(bar.clone());
+        Progress::Bar(bar)
+    }
+
+    pub(super) fn update(&self, counts: Counts) {
+        match self {
+            Progress::Hidden => (),
+            Progress::Bar(bar) => {
+                bar.set_position(counts.done as u64);
+                bar.set_message(counts.to_string());
+            }
+            Progress::Log { started, last } => {
+                let mut last = last.lock().unwrap();
+                if last.elapsed() < LOG_INTERVAL {
+                    return;
+                }
+                *last = Instant::now();
+                let seconds = started.elapsed().as_secs_f64();
+                info!(
+                    "{} tests ({:.1}/s), {}",
+                    counts.done,
+                    counts.done as f64 / seconds,
+                    counts
+                );
+            }
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if let Progress::Bar(bar) = self {
+            bar.finish_and_clear();
+            *BAR.lock().unwrap() = None;
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Limit how quickly tests are produced.
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Events per unit of time, e.g., `50/s`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rate {
+    count: f64,
+    per: Duration,
+}
+
+impl Rate {
+    fn interval(&self) -> Duration {
+        self.per.div_f64(self.count)
+    }
+}
+
+impl FromStr for Rate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, unit) = s.split_once('/').unwrap_or((s, "s"));
+        let count: f64 = count
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid count in rate: {}", count))?;
+        if !count.is_finite() || count <= 0.0 {
+            return Err(format!("Rate must be positive: {}", s));
+        }
+        let per = match unit.trim() {
+            "s" => Duration::from_secs(1),
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(60 * 60),
+            _ => return Err(format!("Unknown unit in rate, expected s, m, or h: {}", s)),
+        };
+        Ok(Rate { count, per })
+    }
+}
+
+impl std::fmt::Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let unit = match self.per.as_secs() {
+            60 => "m",
+            3600 => "h",
+            _ => "s",
+        };
+        write!(f, "{}/{}", self.count, unit)
+    }
+}
+
+/// Spaces out events so that they happen at most at a given rate
+pub(super) struct Limiter {
+    interval: Duration,
+    next: Instant,
+}
+
+impl Limiter {
+    pub(super) fn new(rate: Rate) -> Self {
+        Limiter {
+            interval: rate.interval(),
+            next: Instant::now(),
+        }
+    }
+
+    /// Reserve the time of the next event, and return how long to sleep until
+    /// then
+    pub(super) fn delay(&mut self) -> Duration {
+        let now = Instant::now();
+        if self.next < now {
+            // Don't make up for lost time with a burst
+            self.next = now;
+        }
+        let delay = self.next - now;
+        self.next += self.interval;
+        delay
+    }
+}
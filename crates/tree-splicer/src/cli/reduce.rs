@@ -0,0 +1,157 @@
+//! Reduce tests that the `--exec` command finds interesting.
+
+use std::fs;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use tree_sitter::{Language, Node, Tree};
+
+use super::exec::{Outcome, Run, Target};
+use super::parse;
+use crate::node_types::NodeTypes;
+
+/// Give up on reducing a test after running the command this many times
+const MAX_RUNS: usize = 1000;
+
+/// Shrinks a test while the target still finds it interesting, by deleting
+/// nodes that [`NodeTypes::can_delete`] and replacing nodes with smaller
+/// nodes of the same kind inside them
+pub(super) struct Reducer<'t> {
+    pub(super) language: Language,
+    pub(super) node_types: &'t NodeTypes,
+    pub(super) target: &'t Target,
+    /// Where to save candidates if the target takes a file
+    pub(super) path: PathBuf,
+}
+
+/// `test` with `edits`, which are sorted and don't overlap, applied
+fn apply(test: &[u8], edits: &[(Range<usize>, &[u8])]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(test.len());
+    let mut end = 0;
+    for (range, replace) in edits {
+        out.extend(&test[end..range.start]);
+        out.extend(*replace);
+        end = range.end;
+    }
+    out.extend(&test[end..]);
+    out
+}
+
+/// The nodes of `tree`, parents before children
+fn preorder(tree: &Tree) -> Vec<Node<'_>> {
+    let mut nodes = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        nodes.push(cursor.node());
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return nodes;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+/// The first node inside `node` with the same kind, if any
+fn same_kind_descendant<'t>(node: &Node<'t>) -> Option<Node<'t>> {
+    let mut cursor = node.walk();
+    let mut nodes: Vec<_> = node.children(&mut cursor).collect();
+    while !nodes.is_empty() {
+        if let Some(found) = nodes.iter().find(|n| n.kind_id() == node.kind_id()) {
+            return Some(*found);
+        }
+        nodes = nodes
+            .iter()
+            .flat_map(|n| n.children(&mut n.walk()).collect::<Vec<_>>())
+            .collect();
+    }
+    None
+}
+
+impl Reducer<'_> {
+    /// Smaller versions of `test` to try, from deleting or replacing `node`
+    fn candidates(&self, test: &[u8], node: &Node<'_>) -> Vec<Vec<u8>> {
+        let mut candidates = Vec::new();
+        if self.node_types.can_delete(node) {
+            let mut edits = vec![(node.byte_range(), &[][..])];
+            if let Some(separator) = self.node_types.separator(node) {
+                edits.push((separator.byte_range(), &[][..]));
+                edits.sort_by_key(|(range, _)| range.start);
+            }
+            candidates.push(apply(test, &edits));
+        }
+        if let Some(inner) = same_kind_descendant(node) {
+            candidates.push(apply(
+                test,
+                &[(node.byte_range(), &test[inner.byte_range()])],
+            ));
+        }
+        // E.g., deleting an empty node, or a node with a child just like it
+        candidates.retain(|c| c.len() < test.len());
+        candidates
+    }
+
+    fn reproduces(&self, test: &[u8], original: &Run) -> Result<bool> {
+        if self.target.takes_file() {
+            fs::write(&self.path, test).context("Couldn't save test for --exec")?;
+        }
+        let run = self.target.run(test, &self.path, None);
+        if self.target.takes_file() {
+            let _ = fs::remove_file(&self.path);
+        }
+        let run = run?;
+        // A crash should be the same crash, e.g., not a different signal
+        let same = match original.outcome {
+            Outcome::Signal(_) => run.outcome == original.outcome,
+            _ => true,
+        };
+        Ok(same && self.target.interesting(&run))
+    }
+
+    /// Reduce `test`, which made the target finish like `original`. Each
+    /// reduction is smaller, so this terminates even without a limit on the
+    /// number of runs.
+    pub(super) fn reduce(&self, test: &[u8], original: &Run) -> Result<Vec<u8>> {
+        let mut test = test.to_vec();
+        let mut runs = 0;
+        // Where to resume looking for reductions after one succeeds
+        let mut start = 0;
+        'passes: loop {
+            let tree = parse(self.language, &test)?;
+            let nodes = preorder(&tree);
+            for (idx, node) in nodes.iter().enumerate().skip(start) {
+                for candidate in self.candidates(&test, node) {
+                    if runs >= MAX_RUNS {
+                        return Ok(test);
+                    }
+                    runs += 1;
+                    if self.reproduces(&candidate, original)? {
+                        test = candidate;
+                        start = idx;
+                        continue 'passes;
+                    }
+                }
+            }
+            if start == 0 {
+                return Ok(test);
+            }
+            // Earlier nodes may be deletable now
+            start = 0;
+        }
+    }
+}
+
+/// Name of the reduced version of the test named `name`, e.g., `foo.min.rs`
+/// for `foo.rs`
+pub(super) fn reduced_name(name: &str) -> String {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}.min.{}", stem, ext),
+        _ => format!("{}.min", name),
+    }
+}
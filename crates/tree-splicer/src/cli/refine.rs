@@ -0,0 +1,146 @@
+//! Improve a fuzzer's corpus.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use tracing::{debug, info};
+use tree_sitter::Language;
+
+use super::{
+    by_ref, handle_invalid_utf8, init_tracing, load_donors, parse, preprocess, save, Common,
+    Splicing,
+};
+use crate::index::hash;
+use crate::node_types::NodeTypes;
+use crate::splice::{Config, Splicer};
+
+/// Discard the files in a fuzzer's corpus that don't parse, and add tests
+/// spliced from the rest, e.g., as a cron job that improves an OSS-Fuzz
+/// corpus.
+///
+/// The corpus is one directory of files, like libFuzzer's. New tests are
+/// named after a hash of their contents.
+#[derive(clap::Args, Debug)]
+pub struct Refine {
+    /// Write the corpus to this directory; default = the corpus directory,
+    /// deleting the files in it that don't parse
+    #[arg(short, long, value_name = "DIR")]
+    pub output: Option<PathBuf>,
+
+    /// How many tests to make; those that don't parse or are already in the
+    /// corpus aren't added
+    #[arg(long, default_value_t = 100)]
+    pub tests: usize,
+
+    /// Corpus directory
+    #[arg(value_name = "DIR")]
+    pub corpus: String,
+
+    #[command(flatten, next_help_heading = "Input options")]
+    pub common: Common,
+
+    #[command(flatten, next_help_heading = "Mutation options")]
+    pub splicing: Splicing,
+}
+
+impl Refine {
+    /// The files directly in the corpus directory, sorted
+    fn corpus_files(&self) -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(&self.corpus)
+            .with_context(|| format!("Failed to read directory {}", self.corpus))?
+        {
+            let entry = entry?;
+            let hidden = entry.file_name().to_string_lossy().starts_with('.');
+            let path = entry.path();
+            if !hidden && path.is_file() && self.common.wanted(&path) {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    pub(super) fn run(&self, language: Language, node_types_json_str: &str) -> Result<()> {
+        init_tracing(&self.common);
+        let paths = self.corpus_files()?;
+        let mut files = HashMap::with_capacity(paths.len());
+        let mut invalid = Vec::new();
+        for path in paths {
+            let text = fs::read(&path)
+                .with_context(|| format!("Failed to read file {}", path.display()))?;
+            let name = path.display().to_string();
+            let Some(text) = handle_invalid_utf8(&name, text, &self.common.on_invalid_utf8)? else {
+                continue;
+            };
+            let tree = parse(language, &text)?;
+            if tree.root_node().has_error() {
+                debug!("Discarding {}, parse error", path.display());
+                invalid.push(path);
+            } else {
+                files.insert(name, (text, tree));
+            }
+        }
+        if files.is_empty() {
+            bail!("No file in {} parses", self.corpus);
+        }
+        let donors = load_donors(&self.common, &self.splicing, language)?;
+        let dictionary = self.splicing.dictionary()?;
+
+        let corpus = Path::new(&self.corpus);
+        let output = self.output.as_deref().unwrap_or(corpus);
+        fs::create_dir_all(output).context("Couldn't create output directory")?;
+        let in_place = fs::canonicalize(output)? == fs::canonicalize(corpus)?;
+        if in_place {
+            for path in &invalid {
+                fs::remove_file(path)
+                    .with_context(|| format!("Couldn't delete {}", path.display()))?;
+            }
+        } else {
+            for (path, (text, _)) in &files {
+                let name = Path::new(path).file_name().context("No file name")?;
+                save(output, &name.to_string_lossy(), text)
+                    .with_context(|| format!("Couldn't copy {}", path))?;
+            }
+        }
+
+        // Splice the preprocessed files, but copy and deduplicate the originals
+        let mut seen: HashSet<_> = files.values().map(|(text, _)| hash(text)).collect();
+        let files = files
+            .into_iter()
+            .map(|(path, (text, tree))| (path, preprocess(self.common.preprocess(), text, tree)))
+            .collect::<HashMap<_, _>>();
+
+        let node_types = NodeTypes::new(node_types_json_str)?;
+        let config = Config {
+            focus: self.splicing.focus(language)?,
+            rules: self.splicing.rules(language)?,
+            templates: self.splicing.templates(&node_types)?,
+            ..self.splicing.config(language, node_types)
+        };
+        let mut added = 0;
+        let mut splicer = Splicer::new_with_weighted_donors(config, &files, &by_ref(&donors));
+        self.splicing.add_dictionary(&mut splicer, &dictionary)?;
+        for test in splicer.take(self.tests) {
+            if parse(language, &test)?.root_node().has_error() {
+                continue;
+            }
+            let h = hash(&test);
+            if !seen.insert(h) {
+                continue;
+            }
+            save(output, &format!("{:016x}", h), &test).context("Couldn't save test")?;
+            added += 1;
+        }
+        info!(
+            "Discarded {} of {} files that don't parse, added {} tests to {}",
+            invalid.len(),
+            files.len() + invalid.len(),
+            added,
+            output.display()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+//! Save traces of tests and replay them.
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use tree_sitter::Language;
+
+use super::{normalize_line_endings, parse, preprocess, read_file, LineEndings};
+use crate::preprocess::Preprocess;
+use crate::trace::Trace;
+
+/// Re-apply the mutations in a trace from `--trace-out`
+#[derive(clap::Args, Debug)]
+pub struct Replay {
+    /// The file that was mutated; default = the input named in the trace
+    #[arg(long, value_name = "FILE")]
+    pub input: Option<PathBuf>,
+
+    /// Write the test to this file; default = stdout
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Trace from `--trace-out`
+    #[arg(value_name = "TRACE")]
+    pub trace: PathBuf,
+}
+
+/// What `--trace-out` writes for each test
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct TraceFile {
+    pub(super) line_endings: LineEndings,
+    /// `--on-invalid-utf8 lossy`
+    #[serde(default)]
+    pub(super) lossy_utf8: bool,
+    #[serde(default)]
+    pub(super) normalize_whitespace: bool,
+    #[serde(default)]
+    pub(super) strip_comments: bool,
+    #[serde(flatten)]
+    pub(super) trace: Trace,
+}
+
+impl TraceFile {
+    pub(super) fn save(&self, dir: &Path, name: &str) -> Result<()> {
+        fs::create_dir_all(dir).context("Couldn't create trace directory")?;
+        let json = serde_json::to_vec_pretty(self).context("Couldn't serialize trace")?;
+        fs::write(dir.join(format!("{}.json", name)), json).context("Couldn't save trace")
+    }
+}
+
+impl Replay {
+    /// Replay the trace; the language is only needed if the input was
+    /// preprocessed with `--strip-comments` or `--normalize-whitespace`.
+    pub fn run(&self, language: Option<Language>) -> Result<()> {
+        let json = fs::read(&self.trace)
+            .with_context(|| format!("Couldn't read trace {}", self.trace.display()))?;
+        let file: TraceFile = serde_json::from_slice(&json)
+            .with_context(|| format!("Invalid trace {}", self.trace.display()))?;
+        let input = match (&self.input, &file.trace.input) {
+            (Some(input), _) => input.display().to_string(),
+            (None, Some(input)) => input.clone(),
+            (None, None) => anyhow::bail!("The trace doesn't name its input, pass --input"),
+        };
+        let mut text = read_file(&input)?;
+        if file.lossy_utf8 {
+            text = String::from_utf8_lossy(&text).into_owned().into_bytes();
+        }
+        let text = normalize_line_endings(text, &file.line_endings);
+        let options = Preprocess {
+            strip_comments: file.strip_comments,
+            normalize_whitespace: file.normalize_whitespace,
+        };
+        let text = if options.is_noop() {
+            text
+        } else {
+            let language = language.context("Replaying a preprocessed input needs the grammar")?;
+            let tree = parse(language, &text)?;
+            preprocess(options, text, tree).0
+        };
+        let test = file
+            .trace
+            .replay(&text)
+            .with_context(|| format!("The trace doesn't apply to {}", input))?;
+        let test = normalize_line_endings(test, &file.line_endings);
+        match &self.output {
+            Some(path) => fs::write(path, test).context("Couldn't write test"),
+            None => std::io::stdout()
+                .write_all(&test)
+                .context("Couldn't write test"),
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Self-contained HTML summary of a run.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use tree_sitter::{Language, Tree};
+
+use super::Generate;
+
+/// How many generated tests to show in the report
+const SAMPLES: usize = 5;
+/// Truncate samples to this many bytes
+const SAMPLE_BYTES: usize = 4096;
+
+pub(super) struct Report {
+    started: Instant,
+    seeds: usize,
+    seed_bytes: usize,
+    seed_kinds: HashSet<&'static str>,
+    output_kinds: HashMap<&'static str, usize>,
+    sizes: Vec<usize>,
+    samples: Vec<Vec<u8>>,
+    clusters: Vec<Vec<String>>,
+}
+
+pub(super) fn kinds(tree: &Tree, mut f: impl FnMut(&'static str)) {
+    let mut cursor = tree.walk();
+    loop {
+        if cursor.node().is_named() {
+            f(cursor.node().kind());
+        }
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Horizontal bar chart, one row per `(label, value)`
+fn chart(html: &mut String, rows: &[(String, usize)]) {
+    let max = rows.iter().map(|(_, v)| *v).max().unwrap_or(0).max(1);
+    html.push_str("<table class=\"chart\">\n");
+    for (label, value) in rows {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td><div class=\"bar\" style=\"width: {}%\"></div></td><td>{}</td></tr>",
+            escape(label),
+            value * 100 / max,
+            value
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+impl Report {
+    pub(super) fn new<'a>(files: impl Iterator<Item = (&'a [u8], &'a Tree)>) -> Self {
+        let mut report = Report {
+            started: Instant::now(),
+            seeds: 0,
+            seed_bytes: 0,
+            seed_kinds: HashSet::new(),
+            output_kinds: HashMap::new(),
+            sizes: Vec::new(),
+            samples: Vec::new(),
+            clusters: Vec::new(),
+        };
+        for (text, tree) in files {
+            report.seeds += 1;
+            report.seed_bytes += text.len();
+            kinds(tree, |k| {
+                report.seed_kinds.insert(k);
+            });
+        }
+        report
+    }
+
+    pub(super) fn record(&mut self, out: &[u8], tree: &Tree) {
+        self.sizes.push(out.len());
+        kinds(tree, |k| *self.output_kinds.entry(k).or_insert(0) += 1);
+        if self.samples.len() < SAMPLES {
+            self.samples
+                .push(out[..out.len().min(SAMPLE_BYTES)].to_vec());
+        }
+    }
+
+    /// Paths of the seeds in each cluster
+    pub(super) fn clusters(&mut self, clusters: Vec<Vec<String>>) {
+        self.clusters = clusters;
+    }
+
+    fn size_histogram(&self) -> Vec<(String, usize)> {
+        // Buckets are powers of two
+        let mut buckets = BTreeMap::new();
+        for size in &self.sizes {
+            let bucket = usize::BITS - size.leading_zeros();
+            *buckets.entry(bucket).or_insert(0) += 1;
+        }
+        buckets
+            .into_iter()
+            .map(|(b, n)| (format!("< {} B", 1u128 << b), n))
+            .collect()
+    }
+
+    pub(super) fn write(&self, path: &Path, args: &Generate, language: Language) -> Result<()> {
+        let mut named = HashSet::new();
+        for id in 0..u16::try_from(language.node_kind_count()).unwrap_or(u16::MAX) {
+            if language.node_kind_is_named(id) && language.node_kind_is_visible(id) {
+                if let Some(kind) = language.node_kind_for_id(id) {
+                    named.insert(kind);
+                }
+            }
+        }
+        let in_seeds = named.intersection(&self.seed_kinds).count();
+        let in_outputs = named
+            .iter()
+            .filter(|k| self.output_kinds.contains_key(*k))
+            .count();
+        let mut missing: Vec<_> = named
+            .iter()
+            .filter(|k| !self.output_kinds.contains_key(*k))
+            .copied()
+            .collect();
+        missing.sort_unstable();
+        let mut kind_counts: Vec<_> = self
+            .output_kinds
+            .iter()
+            .map(|(k, n)| (k.to_string(), *n))
+            .collect();
+        kind_counts.sort_by(|(k1, n1), (k2, n2)| n2.cmp(n1).then(k1.cmp(k2)));
+
+        let mut html = String::new();
+        html.push_str(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>tree-splicer report</title>\n<style>\n\
+             body { font-family: sans-serif; max-width: 60em; margin: auto; }\n\
+             td { padding: 0 0.5em; }\n\
+             .chart td:nth-child(2) { width: 60%; }\n\
+             .bar { background: steelblue; height: 1em; }\n\
+             pre { background: #eee; padding: 0.5em; overflow-x: auto; }\n\
+             </style>\n</head>\n<body>\n<h1>tree-splicer report</h1>\n",
+        );
+
+        html.push_str("<h2>Configuration</h2>\n<table>\n");
+        let config = [
+            ("attempts", args.attempts.to_string()),
+            (
+                "budget",
+                args.splicing
+                    .budget
+                    .map_or("none".to_string(), |b| b.to_string()),
+            ),
+            ("chaos", format!("{}%", args.splicing.chaos)),
+            ("clusters", args.clusters.to_string()),
+            ("crossover", format!("{}%", args.splicing.crossover)),
+            ("dedup", args.dedup.to_string()),
+            ("deletions", format!("{}%", args.splicing.deletions)),
+            ("duplications", format!("{}%", args.splicing.duplications)),
+            ("havoc", format!("{}%", args.splicing.havoc)),
+            ("insertions", format!("{}%", args.splicing.insertions)),
+            (
+                "intra-file mutations",
+                format!("{}%", args.splicing.intra_mutations),
+            ),
+            ("jobs", args.jobs.to_string()),
+            ("line endings", args.common.line_endings.to_string()),
+            ("literals", format!("{}%", args.splicing.literals)),
+            (
+                "max input bytes",
+                args.max_input_bytes
+                    .map_or("unlimited".to_string(), |n| n.to_string()),
+            ),
+            ("max size", format!("{} B", args.splicing.max_size)),
+            ("mutations", args.splicing.mutations.to_string()),
+            ("name template", args.name_template.to_string()),
+            ("only kinds", args.splicing.only_kinds.join(", ")),
+            ("renames", format!("{}%", args.splicing.renames)),
+            ("reparse", args.splicing.reparse.to_string()),
+            ("seed", args.splicing.seed.to_string()),
+            ("skip kinds", args.splicing.skip_kinds.join(", ")),
+            ("swaps", format!("{}%", args.splicing.swaps)),
+            ("tests", args.tests.to_string()),
+            (
+                "type hints",
+                args.splicing
+                    .type_hints
+                    .iter()
+                    .map(|r| format!("{}={}", r.ty, r.regex))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            ("validate", args.validate.to_string()),
+            (
+                "weights",
+                args.splicing
+                    .weights
+                    .iter()
+                    .map(|w| format!("{}={}", w.kind, w.weight))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        ];
+        for (k, v) in config {
+            let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", k, escape(&v));
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Statistics</h2>\n<table>\n");
+        let total: usize = self.sizes.iter().sum();
+        let stats = [
+            ("seed files", self.seeds.to_string()),
+            ("seed bytes", self.seed_bytes.to_string()),
+            ("tests generated", self.sizes.len().to_string()),
+            ("bytes generated", total.to_string()),
+            (
+                "elapsed",
+                format!("{:.2}s", self.started.elapsed().as_secs_f64()),
+            ),
+        ];
+        for (k, v) in stats {
+            let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", k, escape(&v));
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Grammar coverage</h2>\n");
+        let _ = writeln!(
+            html,
+            "<p>{} of {} named node kinds appear in the seeds, {} in the generated tests.</p>",
+            in_seeds,
+            named.len(),
+            in_outputs
+        );
+        chart(&mut html, &kind_counts);
+        if !missing.is_empty() {
+            let _ = writeln!(
+                html,
+                "<p>Never generated: {}</p>",
+                escape(&missing.join(", "))
+            );
+        }
+
+        if !self.clusters.is_empty() {
+            html.push_str("<h2>Seed clusters</h2>\n<ol>\n");
+            for cluster in &self.clusters {
+                let _ = writeln!(html, "<li>{}</li>", escape(&cluster.join(", ")));
+            }
+            html.push_str("</ol>\n");
+        }
+
+        html.push_str("<h2>Sizes of generated tests</h2>\n");
+        chart(&mut html, &self.size_histogram());
+
+        html.push_str("<h2>Sample of generated tests</h2>\n");
+        for sample in &self.samples {
+            let _ = writeln!(
+                html,
+                "<pre>{}</pre>",
+                escape(&String::from_utf8_lossy(sample))
+            );
+        }
+        html.push_str("</body>\n</html>\n");
+
+        std::fs::write(path, html).context("Couldn't write report")
+    }
+}
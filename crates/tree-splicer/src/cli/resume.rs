@@ -0,0 +1,88 @@
+//! Continue interrupted runs, see `--resume`.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::save;
+
+/// Name of the state file in the output directory
+const STATE: &str = ".tree-splicer-resume.json";
+
+/// Name of the `--dedup-index` in the output directory, if there isn't one
+pub(super) const DEDUP_INDEX: &str = ".tree-splicer-dedup";
+
+/// Where a job is up to
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub(super) struct Job {
+    /// Number of the next test it makes
+    pub(super) next: usize,
+    /// Number of the next test its splicer makes, which is ahead of `next` if
+    /// it remade tests, see [`Splicer::next_index`](crate::Splicer::next_index)
+    pub(super) index: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct State {
+    seed: u64,
+    /// One per thread
+    jobs: Vec<Job>,
+}
+
+/// Where each job is up to, saved in the output directory before each test
+pub(super) struct Checkpoint {
+    dir: PathBuf,
+    state: Mutex<State>,
+}
+
+impl Checkpoint {
+    /// Read the state of an earlier run in `dir`, or start a new one
+    pub(super) fn load(dir: &Path, seed: u64, jobs: usize) -> Result<Self> {
+        let path = dir.join(STATE);
+        let state = match fs::read_to_string(&path) {
+            Ok(text) => {
+                let state: State = serde_json::from_str(&text)
+                    .with_context(|| format!("Invalid state file {}", path.display()))?;
+                if state.seed != seed {
+                    bail!(
+                        "Can't resume a run with --seed {} with --seed {}",
+                        state.seed,
+                        seed
+                    );
+                }
+                if state.jobs.len() != jobs {
+                    bail!(
+                        "Can't resume a run with {} jobs with {} jobs, see --jobs",
+                        state.jobs.len(),
+                        jobs
+                    );
+                }
+                state
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => State {
+                seed,
+                jobs: (0..jobs).map(|next| Job { next, index: next }).collect(),
+            },
+            Err(e) => return Err(e).with_context(|| format!("Couldn't read {}", path.display())),
+        };
+        Ok(Checkpoint {
+            dir: dir.to_path_buf(),
+            state: Mutex::new(state),
+        })
+    }
+
+    pub(super) fn job(&self, job: usize) -> Job {
+        self.state.lock().unwrap().jobs[job]
+    }
+
+    pub(super) fn save(&self, job: usize, at: Job) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.jobs[job] = at;
+        let json = serde_json::to_vec(&*state)?;
+        save(&self.dir, STATE, &json).context("Couldn't save state for --resume")
+    }
+}
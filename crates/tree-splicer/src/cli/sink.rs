@@ -0,0 +1,133 @@
+//! Where `generate` saves tests, see `--output-format`.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::Serialize;
+
+use super::save;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// A file per test in the output directory
+    #[default]
+    Dir,
+    /// `tests.tar` in the output directory
+    Tar,
+    /// `tests.zip` in the output directory, uncompressed
+    Zip,
+    /// A JSON object per test on stdout, one per line
+    NdjsonStdout,
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            OutputFormat::Dir => write!(f, "dir"),
+            OutputFormat::Tar => write!(f, "tar"),
+            OutputFormat::Zip => write!(f, "zip"),
+            OutputFormat::NdjsonStdout => write!(f, "ndjson-stdout"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    name: &'a str,
+    content_base64: String,
+}
+
+/// Saves tests in the `--output-format`, from any thread
+pub(super) enum Sink {
+    Dir(PathBuf),
+    Tar(Mutex<tar::Builder<File>>),
+    Zip(Mutex<zip::ZipWriter<BufWriter<File>>>),
+    Ndjson(Mutex<io::Stdout>),
+}
+
+impl Sink {
+    /// Archives are created in `dir`, which must exist
+    pub(super) fn new(format: OutputFormat, dir: &Path) -> Result<Self> {
+        let create = |name: &str| {
+            let path = dir.join(name);
+            File::create(&path).with_context(|| format!("Couldn't create {}", path.display()))
+        };
+        Ok(match format {
+            OutputFormat::Dir => Sink::Dir(dir.to_path_buf()),
+            OutputFormat::Tar => Sink::Tar(Mutex::new(tar::Builder::new(create("tests.tar")?))),
+            OutputFormat::Zip => Sink::Zip(Mutex::new(zip::ZipWriter::new(BufWriter::new(
+                create("tests.zip")?,
+            )))),
+            OutputFormat::NdjsonStdout => Sink::Ndjson(Mutex::new(io::stdout())),
+        })
+    }
+
+    pub(super) fn save(&self, name: &str, contents: &[u8]) -> Result<()> {
+        match self {
+            Sink::Dir(dir) => save(dir, name, contents).map_err(Into::into),
+            Sink::Tar(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                );
+                // Whole entries, so that an interrupted archive can be read
+                builder
+                    .lock()
+                    .unwrap()
+                    .append_data(&mut header, name, contents)
+                    .map_err(Into::into)
+            }
+            Sink::Zip(writer) => {
+                let options = zip::write::SimpleFileOptions::default()
+                    .compression_method(zip::CompressionMethod::Stored);
+                let mut writer = writer.lock().unwrap();
+                writer.start_file(name, options)?;
+                writer.write_all(contents).map_err(Into::into)
+            }
+            Sink::Ndjson(stdout) => {
+                let record = Record {
+                    name,
+                    content_base64: base64::engine::general_purpose::STANDARD.encode(contents),
+                };
+                let mut line = serde_json::to_vec(&record)?;
+                line.push(b'\n');
+                // Flush each test so that consumers get it right away
+                let mut stdout = stdout.lock().unwrap();
+                stdout
+                    .write_all(&line)
+                    .and_then(|()| stdout.flush())
+                    .map_err(Into::into)
+            }
+        }
+    }
+
+    /// Finish the archive, if any
+    pub(super) fn finish(self) -> Result<()> {
+        match self {
+            Sink::Dir(_) | Sink::Ndjson(_) => Ok(()),
+            Sink::Tar(builder) => builder
+                .into_inner()
+                .unwrap()
+                .into_inner()
+                .and_then(|mut file| file.flush())
+                .context("Couldn't finish tests.tar"),
+            Sink::Zip(writer) => writer
+                .into_inner()
+                .unwrap()
+                .finish()
+                .context("Couldn't finish tests.zip")?
+                .flush()
+                .context("Couldn't finish tests.zip"),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! The jobs of `generate`, which each make every `jobs`th test with their own
+//! splicer, on their own thread.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use tracing::info;
+use tree_sitter::{Language, Tree};
+
+use super::metrics::Metrics;
+use super::output::{Made, Outputs};
+use super::progress::Progress;
+use super::resume::{Checkpoint, Job};
+use super::{by_ref, normalize_line_endings, parse, Generate, Validate};
+use crate::index::CorpusIndex;
+use crate::injection::Injection;
+use crate::splice::{Config, Splicer};
+
+/// A language embedded in the language of the inputs, and the parts of the
+/// inputs and donors in it
+pub(super) struct Embedded<'a> {
+    pub(super) injection: &'a Injection,
+    pub(super) config: Config,
+    pub(super) files: HashMap<String, (Vec<u8>, Tree)>,
+    #[allow(clippy::type_complexity)]
+    pub(super) donors: Vec<(HashMap<String, (Vec<u8>, Tree)>, u32)>,
+}
+
+/// What every job needs to make its tests
+pub(super) struct Worker<'a> {
+    pub(super) args: &'a Generate,
+    pub(super) language: Language,
+    /// Of the splicers of the inputs, but for their stride
+    pub(super) config: Config,
+    pub(super) files: &'a HashMap<String, (Vec<u8>, Tree)>,
+    #[allow(clippy::type_complexity)]
+    pub(super) donors: &'a [(HashMap<String, (Vec<u8>, Tree)>, u32)],
+    pub(super) index: &'a CorpusIndex,
+    pub(super) dictionary: &'a [Vec<u8>],
+    pub(super) embedded: Vec<Embedded<'a>>,
+    pub(super) checkpoint: Option<&'a Checkpoint>,
+    pub(super) jobs: usize,
+    /// Stop at this test number
+    pub(super) tests: usize,
+    /// Remake invalid or duplicate tests up to this many times
+    pub(super) max_attempts: usize,
+    pub(super) progress: &'a Progress,
+    /// With `--metrics-addr`, record the mutations of each test here
+    pub(super) metrics: Option<&'a Metrics>,
+    pub(super) outputs: &'a Outputs<'a>,
+}
+
+impl Worker<'_> {
+    /// Run each job on its own thread. If one fails, the others stop before
+    /// their next test, and the first error is returned.
+    pub(super) fn run_all(&self) -> Result<()> {
+        let stop = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.jobs)
+                .map(|job| {
+                    let stop = &stop;
+                    scope.spawn(move || {
+                        let made = self.run(job, stop);
+                        if made.is_err() {
+                            stop.store(true, Ordering::Relaxed);
+                        }
+                        made
+                    })
+                })
+                .collect();
+            let mut made = Ok(());
+            for handle in handles {
+                let result = handle
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                if made.is_ok() {
+                    made = result;
+                }
+            }
+            made
+        })
+    }
+
+    /// Make the tests of `job` until they're done or `stop` is set
+    fn run(&self, job: usize, stop: &AtomicBool) -> Result<()> {
+        let args = self.args;
+        let outputs = self.outputs;
+        // This job's references to `discovered`, which outlive its splicer
+        let added_tests = typed_arena::Arena::new();
+        let mut injected: Vec<_> = self
+            .embedded
+            .iter()
+            .filter(|e| !e.files.is_empty())
+            .map(|e| {
+                let splicer = Splicer::new_with_weighted_donors(
+                    e.config.clone(),
+                    &e.files,
+                    &by_ref(&e.donors),
+                );
+                (e.injection, splicer)
+            })
+            .collect();
+        let mut splicer = Splicer::new_with_index(
+            self.config.clone(),
+            self.files,
+            &by_ref(self.donors),
+            self.index,
+        )
+        .context("Index is of files in another language")?
+        .stride(job, self.jobs);
+        args.splicing
+            .add_dictionary(&mut splicer, self.dictionary)?;
+        let start = match (self.checkpoint, args.reproduce) {
+            (Some(checkpoint), _) => checkpoint.job(job),
+            (None, Some(id)) => Job {
+                next: id.index,
+                index: id.index,
+            },
+            (None, None) => Job {
+                next: job,
+                index: job,
+            },
+        };
+        if start.next != job && args.reproduce.is_none() {
+            info!("Job {}: resuming at test {}", job, start.next);
+        }
+        splicer = splicer.resume(start.index);
+        let coverage_dir = args
+            .coverage_guided
+            .then(|| args.output.join(format!(".coverage-{}", job)));
+        let mut added = 0;
+        let mut next = start.next;
+        for i in (start.next..self.tests).step_by(self.jobs) {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            self.progress.update(outputs.counts());
+            if let Some(checkpoint) = self.checkpoint {
+                let index = splicer.next_index();
+                checkpoint.save(job, Job { next: i, index })?;
+            }
+            next = i.saturating_add(self.jobs);
+            for test in &outputs.discovered.lock().unwrap()[added..] {
+                let test: &(Vec<u8>, Tree) = added_tests.alloc(Arc::clone(test));
+                splicer.add_tree(&test.0, &test.1);
+                added += 1;
+            }
+            let Some(made) = self.make(&mut splicer, &mut injected)? else {
+                break;
+            };
+            outputs.handle(i, made, coverage_dir.as_deref())?;
+        }
+        if let Some(checkpoint) = self.checkpoint {
+            let index = splicer.next_index();
+            checkpoint.save(job, Job { next, index })?;
+        }
+        Ok(())
+    }
+
+    /// Make the next test, remaking it while it's invalid or a duplicate,
+    /// see `--validate` and `--dedup`. `None` if the splicer is done.
+    fn make<'s>(
+        &self,
+        splicer: &mut Splicer<'s>,
+        injected: &mut [(&Injection, Splicer<'_>)],
+    ) -> Result<Option<Made<'s>>> {
+        let mut attempts = 0;
+        loop {
+            let index = splicer.next_index();
+            let Some(mut out) = splicer.next() else {
+                return Ok(None);
+            };
+            let input = splicer.input();
+            let mut trace = splicer.take_trace();
+            if let (Some(trace), Some(metrics)) = (&trace, self.metrics) {
+                metrics.record(trace);
+            }
+            for (inj, splicer) in injected.iter_mut() {
+                splicer.reseed(index);
+                let tree = parse(self.language, &out)?;
+                if let Some((spliced, step)) = inj.splice_traced(splicer, &out, &tree) {
+                    out = spliced;
+                    if let (Some(trace), false) = (&mut trace, step.is_empty()) {
+                        trace.steps.push(step);
+                    }
+                }
+            }
+            let out = normalize_line_endings(out, &self.args.common.line_endings);
+            attempts += 1;
+            let (valid, duplicate) = self.outputs.check(&out)?;
+            if (valid && !duplicate)
+                || (!valid && self.args.validate == Validate::Discard)
+                || attempts >= self.max_attempts
+            {
+                return Ok(Some(Made {
+                    out,
+                    trace,
+                    input,
+                    index,
+                    valid,
+                    duplicate,
+                }));
+            }
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Group structurally similar files by how often each node kind occurs in
+//! them.
+
+use std::collections::BTreeMap;
+
+use tree_sitter::Tree;
+
+/// Rounds of refinement after picking initial centers
+const ROUNDS: usize = 5;
+
+/// Sparse vector of node kind frequencies with unit length
+type Profile = BTreeMap<u16, f64>;
+
+fn normalize(mut profile: Profile) -> Profile {
+    let norm = profile.values().map(|v| v * v).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        for v in profile.values_mut() {
+            *v /= norm;
+        }
+    }
+    profile
+}
+
+fn profile(tree: &Tree) -> Profile {
+    let mut profile = Profile::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if node.is_named() {
+            *profile.entry(node.kind_id()).or_insert(0.0) += 1.0;
+        }
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return normalize(profile);
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+/// Cosine similarity of two unit vectors
+fn similarity(p: &Profile, q: &Profile) -> f64 {
+    p.iter().filter_map(|(k, v)| q.get(k).map(|w| v * w)).sum()
+}
+
+fn nearest(profile: &Profile, centers: &[Profile]) -> usize {
+    let mut best = 0;
+    let mut best_sim = f64::NEG_INFINITY;
+    for (i, center) in centers.iter().enumerate() {
+        let sim = similarity(profile, center);
+        if sim > best_sim {
+            best = i;
+            best_sim = sim;
+        }
+    }
+    best
+}
+
+/// Split `trees` into at most `k` clusters of similar trees.
+///
+/// Returns the indices of the trees in each cluster. Deterministic: the
+/// result only depends on the order of `trees`.
+pub fn cluster(trees: &[&Tree], k: usize) -> Vec<Vec<usize>> {
+    if trees.is_empty() || k == 0 {
+        return Vec::new();
+    }
+    let profiles: Vec<_> = trees.iter().map(|t| profile(t)).collect();
+
+    // Start with centers that are far apart: repeatedly add the profile
+    // that's least similar to all the centers so far.
+    let mut centers = vec![profiles[0].clone()];
+    while centers.len() < k.min(profiles.len()) {
+        let farthest = profiles
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let closest = centers
+                    .iter()
+                    .map(|c| similarity(p, c))
+                    .fold(f64::NEG_INFINITY, f64::max);
+                (i, closest)
+            })
+            .min_by(|(_, s1), (_, s2)| s1.total_cmp(s2))
+            .map(|(i, _)| i)
+            .unwrap();
+        centers.push(profiles[farthest].clone());
+    }
+
+    let mut assignment = vec![0; profiles.len()];
+    for _ in 0..ROUNDS {
+        for (i, p) in profiles.iter().enumerate() {
+            assignment[i] = nearest(p, &centers);
+        }
+        let mut sums = vec![Profile::new(); centers.len()];
+        for (p, c) in profiles.iter().zip(&assignment) {
+            for (kind, v) in p {
+                *sums[*c].entry(*kind).or_insert(0.0) += v;
+            }
+        }
+        for (center, sum) in centers.iter_mut().zip(sums) {
+            // Keep the old center if nothing was assigned to it
+            if !sum.is_empty() {
+                *center = normalize(sum);
+            }
+        }
+    }
+
+    let mut clusters = vec![Vec::new(); centers.len()];
+    for (i, c) in assignment.into_iter().enumerate() {
+        clusters[c].push(i);
+    }
+    clusters.retain(|c| !c.is_empty());
+    clusters
+}
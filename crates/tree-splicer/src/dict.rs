@@ -0,0 +1,83 @@
+//! AFL-style dictionaries of tokens, see [`parse`] and [`entry`].
+
+/// The tokens of an AFL-style dictionary, e.g., from AFL++'s `dictionaries`
+/// directory.
+///
+/// Each line is a token in double quotes, optionally named (`kw_fn="fn"`)
+/// and with a level (`kw_fn@1="fn"`), which is ignored. `\\`, `\"`, and
+/// `\xNN` are escapes. Blank lines and lines starting with `#` are skipped.
+pub fn parse(text: &[u8]) -> Result<Vec<Vec<u8>>, String> {
+    let mut tokens = Vec::new();
+    for (i, line) in text.split(|b| *b == b'\n').enumerate() {
+        let line = line.trim_ascii();
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+        let token = token(line).map_err(|e| format!("Line {}: {}", i + 1, e))?;
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// A line of an AFL-style dictionary, `name="token"`, escaping `token` so
+/// that [`parse`] reads it back
+pub fn entry(name: &str, token: &[u8]) -> String {
+    let mut line = format!("{}=\"", name);
+    for b in token {
+        match b {
+            b'"' | b'\\' => {
+                line.push('\\');
+                line.push(char::from(*b));
+            }
+            b' '..=b'~' => line.push(char::from(*b)),
+            _ => line.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    line.push('"');
+    line
+}
+
+fn token(line: &[u8]) -> Result<Vec<u8>, String> {
+    let start = line
+        .iter()
+        .position(|b| *b == b'"')
+        .ok_or("Expected a token in double quotes")?;
+    let name = line[..start].trim_ascii_end();
+    let name = name.strip_suffix(b"=").unwrap_or(name).trim_ascii_end();
+    let name = match name.iter().position(|b| *b == b'@') {
+        Some(at) => &name[..at],
+        None => name,
+    };
+    if !name.iter().all(|b| b.is_ascii_alphanumeric() || *b == b'_') {
+        return Err("Expected NAME=\"TOKEN\" or \"TOKEN\"".to_string());
+    }
+    let quoted = &line[start + 1..];
+    let mut token = Vec::with_capacity(quoted.len());
+    let mut bytes = quoted.iter();
+    loop {
+        match bytes.next() {
+            None => return Err("Unclosed double quote".to_string()),
+            Some(b'"') => break,
+            Some(b'\\') => match bytes.next() {
+                Some(b'\\') => token.push(b'\\'),
+                Some(b'"') => token.push(b'"'),
+                Some(b'x') => {
+                    let hex = [bytes.next(), bytes.next()];
+                    let byte = match hex {
+                        [Some(h), Some(l)] => std::str::from_utf8(&[*h, *l])
+                            .ok()
+                            .and_then(|s| u8::from_str_radix(s, 16).ok()),
+                        _ => None,
+                    };
+                    token.push(byte.ok_or("Expected two hex digits after \\x")?);
+                }
+                _ => return Err("Expected \\\\, \\\", or \\xNN".to_string()),
+            },
+            Some(b) => token.push(*b),
+        }
+    }
+    if !bytes.as_slice().trim_ascii().is_empty() {
+        return Err("Unexpected text after the token".to_string());
+    }
+    Ok(token)
+}
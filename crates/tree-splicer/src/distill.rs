@@ -0,0 +1,90 @@
+//! Pick a small subset of a corpus with (nearly) all of its splice
+//! candidates, to make [`Splicer::new`](crate::Splicer::new) faster on large
+//! corpora.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use tree_sitter::Tree;
+
+/// The distinct splice candidates of a file, as hashes of their kinds and
+/// text
+fn candidates(text: &[u8], tree: &Tree) -> HashSet<u64> {
+    let mut candidates = HashSet::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        let mut hasher = DefaultHasher::new();
+        node.kind_id().hash(&mut hasher);
+        text[node.byte_range()].hash(&mut hasher);
+        candidates.insert(hasher.finish());
+        if cursor.goto_first_child() || cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return candidates;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+/// What [`distill`] picked
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Distilled {
+    /// Indices of the picked files, in the order they were picked
+    pub files: Vec<usize>,
+    /// Number of distinct splice candidates in the picked files
+    pub covered: usize,
+    /// Number of distinct splice candidates in all of the files
+    pub total: usize,
+}
+
+/// Pick files that together have at least `coverage` (a fraction, e.g.,
+/// `0.99`) of the distinct splice candidates of all of `files`.
+///
+/// Greedily picks the file with the most candidates that the files picked so
+/// far don't have, preferring smaller files, so the subset is small but not
+/// always the smallest.
+pub fn distill(files: &[(&[u8], &Tree)], coverage: f64) -> Distilled {
+    let sets: Vec<_> = files
+        .iter()
+        .map(|(text, tree)| candidates(text, tree))
+        .collect();
+    let total = sets.iter().flatten().collect::<HashSet<_>>().len();
+    let needed = (coverage.clamp(0.0, 1.0) * total as f64).ceil() as usize;
+    let mut covered = HashSet::with_capacity(needed);
+    let mut picked = Vec::new();
+    // Gains only shrink as files are picked, so a file whose up-to-date gain
+    // is still the largest is the best
+    let mut heap: BinaryHeap<_> = sets
+        .iter()
+        .enumerate()
+        .map(|(i, set)| (set.len(), Reverse(files[i].0.len()), Reverse(i)))
+        .collect();
+    while covered.len() < needed {
+        let Some((gain, size, Reverse(i))) = heap.pop() else {
+            break;
+        };
+        let new = sets[i].iter().filter(|c| !covered.contains(*c)).count();
+        if new == 0 {
+            continue;
+        }
+        if new < gain {
+            heap.push((new, size, Reverse(i)));
+            continue;
+        }
+        covered.extend(sets[i].iter().copied());
+        picked.push(i);
+    }
+    Distilled {
+        files: picked,
+        covered: covered.len(),
+        total,
+    }
+}
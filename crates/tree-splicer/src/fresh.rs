@@ -0,0 +1,280 @@
+//! Make files from scratch, using only `node-types.json`.
+
+use std::collections::{HashMap, HashSet};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use crate::node_types::{Node, Subtype};
+
+/// How many times to repeat a field or children that may be repeated
+const MAX_REPEATS: usize = 3;
+
+/// A field of a node kind, or its children
+#[derive(Debug)]
+struct Slot {
+    multiple: bool,
+    required: bool,
+    types: Vec<Subtype>,
+}
+
+#[derive(Debug, Default)]
+struct Kind {
+    subtypes: Vec<Subtype>,
+    /// Fields in order of their names, then children
+    slots: Vec<Slot>,
+}
+
+#[derive(Debug)]
+struct Grammar {
+    kinds: HashMap<String, Kind>,
+    /// Depth of the shallowest tree of each kind, `usize::MAX` if there's none
+    costs: HashMap<String, usize>,
+    tokens: HashMap<String, Vec<String>>,
+}
+
+/// Text for a node that isn't in the dictionary and has no named parts.
+/// Leaves are usually identifiers, so their kind is a good guess.
+fn fallback(kind: &str, leaf: bool) -> Option<String> {
+    if ["integer", "number", "float"]
+        .iter()
+        .any(|k| kind.contains(k))
+    {
+        Some("0".to_string())
+    } else if kind.contains("string") {
+        Some("\"\"".to_string())
+    } else {
+        leaf.then(|| kind.to_string())
+    }
+}
+
+impl Grammar {
+    fn new(nodes: Vec<Node>, tokens: HashMap<String, Vec<String>>) -> Self {
+        let kinds: HashMap<_, _> = nodes
+            .into_iter()
+            .filter(|n| n.named)
+            .map(|n| {
+                let mut fields: Vec<_> = n.fields.into_iter().collect();
+                fields.sort_by(|(n1, _), (n2, _)| n1.cmp(n2));
+                let mut slots: Vec<_> = fields
+                    .into_iter()
+                    .map(|(_, f)| Slot {
+                        multiple: f.multiple,
+                        required: f.required,
+                        types: f.types,
+                    })
+                    .collect();
+                if !n.children.types.is_empty() {
+                    slots.push(Slot {
+                        multiple: n.children.multiple,
+                        required: n.children.required,
+                        types: n.children.types,
+                    });
+                }
+                let kind = Kind {
+                    subtypes: n.subtypes,
+                    slots,
+                };
+                (n.ty, kind)
+            })
+            .collect();
+        let mut grammar = Grammar {
+            costs: kinds.keys().map(|k| (k.clone(), usize::MAX)).collect(),
+            kinds,
+            tokens,
+        };
+        // Each round finds the costs of kinds one level deeper
+        for _ in 0..=grammar.kinds.len() {
+            let costs: HashMap<_, _> = grammar
+                .kinds
+                .iter()
+                .map(|(name, kind)| (name.clone(), grammar.kind_cost(name, kind)))
+                .collect();
+            if costs == grammar.costs {
+                break;
+            }
+            grammar.costs = costs;
+        }
+        grammar
+    }
+
+    fn cost(&self, ty: &Subtype) -> usize {
+        if !ty.named || self.tokens.contains_key(&ty.ty) {
+            return 0;
+        }
+        self.costs.get(&ty.ty).copied().unwrap_or(0)
+    }
+
+    fn kind_cost(&self, name: &str, kind: &Kind) -> usize {
+        if self.tokens.contains_key(name) {
+            return 0;
+        }
+        if !kind.subtypes.is_empty() {
+            // One more than the subtypes, so that cycles of supertypes end
+            // at a kind that isn't one
+            let min = kind.subtypes.iter().map(|t| self.cost(t)).min().unwrap();
+            return min.saturating_add(1);
+        }
+        kind.slots
+            .iter()
+            .filter(|s| s.required)
+            .map(|s| s.types.iter().map(|t| self.cost(t)).min().unwrap_or(0))
+            .max()
+            .map_or(0, |c| c.saturating_add(1))
+    }
+
+    /// Any of `types` while there's depth to spare, otherwise the shallowest
+    fn pick<'t>(&self, rng: &mut ChaCha12Rng, types: &'t [Subtype], depth: usize) -> &'t Subtype {
+        if depth > 0 {
+            return types.choose(rng).unwrap();
+        }
+        let min = types.iter().map(|t| self.cost(t)).min().unwrap();
+        let shallowest: Vec<_> = types.iter().filter(|t| self.cost(t) == min).collect();
+        shallowest.choose(rng).unwrap()
+    }
+
+    /// Expand optional parts while `depth > 0`, and all of them if `top`.
+    fn expand(
+        &self,
+        rng: &mut ChaCha12Rng,
+        ty: &Subtype,
+        depth: usize,
+        top: bool,
+        out: &mut Vec<String>,
+    ) {
+        if !ty.named {
+            out.push(ty.ty.clone());
+            return;
+        }
+        if let Some(tokens) = self.tokens.get(&ty.ty) {
+            out.extend(tokens.choose(rng).cloned());
+            return;
+        }
+        let (Some(kind), false) = (
+            self.kinds.get(&ty.ty),
+            depth == 0 && self.cost(ty) == usize::MAX,
+        ) else {
+            out.extend(fallback(&ty.ty, true));
+            return;
+        };
+        if !kind.subtypes.is_empty() {
+            // Supertypes can be subtypes of each other, so each one counts as
+            // a level
+            let subtype = self.pick(rng, &kind.subtypes, depth);
+            self.expand(rng, subtype, depth.saturating_sub(1), top, out);
+            return;
+        }
+        let before = out.len();
+        for slot in &kind.slots {
+            let skip = depth == 0 || (!top && rng.gen());
+            if slot.types.is_empty() || (!slot.required && skip) {
+                continue;
+            }
+            let repeats = if slot.multiple && depth > 0 {
+                rng.gen_range(1..=MAX_REPEATS)
+            } else {
+                1
+            };
+            for _ in 0..repeats {
+                let child = self.pick(rng, &slot.types, depth);
+                self.expand(rng, child, depth.saturating_sub(1), false, out);
+            }
+        }
+        if out.len() == before {
+            out.extend(fallback(&ty.ty, kind.slots.is_empty()));
+        }
+    }
+}
+
+/// Makes random files by recursively expanding the fields and children of
+/// nodes, as described by `node-types.json`.
+///
+/// `node-types.json` doesn't say what order fields come in, nor which
+/// keywords and punctuation go between them, so most files won't parse.
+/// They're meant to bootstrap a corpus for a language with no seed files.
+#[derive(Debug)]
+pub struct Generator {
+    grammar: Grammar,
+    /// Kinds that aren't part of any other kind, e.g., `source_file`
+    roots: Vec<String>,
+    /// Optional fields and children are only expanded this many levels deep
+    pub depth: usize,
+    rng: ChaCha12Rng,
+}
+
+impl Generator {
+    /// `tokens` has text for leaf kinds, e.g., `identifier` to `["x", "y"]`.
+    pub fn new(
+        node_types_json_str: &str,
+        tokens: HashMap<String, Vec<String>>,
+        depth: usize,
+        seed: u64,
+    ) -> Result<Self, serde_json::Error> {
+        let nodes: Vec<Node> = serde_json::from_str(node_types_json_str)?;
+        let parts: HashSet<_> = nodes
+            .iter()
+            .flat_map(|n| {
+                n.fields
+                    .values()
+                    .flat_map(|f| &f.types)
+                    .chain(&n.children.types)
+                    .chain(&n.subtypes)
+            })
+            .map(|t| t.ty.clone())
+            .collect();
+        let roots = nodes
+            .iter()
+            .filter(|n| n.named && !parts.contains(&n.ty))
+            .map(|n| n.ty.clone())
+            .collect();
+        Ok(Generator {
+            grammar: Grammar::new(nodes, tokens),
+            roots,
+            depth,
+            rng: ChaCha12Rng::seed_from_u64(seed),
+        })
+    }
+
+    /// The kind of whole files, e.g., `source_file`, if it can be determined
+    pub fn root(&self) -> Option<&str> {
+        self.roots.first().map(String::as_str)
+    }
+
+    /// Make a node of the given kind, with tokens separated by spaces. It has
+    /// all of its own optional fields and children.
+    pub fn generate(&mut self, kind: &str) -> Vec<u8> {
+        let mut tokens = Vec::new();
+        let ty = Subtype {
+            ty: kind.to_string(),
+            named: true,
+        };
+        self.grammar
+            .expand(&mut self.rng, &ty, self.depth, true, &mut tokens);
+        tokens.join(" ").into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Node types whose supertypes are mutually recursive
+    const CYCLIC: &str = include_str!("../tests/cyclic-node-types.json");
+
+    #[test]
+    fn cyclic_generate() {
+        let tokens = HashMap::from([("identifier".to_string(), vec!["x".to_string()])]);
+        for seed in 0..100 {
+            let mut generator = Generator::new(CYCLIC, tokens.clone(), 4, seed).unwrap();
+            let text = String::from_utf8(generator.generate("_expression")).unwrap();
+            assert!(text.split(' ').all(|t| t == "x" || t == "+"), "{}", text);
+        }
+        // Supertypes with no other subtypes
+        let json = r#"[
+            { "type": "_a", "named": true, "subtypes": [{ "type": "_b", "named": true }] },
+            { "type": "_b", "named": true, "subtypes": [{ "type": "_a", "named": true }] }
+        ]"#;
+        let mut generator = Generator::new(json, HashMap::new(), 4, 0).unwrap();
+        assert_eq!(generator.generate("_a"), b"_a");
+    }
+}
@@ -0,0 +1,59 @@
+//! Byte- and token-level mutations, which exercise lexers.
+
+use rand::Rng;
+use tree_sitter::Node;
+
+/// Bytes that often start or end tokens
+const INTERESTING: &[u8] = b"\x00\xff\n\"'`\\{}()[]<>;,.:/*#$@";
+
+/// At most this many byte-level edits
+const MAX_ROUNDS: usize = 4;
+
+/// Byte ranges of the leaves of `node`, relative to its start
+fn tokens(node: Node<'_>) -> Vec<(usize, usize)> {
+    let start = node.start_byte();
+    let mut tokens = Vec::new();
+    let mut nodes = vec![node];
+    while let Some(node) = nodes.pop() {
+        if node.child_count() == 0 {
+            tokens.push((node.start_byte() - start, node.end_byte() - start));
+        } else {
+            let mut cursor = node.walk();
+            nodes.extend(node.children(&mut cursor));
+        }
+    }
+    tokens
+}
+
+/// Duplicate or delete a token of `node`, then flip bits, overwrite or insert
+/// bytes, or truncate.
+pub(crate) fn havoc(rng: &mut impl Rng, text: &[u8], node: Node<'_>) -> Vec<u8> {
+    let node_text = &text[node.byte_range()];
+    let mut out = node_text.to_vec();
+    let tokens = tokens(node);
+    if !tokens.is_empty() && rng.gen() {
+        let (start, end) = tokens[rng.gen_range(0..tokens.len())];
+        if rng.gen() {
+            out.splice(end..end, node_text[start..end].iter().copied());
+        } else {
+            out.drain(start..end);
+        }
+    }
+    for _ in 0..rng.gen_range(1..=MAX_ROUNDS) {
+        let byte = INTERESTING[rng.gen_range(0..INTERESTING.len())];
+        match rng.gen_range(0..4) {
+            0 => out.insert(rng.gen_range(0..=out.len()), byte),
+            _ if out.is_empty() => (),
+            1 => {
+                let idx = rng.gen_range(0..out.len());
+                out[idx] ^= 1 << rng.gen_range(0..8);
+            }
+            2 => {
+                let idx = rng.gen_range(0..out.len());
+                out[idx] = byte;
+            }
+            _ => out.truncate(rng.gen_range(0..out.len())),
+        }
+    }
+    out
+}
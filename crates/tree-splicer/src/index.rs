@@ -0,0 +1,132 @@
+//! Splice candidates of a corpus, computed once and shared between splicers.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Tree;
+
+use crate::splice::{Branches, Splicer};
+
+/// FNV-1a, which unlike `DefaultHasher` is the same in every build, so
+/// hashes can be saved and compared in later runs
+pub(crate) fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A file that candidates come from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Source {
+    pub(crate) path: String,
+    pub(crate) len: usize,
+    pub(crate) hash: u64,
+}
+
+impl Source {
+    fn new(path: &str, text: &[u8]) -> Self {
+        Source {
+            path: path.to_string(),
+            len: text.len(),
+            hash: hash(text),
+        }
+    }
+}
+
+/// The distinct splice candidates of some files and donors, by node kind.
+///
+/// Finding the candidates takes time in the size of the corpus. An index can
+/// be built once and shared (e.g., in an `Arc`) between splicers on several
+/// threads, see [`Splicer::new_with_index`], or saved with `serde` and
+/// loaded in a later run on the same files.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CorpusIndex {
+    /// Files, then donors, in the order that splicers see them
+    pub(crate) sources: Vec<Source>,
+    /// Index into `sources`, byte range, and weight of each candidate
+    pub(crate) candidates: BTreeMap<String, Vec<(usize, usize, usize, u32)>>,
+}
+
+impl CorpusIndex {
+    #[allow(clippy::type_complexity)]
+    fn sources<'a>(
+        files: &'a HashMap<String, (Vec<u8>, Tree)>,
+        donors: &[(&'a HashMap<String, (Vec<u8>, Tree)>, u32)],
+    ) -> Vec<(&'a str, &'a [u8], &'a Tree, u32)> {
+        let mut all: Vec<_> = Splicer::sorted(files)
+            .into_iter()
+            .map(|(p, txt, tree)| (p, txt, tree, 1))
+            .collect();
+        for (corpus, weight) in donors {
+            for (p, txt, tree) in Splicer::sorted(corpus) {
+                all.push((p, txt, tree, *weight));
+            }
+        }
+        all
+    }
+
+    /// Index `files` and `donors`, as for
+    /// [`Splicer::new_with_weighted_donors`].
+    #[allow(clippy::type_complexity)]
+    pub fn new(
+        files: &HashMap<String, (Vec<u8>, Tree)>,
+        donors: &[(&HashMap<String, (Vec<u8>, Tree)>, u32)],
+    ) -> Self {
+        let all = Self::sources(files, donors);
+        let branches = Branches::new(
+            all.iter()
+                .map(|(_, txt, tree, weight)| (*txt, *tree, *weight))
+                .collect(),
+        );
+        let candidates = branches
+            .candidates
+            .iter()
+            .map(|(kind, candidates)| {
+                let located = candidates
+                    .iter()
+                    .zip(&branches.weights[kind])
+                    .zip(&branches.origins[kind])
+                    .map(|((candidate, weight), origin)| {
+                        let (idx, start) = origin.expect("Candidates of files have origins");
+                        (idx, start, start + candidate.len(), *weight)
+                    })
+                    .collect();
+                (kind.to_string(), located)
+            })
+            .collect();
+        CorpusIndex {
+            sources: all
+                .iter()
+                .map(|(path, txt, _, _)| Source::new(path, txt))
+                .collect(),
+            candidates,
+        }
+    }
+
+    /// Whether this is the index of `files` and `donors`, i.e., they have the
+    /// same paths and contents as when it was built.
+    #[allow(clippy::type_complexity)]
+    pub fn describes(
+        &self,
+        files: &HashMap<String, (Vec<u8>, Tree)>,
+        donors: &[(&HashMap<String, (Vec<u8>, Tree)>, u32)],
+    ) -> bool {
+        let all = Self::sources(files, donors);
+        all.len() == self.sources.len()
+            && all
+                .iter()
+                .zip(&self.sources)
+                .all(|((path, txt, _, _), source)| {
+                    source.path == *path && source.len == txt.len() && source.hash == hash(txt)
+                })
+    }
+
+    /// Number of distinct candidates
+    pub fn len(&self) -> usize {
+        self.candidates.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+}
@@ -0,0 +1,128 @@
+//! Code in one language embedded in another, e.g., JavaScript in HTML or
+//! Rust in Markdown.
+
+use std::collections::HashMap;
+
+use tree_sitter::{Language, Node, Tree};
+
+use crate::node_types::NodeTypes;
+use crate::splice::{parse, Edits, Splicer};
+use crate::trace::{Edit, Operation};
+
+/// Code in one language embedded in another
+#[derive(Clone, Debug)]
+pub struct Injection {
+    /// Language of the embedded code
+    pub language: Language,
+    pub node_types: NodeTypes,
+    /// Kind of the host node containing the embedded code, e.g., `script_element`
+    pub parent_kind: &'static str,
+    /// Kind of the host node whose text is the embedded code, e.g., `raw_text`
+    pub kind: &'static str,
+    pub tag: Option<Tag>,
+}
+
+/// Names the language of embedded code, e.g., the info string of a Markdown
+/// code block
+///
+/// An [`Injection`] with a tag only applies where the parent node has a
+/// descendant of kind `kind` with one of the `names` as its text.
+#[derive(Clone, Copy, Debug)]
+pub struct Tag {
+    pub kind: &'static str,
+    pub names: &'static [&'static str],
+}
+
+impl Tag {
+    fn matches(&self, text: &[u8], parent: Node<'_>) -> bool {
+        let mut nodes = vec![parent];
+        while let Some(node) = nodes.pop() {
+            if node.kind() == self.kind {
+                return self
+                    .names
+                    .iter()
+                    .any(|n| n.as_bytes() == &text[node.byte_range()]);
+            }
+            let mut cursor = parent.walk();
+            nodes.extend(node.children(&mut cursor));
+        }
+        false
+    }
+}
+
+impl Injection {
+    fn nodes<'t>(&self, text: &[u8], tree: &'t Tree) -> Vec<Node<'t>> {
+        let mut found = Vec::new();
+        let mut nodes = vec![tree.root_node()];
+        while let Some(node) = nodes.pop() {
+            if node.kind() == self.kind {
+                if let Some(parent) = node.parent() {
+                    if parent.kind() == self.parent_kind
+                        && self.tag.map_or(true, |t| t.matches(text, parent))
+                    {
+                        found.push(node);
+                        continue;
+                    }
+                }
+            }
+            let mut cursor = tree.walk();
+            nodes.extend(node.children(&mut cursor));
+        }
+        found
+    }
+
+    /// Parse the embedded code in each of the host `files`.
+    pub fn extract(
+        &self,
+        files: &HashMap<String, (Vec<u8>, Tree)>,
+    ) -> HashMap<String, (Vec<u8>, Tree)> {
+        let mut embedded = HashMap::new();
+        for (path, (text, tree)) in files {
+            for (i, node) in self.nodes(text, tree).into_iter().enumerate() {
+                let code = Vec::from(&text[node.byte_range()]);
+                let tree = parse(self.language, &code);
+                embedded.insert(format!("{}:{}", path, i), (code, tree));
+            }
+        }
+        embedded
+    }
+
+    /// Replace the embedded code in `text` with mutants from `splicer`.
+    ///
+    /// `splicer` should have been built from the output of [`Injection::extract`].
+    pub fn splice(&self, splicer: &mut Splicer<'_>, text: &[u8], tree: &Tree) -> Option<Vec<u8>> {
+        self.splice_traced(splicer, text, tree)
+            .map(|(result, _)| result)
+    }
+
+    /// Like [`Injection::splice`], but also return the edits to `text` as a
+    /// step of a trace.
+    pub fn splice_traced(
+        &self,
+        splicer: &mut Splicer<'_>,
+        text: &[u8],
+        tree: &Tree,
+    ) -> Option<(Vec<u8>, Vec<Edit>)> {
+        let mut edits = Edits::default();
+        let mut step = Vec::new();
+        for node in self.nodes(text, tree) {
+            let code = &text[node.byte_range()];
+            let code_tree = parse(self.language, code);
+            if let Some(mutant) = splicer.splice_tree(code, code_tree) {
+                step.push(Edit {
+                    operation: Operation::Embedded,
+                    kind: node.kind().to_string(),
+                    start: node.start_byte(),
+                    end: node.end_byte(),
+                    replacement: mutant.as_slice().into(),
+                    origin: None,
+                });
+                edits.0.insert(node.id(), mutant);
+            }
+        }
+        step.sort_by_key(|e| e.start);
+        let mut result = Vec::with_capacity(text.len());
+        tree_sitter_edit::render(&mut result, tree, text, &edits).ok()?;
+        Some((result, step))
+    }
+}
@@ -1,4 +1,28 @@
+pub mod builder;
 #[cfg(feature = "cli")]
 pub mod cli;
+pub(crate) mod cluster;
+pub(crate) mod dict;
+pub(crate) mod distill;
+pub(crate) mod fresh;
+mod havoc;
+pub(crate) mod index;
+pub(crate) mod injection;
+mod literal;
+pub(crate) mod mutate;
 pub mod node_types;
+pub(crate) mod preprocess;
+pub(crate) mod rules;
 pub mod splice;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub(crate) mod templates;
+pub(crate) mod trace;
+pub(crate) mod type_hint;
+
+pub use builder::SplicerBuilder;
+// For the fuzzer and HTML/Markdown crates in this workspace
+pub use injection::{Injection, Tag};
+pub use mutate::Mutator;
+pub use node_types::NodeTypes;
+pub use splice::{Config, OperatorWeights, Splicer};
@@ -0,0 +1,166 @@
+//! Edge cases for literals, which splicing only finds if the inputs have them.
+
+/// Longest string to make, if `max_size` allows
+const LONG_STRING: usize = 65536;
+
+const INTEGERS: &[&str] = &[
+    "0",
+    "1",
+    "-1",
+    "127",
+    "128",
+    "255",
+    "256",
+    "65535",
+    "65536",
+    "2147483647",
+    "-2147483648",
+    "4294967295",
+    "9223372036854775807",
+    "-9223372036854775808",
+    "18446744073709551615",
+    "340282366920938463463374607431768211455",
+];
+
+const FLOATS: &[&str] = &[
+    "0.0",
+    "-0.0",
+    "NaN",
+    "inf",
+    "-inf",
+    "1e308",
+    "1e309",
+    "5e-324",
+    "1.7976931348623157e308",
+    "0.1",
+];
+
+/// String contents, without quotes
+const STRINGS: &[&str] = &[
+    "",
+    "\\\\",
+    "\\n",
+    "\\0",
+    "\\x00",
+    "\\x7f",
+    "\\u0000",
+    "\\u{10FFFF}",
+    "\\uD800",
+    "${x}",
+    "%s%n",
+    "\u{202E}",
+    "\u{1F600}",
+];
+
+const CHARS: &[&str] = &[
+    "\\0",
+    "\\\\",
+    "\\'",
+    "\\n",
+    "\\u{10FFFF}",
+    "\\x7f",
+    "\u{1F600}",
+];
+
+/// A kind of literal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Literal {
+    Integer,
+    Float,
+    String,
+    Char,
+    Boolean,
+}
+
+fn quoted(text: &[u8]) -> bool {
+    text.len() >= 2 && matches!(text[0], b'"' | b'\'' | b'`') && text.last() == text.first()
+}
+
+fn numeric(text: &[u8]) -> bool {
+    text.first()
+        .is_some_and(|b| b.is_ascii_digit() || matches!(b, b'-' | b'.'))
+}
+
+impl Literal {
+    pub(crate) const ALL: [Literal; 5] = [
+        Literal::Integer,
+        Literal::Float,
+        Literal::String,
+        Literal::Char,
+        Literal::Boolean,
+    ];
+
+    /// Guess which kind of literal a node is from its kind, e.g.,
+    /// `integer_literal`, and its text
+    pub(crate) fn of(kind: &str, text: &[u8]) -> Option<Self> {
+        if kind.contains("char") && quoted(text) {
+            Some(Literal::Char)
+        } else if kind.contains("string") && quoted(text) {
+            Some(Literal::String)
+        } else if kind.contains("float") && numeric(text) {
+            Some(Literal::Float)
+        } else if (kind.contains("integer") || kind.contains("number")) && numeric(text) {
+            if text.iter().any(|b| matches!(b, b'.' | b'e' | b'E')) && !text.starts_with(b"0x") {
+                Some(Literal::Float)
+            } else {
+                Some(Literal::Integer)
+            }
+        } else if (kind.contains("bool") || kind == "true" || kind == "false")
+            && matches!(text, b"true" | b"false")
+        {
+            Some(Literal::Boolean)
+        } else {
+            None
+        }
+    }
+
+    /// Guess which kind of literal a node is from its text alone, e.g., for
+    /// [`crate::splice::Config::literal_kinds`]. Defaults to a string.
+    pub(crate) fn guess(text: &[u8]) -> Self {
+        if matches!(text, b"true" | b"false") {
+            Literal::Boolean
+        } else if numeric(text) && std::str::from_utf8(text).is_ok_and(|t| t.parse::<f64>().is_ok())
+        {
+            if text.iter().any(|b| matches!(b, b'.' | b'e' | b'E')) {
+                Literal::Float
+            } else {
+                Literal::Integer
+            }
+        } else {
+            Literal::String
+        }
+    }
+
+    /// Values to replace `text` with. Strings and chars keep the quotes of
+    /// `text`, if it has any.
+    pub(crate) fn values(self, text: &[u8], max_size: usize) -> Vec<Vec<u8>> {
+        let quote = |contents: &[u8], default: u8| {
+            let q = if quoted(text) { text[0] } else { default };
+            [&[q], contents, &[q]].concat()
+        };
+        match self {
+            Literal::Integer => {
+                let mut values: Vec<_> = INTEGERS.iter().map(|v| v.as_bytes().to_vec()).collect();
+                if let Some(n) = std::str::from_utf8(text)
+                    .ok()
+                    .and_then(|t| t.parse::<i128>().ok())
+                {
+                    values.extend(n.checked_add(1).map(|n| n.to_string().into_bytes()));
+                    values.extend(n.checked_sub(1).map(|n| n.to_string().into_bytes()));
+                }
+                values
+            }
+            Literal::Float => FLOATS.iter().map(|v| v.as_bytes().to_vec()).collect(),
+            Literal::String => {
+                let mut values: Vec<_> =
+                    STRINGS.iter().map(|v| quote(v.as_bytes(), b'"')).collect();
+                for len in [256, LONG_STRING.min(max_size / 2)] {
+                    values.push(quote(&vec![b'A'; len], b'"'));
+                }
+                values
+            }
+            Literal::Char => CHARS.iter().map(|v| quote(v.as_bytes(), b'\'')).collect(),
+            Literal::Boolean => vec![b"true".to_vec(), b"false".to_vec()],
+        }
+    }
+}
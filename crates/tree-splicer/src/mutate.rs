@@ -0,0 +1,49 @@
+//! Mutate one input at a time, e.g., from inside a fuzzer.
+
+use std::collections::HashMap;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use crate::splice::{parse, Config, Splicer};
+
+/// Splices inputs one at a time, using the input itself and any donors as
+/// the source of splice candidates
+#[derive(Debug)]
+pub struct Mutator {
+    /// `config.seed` only seeds the mutator, each mutation gets a new seed
+    pub config: Config,
+    rng: ChaCha12Rng,
+}
+
+impl Mutator {
+    pub fn new(config: Config) -> Self {
+        let rng = ChaCha12Rng::seed_from_u64(config.seed);
+        Mutator { config, rng }
+    }
+
+    /// Reset the random number generator, e.g., to the seed a fuzzer passes in
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+    }
+
+    /// Splice `input`, taking candidates from it and from `donors`.
+    ///
+    /// Returns `None` if no mutation was possible.
+    pub fn mutate(&mut self, input: &[u8], donors: &[&[u8]]) -> Option<Vec<u8>> {
+        let config = Config {
+            seed: self.rng.gen(),
+            ..self.config.clone()
+        };
+        let tree = parse(config.language, input);
+        let mut files = HashMap::with_capacity(1);
+        files.insert(String::new(), (input.to_vec(), tree.clone()));
+        let donors: HashMap<_, _> = donors
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (i.to_string(), (d.to_vec(), parse(config.language, d))))
+            .collect();
+        let mut splicer = Splicer::new_with_donors(config, &files, &donors);
+        splicer.splice_tree(input, tree)
+    }
+}
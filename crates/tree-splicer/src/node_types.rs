@@ -4,43 +4,57 @@
 //
 // [treeedbgen]: https://github.com/langston-barrett/treeedb/blob/1a2fae3509c76cd5a8e1004f808ea800d49d1a19/treeedbgen/src/lib.rs
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Read};
+use std::path::Path;
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Serialize};
 
 /// node-types.json
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
-struct Node {
+pub(crate) struct Node {
     #[serde(rename(deserialize = "type", serialize = "type"))]
-    ty: String,
-    named: bool,
+    pub(crate) ty: String,
+    pub(crate) named: bool,
     #[serde(default)] // empty
-    children: Children,
+    pub(crate) children: Children,
     #[serde(default)] // empty
-    fields: HashMap<String, Field>,
+    pub(crate) fields: HashMap<String, Field>,
     #[serde(default)] // empty
-    subtypes: Vec<Subtype>,
+    pub(crate) subtypes: Vec<Subtype>,
 }
 
 #[derive(Default, Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
-struct Children {
-    multiple: bool,
-    required: bool,
-    types: Vec<Subtype>,
+pub(crate) struct Children {
+    pub(crate) multiple: bool,
+    pub(crate) required: bool,
+    pub(crate) types: Vec<Subtype>,
 }
 
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
-struct Field {
-    multiple: bool,
-    required: bool,
-    types: Vec<Subtype>,
+pub(crate) struct Field {
+    pub(crate) multiple: bool,
+    pub(crate) required: bool,
+    pub(crate) types: Vec<Subtype>,
 }
 
 #[derive(Clone, Eq, PartialEq, Serialize, Deserialize, Debug)]
-struct Subtype {
+pub(crate) struct Subtype {
     #[serde(rename(deserialize = "type", serialize = "type"))]
-    ty: String,
-    named: bool,
+    pub(crate) ty: String,
+    pub(crate) named: bool,
+}
+
+/// A field of a node kind, see [`NodeTypes::fields_of`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: String,
+    /// Whether it can hold more than one node
+    pub multiple: bool,
+    /// Whether it must hold at least one node
+    pub required: bool,
+    /// The kinds it can hold, which may be supertypes or tokens
+    pub types: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -50,33 +64,95 @@ pub struct FieldInfo {
     required: bool,
 }
 
+/// What a grammar's `node-types.json` says about each node kind
+///
+/// Owns its data, so it can be built from `node-types.json` that's read at
+/// runtime, e.g., from a file.
 #[derive(Clone, Debug)]
 pub struct NodeTypes {
     children: HashMap<String, Children>,
+    fields: HashMap<String, HashMap<String, Field>>,
     subtypes: HashMap<String, Vec<String>>,
     reverse_fields: HashMap<String, Vec<FieldInfo>>,
 }
 
-fn subtypes(name: &str, nodes: &Vec<Node>) -> Vec<String> {
-    let mut r = vec![name.to_string()];
-    for n in nodes {
-        if n.ty == name {
-            for subty in &n.subtypes {
-                r.push(subty.ty.clone());
-                r.extend(subtypes(&subty.ty, nodes));
+/// Each kind and its subtypes, transitively, each once. Supertypes can be
+/// mutually recursive, so this uses a worklist and a set of seen kinds
+/// rather than recursion, and reuses the subtypes of kinds that are done.
+fn subtypes(nodes: &[Node]) -> HashMap<String, Vec<String>> {
+    let mut direct: HashMap<&str, Vec<&str>> = HashMap::with_capacity(nodes.len());
+    for node in nodes {
+        direct
+            .entry(&node.ty)
+            .or_default()
+            .extend(node.subtypes.iter().map(|s| s.ty.as_str()));
+    }
+    let mut done: HashMap<&str, Vec<&str>> = HashMap::with_capacity(direct.len());
+    for node in nodes {
+        let kind = node.ty.as_str();
+        if done.contains_key(kind) {
+            continue;
+        }
+        let mut seen = HashSet::from([kind]);
+        let mut kinds = vec![kind];
+        let mut work: Vec<&str> = direct[kind].iter().rev().copied().collect();
+        while let Some(ty) = work.pop() {
+            if !seen.insert(ty) {
+                continue;
+            }
+            kinds.push(ty);
+            match done.get(ty) {
+                Some(subtypes) => kinds.extend(subtypes.iter().filter(|t| seen.insert(t))),
+                None => work.extend(direct.get(ty).into_iter().flatten().rev()),
             }
         }
+        done.insert(kind, kinds);
     }
-    r
+    done.into_iter()
+        .map(|(kind, kinds)| {
+            let kinds = kinds.into_iter().map(String::from).collect();
+            (kind.to_string(), kinds)
+        })
+        .collect()
 }
 
 impl NodeTypes {
+    /// Parse the contents of `node-types.json`.
+    ///
+    /// The error says which node is malformed, if any, by its index and kind.
     pub fn new(node_types_json_str: &str) -> Result<Self, serde_json::Error> {
-        let nodes: Vec<Node> = serde_json::from_str(node_types_json_str)?;
-        let subtypes: HashMap<_, _> = nodes
-            .iter()
-            .map(|n| (n.ty.clone(), subtypes(&n.ty, &nodes)))
-            .collect();
+        Self::from_nodes(serde_json::from_str(node_types_json_str)?)
+    }
+
+    /// Read and parse `node-types.json`, see [`NodeTypes::new`].
+    pub fn from_reader(reader: impl Read) -> Result<Self, serde_json::Error> {
+        Self::from_nodes(serde_json::from_reader(reader)?)
+    }
+
+    /// Read and parse `node-types.json` at `path`, see [`NodeTypes::new`].
+    pub fn from_path(path: &Path) -> io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Self::from_reader(io::BufReader::new(file)).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    fn from_nodes(nodes: Vec<serde_json::Value>) -> Result<Self, serde_json::Error> {
+        let nodes = nodes
+            .into_iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let kind = node.get("type").and_then(|t| t.as_str()).map(String::from);
+                Node::deserialize(node).map_err(|e| {
+                    let kind = kind.map(|k| format!(" ({})", k)).unwrap_or_default();
+                    de::Error::custom(format!("Malformed node {}{}: {}", i, kind, e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let subtypes = subtypes(&nodes);
         let mut reverse_fields = HashMap::new();
 
         // For each type of node...
@@ -107,10 +183,17 @@ impl NodeTypes {
             }
         }
         Ok(NodeTypes {
+            // Keywords can have the same name as a named kind, e.g., `block`
             children: nodes
                 .iter()
+                .filter(|n| n.named)
                 .map(|n| (n.ty.clone(), n.children.clone()))
                 .collect(),
+            fields: nodes
+                .iter()
+                .filter(|n| n.named)
+                .map(|n| (n.ty.clone(), n.fields.clone()))
+                .collect(),
             subtypes,
             reverse_fields,
         })
@@ -128,6 +211,9 @@ impl NodeTypes {
         true
     }
 
+    /// Whether `node` can be missing from its parent, i.e., it isn't in a
+    /// required field of it that holds one node.
+    ///
     /// Defaults to `true` if the real answer can't be determined.
     pub fn optional_node(&self, node: &tree_sitter::Node) -> bool {
         if let Some(p) = node.parent() {
@@ -137,6 +223,109 @@ impl NodeTypes {
         }
     }
 
+    /// Whether the parent of `node` still has all of its required fields and
+    /// children after deleting `node`, and no keyword or punctuation loses the
+    /// node it goes with, e.g., `->` and a return type, or `,` in a list.
+    ///
+    /// Defaults to `false` if the real answer can't be determined, e.g., for
+    /// keywords and punctuation that aren't in a field. Extras like comments
+    /// and the root can always be deleted.
+    ///
+    /// A list element may lose its separator, see [`NodeTypes::separator`].
+    pub fn can_delete(&self, node: &tree_sitter::Node) -> bool {
+        if node.is_extra() {
+            return true;
+        }
+        let Some(parent) = node.parent() else {
+            return true;
+        };
+        let siblings = Self::siblings(&parent);
+        let Some(idx) = siblings.iter().position(|(n, _)| n.id() == node.id()) else {
+            return false;
+        };
+        let separator = Self::separator_at(&siblings, idx).map(|s| s.kind());
+        // Tokens at the ends of the parent are usually brackets, which don't
+        // depend on the node
+        let last = siblings.len() - 1;
+        for neighbor in [idx.checked_sub(1), Some(idx + 1)].into_iter().flatten() {
+            if neighbor == 0 || neighbor >= last {
+                continue;
+            }
+            let (n, _) = siblings[neighbor];
+            if !n.is_named() && Some(n.kind()) != separator {
+                return false;
+            }
+        }
+        let field = siblings[idx].1;
+        let (multiple, required) = match field {
+            Some(name) => match self.fields.get(parent.kind()).and_then(|f| f.get(name)) {
+                Some(f) => (f.multiple, f.required),
+                None => return false,
+            },
+            None if node.is_named() => match self.children.get(parent.kind()) {
+                Some(c) => (c.multiple, c.required),
+                None => return false,
+            },
+            None => return false,
+        };
+        // Other nodes in the same field, or in the children
+        let remaining = siblings
+            .iter()
+            .filter(|(n, f)| n.id() != node.id() && *f == field && (f.is_some() || n.is_named()))
+            .count();
+        !required || (multiple && remaining > 0)
+    }
+
+    /// The children of `parent` that aren't extras, and their fields
+    fn siblings<'t>(
+        parent: &tree_sitter::Node<'t>,
+    ) -> Vec<(tree_sitter::Node<'t>, Option<&'static str>)> {
+        let mut siblings = Vec::new();
+        let mut cursor = parent.walk();
+        if cursor.goto_first_child() {
+            loop {
+                if !cursor.node().is_extra() {
+                    siblings.push((cursor.node(), cursor.field_name()));
+                }
+                if !cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+        siblings
+    }
+
+    /// The token between `siblings[idx]` and the next (or else previous)
+    /// element of the same list, if any
+    fn separator_at<'t>(
+        siblings: &[(tree_sitter::Node<'t>, Option<&'static str>)],
+        idx: usize,
+    ) -> Option<tree_sitter::Node<'t>> {
+        let (node, field) = siblings[idx];
+        if !node.is_named() {
+            return None;
+        }
+        let element = |i: usize| siblings[i].0.is_named() && siblings[i].1 == field;
+        if idx + 2 < siblings.len() && !siblings[idx + 1].0.is_named() && element(idx + 2) {
+            return Some(siblings[idx + 1].0);
+        }
+        if idx >= 2 && !siblings[idx - 1].0.is_named() && element(idx - 2) {
+            return Some(siblings[idx - 1].0);
+        }
+        None
+    }
+
+    /// The separator to delete along with `node` if it's an element of a
+    /// list like `a, b, c`: the token (e.g., `,` or `;`) between it and the
+    /// next element, or the previous one if it's the last.
+    pub fn separator<'t>(&self, node: &tree_sitter::Node<'t>) -> Option<tree_sitter::Node<'t>> {
+        let siblings = Self::siblings(&node.parent()?);
+        let idx = siblings.iter().position(|(n, _)| n.id() == node.id())?;
+        Self::separator_at(&siblings, idx)
+    }
+
+    /// The kinds that can be added to or deleted from the children of
+    /// `node` that aren't in a field, if there can be any number of them.
     // TODO(#21): Also include fields, include multiple and not required
     pub fn list_types(&self, node: &tree_sitter::Node) -> Vec<String> {
         let mut kinds = Vec::new();
@@ -150,7 +339,70 @@ impl NodeTypes {
         kinds
     }
 
+    /// The tokens that can be in field `field` of a node of kind `kind`, if
+    /// it can only hold tokens and there are several, e.g., the operators of
+    /// a binary expression
+    pub fn field_tokens(&self, kind: &str, field: &str) -> Vec<&str> {
+        let Some(field) = self.fields.get(kind).and_then(|fields| fields.get(field)) else {
+            return Vec::new();
+        };
+        if field.types.len() < 2 || field.types.iter().any(|t| t.named) {
+            return Vec::new();
+        }
+        field.types.iter().map(|t| t.ty.as_str()).collect()
+    }
+
+    /// Whether `node-types.json` has a node of kind `kind`
+    pub fn has_kind(&self, kind: &str) -> bool {
+        self.subtypes.contains_key(kind)
+    }
+
+    /// `kind` and its subtypes, transitively, each once.
+    ///
+    /// Panics if there's no such kind, see [`NodeTypes::get_subtypes`].
     pub fn subtypes(&self, kind: &String) -> &[String] {
         self.subtypes.get(kind).expect("Invalid node kind")
     }
+
+    /// `kind` and its subtypes, transitively, each once, or nothing if
+    /// there's no such kind.
+    pub fn get_subtypes(&self, kind: &str) -> Vec<String> {
+        self.subtypes.get(kind).cloned().unwrap_or_default()
+    }
+
+    /// Whether `kind` is a supertype, like `_expression`, that only stands
+    /// for its subtypes and doesn't appear in trees
+    pub fn is_supertype(&self, kind: &str) -> bool {
+        self.subtypes.get(kind).is_some_and(|s| s.len() > 1)
+    }
+
+    /// The fields of named kind `kind`, by name
+    pub fn fields_of(&self, kind: &str) -> Vec<FieldSpec> {
+        let Some(fields) = self.fields.get(kind) else {
+            return Vec::new();
+        };
+        let mut fields: Vec<_> = fields
+            .iter()
+            .map(|(name, field)| FieldSpec {
+                name: name.clone(),
+                multiple: field.multiple,
+                required: field.required,
+                types: field.types.iter().map(|t| t.ty.clone()).collect(),
+            })
+            .collect();
+        fields.sort_by(|a, b| a.name.cmp(&b.name));
+        fields
+    }
+
+    /// The kinds of the children of named kind `kind` that aren't in a
+    /// field, if it must have at least one, otherwise nothing. Required
+    /// fields are in [`NodeTypes::fields_of`].
+    pub fn required_children(&self, kind: &str) -> Vec<String> {
+        match self.children.get(kind) {
+            Some(children) if children.required => {
+                children.types.iter().map(|t| t.ty.clone()).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
 }
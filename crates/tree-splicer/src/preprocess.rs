@@ -0,0 +1,196 @@
+//! Simplify inputs before splicing them, see [`preprocess`].
+
+use std::ops::Range;
+
+use tree_sitter::{Parser, Tree};
+
+use crate::literal::Literal;
+
+/// How [`preprocess`] simplifies an input
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Preprocess {
+    /// Delete comments, i.e., extra nodes whose kinds contain `comment`, and
+    /// the lines they leave empty
+    pub strip_comments: bool,
+    /// Delete trailing whitespace and runs of more than one blank line, and
+    /// make the whitespace between tokens on the same line one space.
+    /// Indentation is kept, e.g., for Python.
+    pub normalize_whitespace: bool,
+}
+
+impl Preprocess {
+    /// Whether [`preprocess`] changes anything
+    pub fn is_noop(&self) -> bool {
+        !self.strip_comments && !self.normalize_whitespace
+    }
+}
+
+/// `text`, which parses as `tree`, simplified so that splice candidates
+/// aren't dominated by comments and variants of the same code that only
+/// differ in whitespace, and its tree.
+///
+/// Only the text between tokens changes, so strings and the like are kept.
+/// If the tokens change anyway, e.g., because the grammar has significant
+/// whitespace that isn't in a token, `text` is returned unchanged.
+pub fn preprocess(text: &[u8], tree: &Tree, options: Preprocess) -> (Vec<u8>, Tree) {
+    let unchanged = || (text.to_vec(), tree.clone());
+    if options.is_noop() {
+        return unchanged();
+    }
+    let tokens = tokens(text, tree);
+    let mut out = Vec::with_capacity(text.len());
+    let mut start = 0;
+    let mut comments = Vec::new();
+    let mut first = true;
+    for (range, comment) in &tokens {
+        if *comment && options.strip_comments {
+            comments.push(range.clone());
+            continue;
+        }
+        let between = start..range.start;
+        gap(&mut out, text, between, &comments, options, first, false);
+        out.extend(&text[range.clone()]);
+        start = range.end;
+        comments.clear();
+        first = false;
+    }
+    gap(
+        &mut out,
+        text,
+        start..text.len(),
+        &comments,
+        options,
+        first,
+        true,
+    );
+
+    let mut parser = Parser::new();
+    if parser.set_language(tree.language()).is_err() {
+        return unchanged();
+    }
+    let Some(new_tree) = parser.parse(&out, None) else {
+        return unchanged();
+    };
+    let kept = |text: &[u8], tokens: Vec<(Range<usize>, bool)>| {
+        tokens
+            .into_iter()
+            .filter(|(_, comment)| !comment || !options.strip_comments)
+            .map(|(range, _)| text[range].to_vec())
+            .collect::<Vec<_>>()
+    };
+    if kept(text, tokens) != kept(&out, self::tokens(&out, &new_tree)) {
+        return unchanged();
+    }
+    (out, new_tree)
+}
+
+/// The byte ranges of the leaves of `tree`, of its comments, and of its
+/// string and character literals, in order, and whether each is a comment
+fn tokens(text: &[u8], tree: &Tree) -> Vec<(Range<usize>, bool)> {
+    let mut tokens = Vec::new();
+    let mut cursor = tree.walk();
+    'walk: loop {
+        let node = cursor.node();
+        let comment = node.is_extra() && node.kind().contains("comment");
+        let literal = Literal::of(node.kind(), &text[node.byte_range()]).is_some();
+        if comment || literal || node.child_count() == 0 {
+            if !node.byte_range().is_empty() {
+                tokens.push((node.byte_range(), comment));
+            }
+        } else if cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                break 'walk;
+            }
+        }
+    }
+    tokens
+}
+
+/// Write the text in `range` between two tokens, without `comments`. `first`
+/// and `last` say if it's before the first token or after the last one.
+fn gap(
+    out: &mut Vec<u8>,
+    text: &[u8],
+    range: Range<usize>,
+    comments: &[Range<usize>],
+    options: Preprocess,
+    first: bool,
+    last: bool,
+) {
+    // Each line of the gap, and where the first comment was deleted from it
+    let mut lines: Vec<(Vec<u8>, Option<usize>)> = vec![(Vec::new(), None)];
+    let mut i = range.start;
+    while i < range.end {
+        if let Some(comment) = comments.iter().find(|c| c.start == i) {
+            let (line, stripped) = lines.last_mut().unwrap();
+            stripped.get_or_insert(line.len());
+            i = comment.end;
+            continue;
+        }
+        if text[i] == b'\n' {
+            lines.push((Vec::new(), None));
+        } else {
+            lines.last_mut().unwrap().0.push(text[i]);
+        }
+        i += 1;
+    }
+    let blank = |line: &[u8]| line.iter().all(u8::is_ascii_whitespace);
+    if lines.iter().any(|(line, _)| !blank(line)) {
+        // Not whitespace, e.g., in an ERROR node
+        out.extend(&text[range]);
+        return;
+    }
+    // Keep the whitespace before a comment, e.g., indentation, but not after
+    let stripped = lines.iter().any(|(_, stripped)| stripped.is_some());
+    let last_line = lines.len() - 1;
+    for (i, (line, stripped)) in lines.iter_mut().enumerate() {
+        if let Some(at) = *stripped {
+            line.truncate(at);
+            if i != last_line {
+                line.truncate(line.trim_ascii_end().len());
+            }
+        }
+    }
+    if lines.len() == 1 {
+        let line = &lines[0].0;
+        let spaced = !line.is_empty() || stripped;
+        match (first || last, options.normalize_whitespace) {
+            (true, true) => {}
+            (true, false) => out.extend(line),
+            (false, true) => out.extend(if spaced { &b" "[..] } else { b"" }),
+            (false, false) if line.is_empty() && stripped => out.push(b' '),
+            (false, false) => out.extend(line),
+        }
+        return;
+    }
+    let indent = lines.pop().unwrap().0;
+    let normalize = options.normalize_whitespace;
+    let mut blanks = 0;
+    for (i, (line, stripped)) in lines.iter().enumerate() {
+        // Unless it's the first gap, the first line is the rest of the line
+        // of the previous token
+        let whole = i > 0 || first;
+        if whole && stripped.is_some() {
+            continue;
+        }
+        if whole {
+            blanks += 1;
+            if normalize && (blanks > 1 || first) {
+                continue;
+            }
+        }
+        if !normalize {
+            out.extend(line);
+        }
+        out.push(b'\n');
+    }
+    if !(last && normalize) {
+        out.extend(indent);
+    }
+}
@@ -0,0 +1,131 @@
+//! Mutations described by tree-sitter queries, see [`Rules`].
+
+use std::collections::HashMap;
+
+use tree_sitter::{Language, Node, Query, QueryCursor, QueryPredicateArg, Tree};
+
+/// What to replace a captured node with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// A splice candidate of the same kind
+    Splice,
+    /// A splice candidate of one of these kinds, from `#kind!`
+    Kinds(Vec<String>),
+    /// One of these templates, from `#replace!`
+    Templates(Vec<String>),
+}
+
+/// Which nodes to mutate and how, as a tree-sitter query. Each capture is a
+/// node to mutate, unless its name starts with `_`. By default, it's replaced
+/// with a splice candidate of the same kind. Directives say otherwise:
+///
+/// - `(#replace! @op "+" "-" "*")` replaces the node with one of the strings,
+///   where `{name}` is the text of the capture `@name` in the same match, e.g.,
+///   `"({l}) - ({r})"`
+/// - `(#kind! @e "identifier" "integer_literal")` replaces the node with a
+///   splice candidate of one of the kinds
+///
+/// For example, to swap the operators of binary expressions:
+///
+/// ```scheme
+/// ((binary_expression operator: _ @op)
+///  (#replace! @op "+" "-" "*" "/" "%"))
+/// ```
+#[derive(Debug)]
+pub struct Rules {
+    query: Query,
+    /// The action for each capture of each pattern
+    actions: Vec<HashMap<u32, Action>>,
+}
+
+impl Rules {
+    pub fn new(language: Language, source: &str) -> Result<Self, String> {
+        let query = Query::new(language, source).map_err(|e| e.to_string())?;
+        let mut actions = Vec::with_capacity(query.pattern_count());
+        for pattern in 0..query.pattern_count() {
+            let mut pattern_actions = HashMap::new();
+            for predicate in query.general_predicates(pattern) {
+                let (capture, strings) = match predicate.args.split_first() {
+                    Some((QueryPredicateArg::Capture(capture), rest)) => (
+                        *capture,
+                        rest.iter()
+                            .map(|arg| match arg {
+                                QueryPredicateArg::String(s) => Ok(s.to_string()),
+                                QueryPredicateArg::Capture(_) => Err(format!(
+                                    "#{} takes a capture and then strings",
+                                    predicate.operator
+                                )),
+                            })
+                            .collect::<Result<Vec<_>, _>>()?,
+                    ),
+                    _ => continue,
+                };
+                if strings.is_empty() {
+                    return Err(format!("#{} needs at least one string", predicate.operator));
+                }
+                let action = match &*predicate.operator {
+                    "replace!" => Action::Templates(strings),
+                    "kind!" => Action::Kinds(strings),
+                    _ => continue,
+                };
+                pattern_actions.insert(capture, action);
+            }
+            actions.push(pattern_actions);
+        }
+        Ok(Rules { query, actions })
+    }
+
+    /// The nodes to mutate in `tree`, with what to replace them with and the
+    /// text of the other captures in the same match, by name
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn targets<'t>(
+        &self,
+        text: &[u8],
+        tree: &'t Tree,
+    ) -> Vec<(Node<'t>, &Action, HashMap<&str, Vec<u8>>)> {
+        let names = self.query.capture_names();
+        let mut targets = Vec::new();
+        let mut cursor = QueryCursor::new();
+        for m in cursor.matches(&self.query, tree.root_node(), text) {
+            let captured: HashMap<_, _> = m
+                .captures
+                .iter()
+                .map(|c| {
+                    (
+                        names[c.index as usize].as_str(),
+                        text[c.node.byte_range()].to_vec(),
+                    )
+                })
+                .collect();
+            for capture in m.captures {
+                if names[capture.index as usize].starts_with('_') {
+                    continue;
+                }
+                let action = self.actions[m.pattern_index]
+                    .get(&capture.index)
+                    .unwrap_or(&Action::Splice);
+                targets.push((capture.node, action, captured.clone()));
+            }
+        }
+        targets
+    }
+}
+
+/// `template` with each `{name}` replaced by the text of capture `@name`
+pub(crate) fn fill(template: &str, captured: &HashMap<&str, Vec<u8>>) -> Vec<u8> {
+    let mut out = Vec::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        out.extend(&rest.as_bytes()[..start]);
+        match captured.get(&rest[start + 1..start + end]) {
+            Some(text) => out.extend(text),
+            None => out.extend(&rest.as_bytes()[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    out.extend(rest.as_bytes());
+    out
+}
@@ -1,19 +1,90 @@
 #![allow(dead_code)]
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
 
-use rand::{prelude::StdRng, Rng, SeedableRng};
-use tree_sitter::{Language, Node, Tree};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use tree_sitter::{InputEdit, Language, Node, Point, Query, QueryCursor, Tree};
 
 use tree_sitter_edit::Editor;
 
+use crate::cluster::cluster;
+use crate::havoc::havoc;
+use crate::index::CorpusIndex;
+use crate::literal::Literal;
 use crate::node_types::NodeTypes;
+use crate::rules::{fill, Action, Rules};
+use crate::templates::{Hole, Segment, Templates};
+use crate::trace::{Edit, Operation, Origin, Trace};
+use crate::type_hint::TypeHint;
 
 #[derive(Debug, Default)]
-struct Edits(HashMap<usize, Vec<u8>>);
+pub(crate) struct Edits(pub(crate) HashMap<usize, Vec<u8>>);
+
+impl Edits {
+    /// The edited nodes that aren't inside other edited nodes, in order.
+    /// These are the edits that [`tree_sitter_edit::render`] applies.
+    fn outermost<'t>(&self, tree: &'t Tree) -> Vec<Node<'t>> {
+        let mut nodes = Vec::new();
+        let mut end = 0;
+        let mut cursor = tree.walk();
+        loop {
+            let node = cursor.node();
+            if self.0.contains_key(&node.id()) && node.start_byte() >= end {
+                nodes.push(node);
+                end = node.end_byte();
+            }
+            if cursor.goto_first_child() || cursor.goto_next_sibling() {
+                continue;
+            }
+            loop {
+                if !cursor.goto_parent() {
+                    return nodes;
+                }
+                if cursor.goto_next_sibling() {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Tell `tree` about the edits, so that it can be reparsed incrementally.
+    fn apply(&self, tree: &mut Tree) {
+        let input_edits: Vec<_> = self
+            .outermost(tree)
+            .into_iter()
+            .map(|node| {
+                let replacement = &self.0[&node.id()];
+                let start = node.start_position();
+                let new_end_position = match replacement.iter().rposition(|b| *b == b'\n') {
+                    None => Point::new(start.row, start.column + replacement.len()),
+                    Some(last) => Point::new(
+                        start.row + replacement.iter().filter(|b| **b == b'\n').count(),
+                        replacement.len() - last - 1,
+                    ),
+                };
+                InputEdit {
+                    start_byte: node.start_byte(),
+                    old_end_byte: node.end_byte(),
+                    new_end_byte: node.start_byte() + replacement.len(),
+                    start_position: start,
+                    old_end_position: node.end_position(),
+                    new_end_position,
+                }
+            })
+            .collect();
+        // Last to first, so that each edit's positions are still accurate
+        for edit in input_edits.iter().rev() {
+            tree.edit(edit);
+        }
+    }
+}
 
 impl Editor for Edits {
     fn has_edit(&self, _tree: &Tree, node: &Node) -> bool {
-        self.0.get(&node.id()).is_some()
+        self.0.contains_key(&node.id())
     }
 
     fn edit(&self, _source: &[u8], tree: &Tree, node: &Node) -> Vec<u8> {
@@ -22,48 +93,234 @@ impl Editor for Edits {
     }
 }
 
-#[derive(Debug)]
-struct Branches<'a>(HashMap<&'static str, Vec<&'a [u8]>>);
+/// A splice candidate, and where it came from
+#[derive(Clone, Copy, Debug)]
+struct Candidate<'a> {
+    text: &'a [u8],
+    /// See [`Branches::origins`]
+    origin: Option<(usize, usize)>,
+}
+
+/// Splice candidates for each node kind
+#[derive(Debug, Default)]
+pub(crate) struct Branches<'a> {
+    pub(crate) candidates: HashMap<&'static str, Vec<&'a [u8]>>,
+    pub(crate) weights: HashMap<&'static str, Vec<u32>>,
+    /// Where each candidate in `candidates` is: the index of the text it's
+    /// from in `texts`, and its offset in that text. `None` for candidates
+    /// that aren't from a text, e.g., the tokens of a dictionary.
+    pub(crate) origins: HashMap<&'static str, Vec<Option<(usize, usize)>>>,
+    /// The texts that candidates came from, in the order they were added
+    texts: Vec<&'a [u8]>,
+    /// Whether any weight isn't 1
+    weighted: bool,
+    /// Index of each candidate in `candidates`. Empty until a tree is added
+    /// if the candidates came from a [`CorpusIndex`].
+    seen: HashMap<(&'static str, &'a [u8]), usize>,
+}
 
 impl<'a> Branches<'a> {
-    fn new(trees: Vec<(&'a [u8], &'a Tree)>) -> Self {
-        let mut branches = HashMap::with_capacity(trees.len()); // min
-        for (text, tree) in trees {
-            let mut nodes = vec![tree.root_node()];
-            while !nodes.is_empty() {
-                let mut children = Vec::with_capacity(nodes.len()); // guesstimate
-                for node in nodes {
-                    branches
-                        .entry(node.kind())
-                        .or_insert_with(|| HashSet::with_capacity(1))
-                        .insert(&text[node.byte_range()]);
-                    let mut i = 0;
-                    while let Some(child) = node.child(i) {
-                        children.push(child);
-                        i += 1;
-                    }
+    pub(crate) fn new(trees: Vec<(&'a [u8], &'a Tree, u32)>) -> Self {
+        let mut branches = Branches {
+            candidates: HashMap::with_capacity(trees.len()), // min
+            weights: HashMap::with_capacity(trees.len()),    // min
+            origins: HashMap::with_capacity(trees.len()),    // min
+            texts: Vec::with_capacity(trees.len()),
+            ..Default::default()
+        };
+        for (text, tree, weight) in trees {
+            branches.add(text, tree, weight);
+        }
+        branches
+    }
+
+    /// The candidates in `index`, which are ranges of `texts`.
+    ///
+    /// `None` if `index` has node kinds that `language` doesn't.
+    fn from_index(language: Language, index: &CorpusIndex, texts: &[&'a [u8]]) -> Option<Self> {
+        let mut branches = Branches {
+            candidates: HashMap::with_capacity(index.candidates.len()),
+            weights: HashMap::with_capacity(index.candidates.len()),
+            origins: HashMap::with_capacity(index.candidates.len()),
+            texts: texts.to_vec(),
+            ..Default::default()
+        };
+        for (kind, candidates) in &index.candidates {
+            if candidates.is_empty() {
+                continue;
+            }
+            let id = match language.id_for_node_kind(kind, true) {
+                0 => language.id_for_node_kind(kind, false),
+                id => id,
+            };
+            let kind = language.node_kind_for_id(id).filter(|k| k == kind)?;
+            let mut kind_branches = Vec::with_capacity(candidates.len());
+            let mut kind_weights = Vec::with_capacity(candidates.len());
+            let mut kind_origins = Vec::with_capacity(candidates.len());
+            for (source, start, end, weight) in candidates {
+                kind_branches.push(texts.get(*source)?.get(*start..*end)?);
+                kind_weights.push(*weight);
+                kind_origins.push(Some((*source, *start)));
+                branches.weighted |= *weight != 1;
+            }
+            branches.candidates.insert(kind, kind_branches);
+            branches.weights.insert(kind, kind_weights);
+            branches.origins.insert(kind, kind_origins);
+        }
+        Some(branches)
+    }
+
+    fn fill_seen(&mut self) {
+        if self.seen.is_empty() {
+            for (kind, candidates) in &self.candidates {
+                for (idx, candidate) in candidates.iter().enumerate() {
+                    self.seen.insert((kind, candidate), idx);
                 }
-                nodes = children;
             }
         }
-        Branches(
-            branches
-                .into_iter()
-                .map(|(k, s)| (k, s.iter().copied().collect()))
-                .collect(),
-        )
+    }
+
+    /// Add `branch`, which is at `origin` (see [`Branches::origins`]), unless
+    /// it's already a candidate of kind `kind`
+    fn insert(
+        &mut self,
+        kind: &'static str,
+        branch: &'a [u8],
+        weight: u32,
+        origin: Option<(usize, usize)>,
+    ) {
+        // Keep candidates in the order they're found, for determinism
+        let kind_branches = self
+            .candidates
+            .entry(kind)
+            .or_insert_with(|| Vec::with_capacity(1));
+        let kind_weights = self.weights.entry(kind).or_default();
+        let kind_origins = self.origins.entry(kind).or_default();
+        match self.seen.get(&(kind, branch)) {
+            // Candidates from several corpora get the highest weight
+            Some(&idx) => {
+                let w: &mut u32 = &mut kind_weights[idx];
+                *w = (*w).max(weight);
+            }
+            None => {
+                self.seen.insert((kind, branch), kind_branches.len());
+                kind_branches.push(branch);
+                kind_weights.push(weight);
+                kind_origins.push(origin);
+            }
+        }
+    }
+
+    fn add(&mut self, text: &'a [u8], tree: &'a Tree, weight: u32) {
+        self.fill_seen();
+        self.weighted |= weight != 1;
+        let source = self.texts.len();
+        self.texts.push(text);
+        let mut nodes = vec![tree.root_node()];
+        while !nodes.is_empty() {
+            let mut children = Vec::with_capacity(nodes.len()); // guesstimate
+            for node in nodes {
+                let origin = Some((source, node.start_byte()));
+                self.insert(node.kind(), &text[node.byte_range()], weight, origin);
+                let mut i = 0;
+                while let Some(child) = node.child(i) {
+                    children.push(child);
+                    i += 1;
+                }
+            }
+            nodes = children;
+        }
+    }
+
+    fn len(&self, kind: &str) -> usize {
+        self.candidates.get(kind).map_or(0, Vec::len)
+    }
+
+    /// Candidate number `idx` of kind `kind`
+    fn get(&self, kind: &str, idx: usize) -> Candidate<'a> {
+        Candidate {
+            text: self.candidates[kind][idx],
+            origin: self.origins[kind][idx],
+        }
+    }
+
+    /// The candidates of kind `kind`
+    fn of_kind<'s>(&'s self, kind: &'s str) -> impl Iterator<Item = Candidate<'a>> + 's {
+        (0..self.len(kind)).map(move |idx| self.get(kind, idx))
+    }
+
+    /// The text that a candidate at `origin` is from, and its offset in it
+    fn source(&self, origin: Option<(usize, usize)>) -> Option<(&'a [u8], usize)> {
+        let (idx, start) = origin?;
+        Some((self.texts[idx], start))
     }
 
     fn possible(&self) -> usize {
         let mut possible_mutations = 0;
-        for s in self.0.values() {
-            possible_mutations += s.len() - 1;
+        for s in self.candidates.values() {
+            possible_mutations += s.len().saturating_sub(1);
         }
         possible_mutations
     }
 }
 
-fn parse(language: Language, code: &str) -> tree_sitter::Tree {
+/// Statistics about the splice candidates in a corpus
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct CandidateStats {
+    /// Number of distinct candidates of each node kind
+    pub candidates: BTreeMap<String, usize>,
+    /// Number of candidates that could replace another of the same kind,
+    /// i.e., the sum over kinds of one less than the number of candidates
+    pub possible: usize,
+    /// Number of distinct tests that one non-chaotic splice could make, at
+    /// most: the sum over nodes of the number of other candidates of their
+    /// kind
+    pub mutants: usize,
+}
+
+impl CandidateStats {
+    pub fn new<'a>(trees: impl IntoIterator<Item = (&'a [u8], &'a Tree)>) -> Self {
+        let trees: Vec<_> = trees.into_iter().map(|(t, tree)| (t, tree, 1)).collect();
+        let mut nodes: HashMap<&'static str, usize> = HashMap::new();
+        for (_, tree, _) in &trees {
+            let mut cursor = tree.walk();
+            'walk: loop {
+                *nodes.entry(cursor.node().kind()).or_default() += 1;
+                if cursor.goto_first_child() || cursor.goto_next_sibling() {
+                    continue;
+                }
+                loop {
+                    if !cursor.goto_parent() {
+                        break 'walk;
+                    }
+                    if cursor.goto_next_sibling() {
+                        break;
+                    }
+                }
+            }
+        }
+        let branches = Branches::new(trees);
+        CandidateStats {
+            mutants: nodes
+                .iter()
+                .map(|(kind, n)| n * branches.len(kind).saturating_sub(1))
+                .sum(),
+            possible: branches.possible(),
+            candidates: branches
+                .candidates
+                .iter()
+                .map(|(kind, c)| (kind.to_string(), c.len()))
+                .collect(),
+        }
+    }
+}
+
+/// Parse `code`, e.g., to make the files to pass to [`Splicer::new`].
+///
+/// # Panics
+///
+/// If `language` is from an incompatible version of tree-sitter.
+pub fn parse(language: Language, code: &[u8]) -> tree_sitter::Tree {
     let mut parser = tree_sitter::Parser::new();
     parser
         .set_language(language)
@@ -71,47 +328,531 @@ fn parse(language: Language, code: &str) -> tree_sitter::Tree {
     parser.parse(code, None).expect("Failed to parse code")
 }
 
+/// The seed of the random number generator that makes test number `index`
+/// with [`Config::seed`] `seed`, so that any test can be made again without
+/// making the ones before it
+pub fn test_seed(seed: u64, index: usize) -> u64 {
+    // SplitMix64, so that nearby indices get unrelated seeds
+    let mut z = seed ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Reparse `code`, reusing the unchanged parts of `old`, which must already
+/// have been edited with [`Tree::edit`]
+fn reparse(language: Language, code: &[u8], old: &Tree) -> tree_sitter::Tree {
+    let mut parser = tree_sitter::Parser::new();
+    parser
+        .set_language(language)
+        .expect("Failed to set tree-sitter parser language");
+    parser.parse(code, Some(old)).expect("Failed to parse code")
+}
+
+/// Where the line containing byte `offset` starts
+fn line_start(text: &[u8], offset: usize) -> usize {
+    text[..offset]
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map_or(0, |i| i + 1)
+}
+
+/// The spaces and tabs at the start of the line containing byte `offset`
+fn indentation(text: &[u8], offset: usize) -> &[u8] {
+    let start = line_start(text, offset);
+    let len = text[start..offset]
+        .iter()
+        .take_while(|b| **b == b' ' || **b == b'\t')
+        .count();
+    &text[start..start + len]
+}
+
+/// Replace the indentation `from` with `to` on every line of `text` but the
+/// first, which goes wherever the node it replaces started. Lines that aren't
+/// indented by `from` (e.g., blank lines) are left alone.
+fn reindent(text: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    let mut lines = text.split(|b| *b == b'\n');
+    let mut out = Vec::with_capacity(text.len());
+    out.extend(lines.next().unwrap_or_default());
+    for line in lines {
+        out.push(b'\n');
+        match line.strip_prefix(from) {
+            Some(rest) => {
+                out.extend(to);
+                out.extend(rest);
+            }
+            None => out.extend(line),
+        }
+    }
+    out
+}
+
+/// With a [`Budget`], stop after this many mutations in a row that don't fit
+/// in it or don't change anything. With [`Config::target_size`], stop after
+/// this many mutations in a row that don't get closer to the size.
+const BUDGET_MISSES: usize = 16;
+
+/// How much to change each test, see [`Config::budget`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Budget {
+    /// Change about this many bytes
+    Bytes(usize),
+    /// Change about this fraction of the input, e.g., `0.05` for 5%
+    Fraction(f64),
+}
+
+impl Budget {
+    fn bytes(self, input_len: usize) -> usize {
+        match self {
+            Budget::Bytes(bytes) => bytes,
+            Budget::Fraction(fraction) => (fraction * input_len as f64).ceil() as usize,
+        }
+    }
+}
+
+impl std::str::FromStr for Budget {
+    type Err = String;
+
+    /// A number of bytes, e.g., `200`, or a percentage, e.g., `5%`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_suffix('%') {
+            Some(percent) => match percent.parse::<f64>() {
+                Ok(p) if (0.0..=100.0).contains(&p) => Ok(Budget::Fraction(p / 100.0)),
+                _ => Err(format!(
+                    "Expected a percentage from 0% to 100%, found {}",
+                    s
+                )),
+            },
+            None => s
+                .parse()
+                .map(Budget::Bytes)
+                .map_err(|_| format!("Expected a number of bytes or a percentage, found {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Budget {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Budget::Bytes(bytes) => write!(f, "{}", bytes),
+            Budget::Fraction(fraction) => write!(f, "{}%", fraction * 100.0),
+        }
+    }
+}
+
+/// How to pick the size to grow or shrink each test toward, up to
+/// [`Config::target_size`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SizeDistribution {
+    /// Any size up to the target, equally likely
+    #[default]
+    Uniform,
+    /// Any size up to the target, with each order of magnitude equally
+    /// likely, so that most tests stay small
+    Log,
+    /// The target
+    Fixed,
+}
+
+impl SizeDistribution {
+    fn sample(self, rng: &mut impl Rng, max: usize) -> usize {
+        let max = max.max(1);
+        match self {
+            SizeDistribution::Uniform => rng.gen_range(1..=max),
+            SizeDistribution::Log => {
+                let exp = rng.gen_range(0.0..=(max as f64).ln());
+                (exp.exp().round() as usize).clamp(1, max)
+            }
+            SizeDistribution::Fixed => max,
+        }
+    }
+}
+
+impl std::str::FromStr for SizeDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(SizeDistribution::Uniform),
+            "log" => Ok(SizeDistribution::Log),
+            "fixed" => Ok(SizeDistribution::Fixed),
+            _ => Err(format!("Expected uniform, log, or fixed, found {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for SizeDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SizeDistribution::Uniform => write!(f, "uniform"),
+            SizeDistribution::Log => write!(f, "log"),
+            SizeDistribution::Fixed => write!(f, "fixed"),
+        }
+    }
+}
+
+/// Which part of each test to mutate, see [`Config::focus`]
+#[derive(Clone, Debug)]
+pub enum Focus {
+    /// These bytes of each input, which move as mutations before them change
+    /// its length
+    Range(Range<usize>),
+    /// The nodes that the captures of this query match, e.g., `((function_item
+    /// name: (identifier) @n) @f (#eq? @n "main"))` for the function `main`
+    Query(Arc<Query>),
+}
+
 /// Splicing configuration
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Config {
+    /// Instead of making up to `inter_splices` mutations, keep mutating until
+    /// this many bytes are changed, e.g., for tests with small diffs from
+    /// their inputs.
+    ///
+    /// A mutation changes as many bytes as the larger of the node and its
+    /// replacement. Mutations that don't fit in what's left are skipped.
+    pub budget: Option<Budget>,
     /// Percent chance to perform chaotic mutation
     ///
     /// Chaotic mutations may result in invalid syntax.
     pub chaos: u8,
-    /// Percent chance to perform a deletion.
+    /// Group the files into this many clusters of structurally similar files,
+    /// and choose files to mutate evenly from each cluster.
+    ///
+    /// Zero disables clustering.
+    pub clusters: usize,
+    /// Percent chance to make a test by crossing its input with another
+    /// input, instead of mutating it.
     ///
-    /// By default, deletes optional nodes. Chaotic deletions delete any node.
-    pub deletions: u8,
+    /// A crossover either alternately takes runs of top-level items (e.g.,
+    /// functions) from each input, or replaces a node with a node of the same
+    /// kind from the other input.
+    pub crossover: u8,
+    /// Half of the time, replace a literal with another literal of the same
+    /// kind from the inputs (see [`Splicer::harvest`]) instead of an edge
+    /// case, e.g., to use the field names and constants that the target
+    /// knows about.
+    pub harvest: bool,
+    /// Also treat leaf nodes of these kinds as identifiers, besides those
+    /// whose kinds end in `identifier`.
+    pub identifier_kinds: HashSet<String>,
+    /// Make nodes of these kinds this many times as likely to be mutated as
+    /// other nodes, which have weight 1.
+    pub kind_weights: HashMap<String, u32>,
     pub language: Language,
-    // pub intra_splices: usize,
-    /// Perform anywhere from zero to this many inter-file splices per test.
+    /// Perform anywhere from one to this many mutations per test, or none
+    /// when it's zero.
     pub inter_splices: usize,
+    /// Also treat nodes of these kinds as literals, besides those that look
+    /// like literals by their kinds (e.g., `integer_literal`). Their text
+    /// decides what kind of literal they are.
+    pub literal_kinds: HashSet<String>,
     /// Approximate maximum file size to produce (bytes)
     ///
-    /// Some of the input tests should be below this size.
+    /// Only inputs no bigger than this are mutated. If there are none, the
+    /// [`Splicer`] makes no tests.
     pub max_size: usize,
+    /// Nest a node in at most this many copies of the text around a node of
+    /// the same kind, see [`OperatorWeights::nest`].
+    pub nest_depth: usize,
     pub node_types: NodeTypes,
+    /// Only mutate nodes inside this part of each test, and keep the rest
+    /// intact.
+    pub focus: Option<Focus>,
+    /// How often to use each kind of mutation
+    pub operators: OperatorWeights,
+    /// Only mutate nodes of these kinds, unless empty.
+    pub only_kinds: HashSet<String>,
+    /// Produce this many tests from each input before choosing inputs at
+    /// random.
+    pub per_seed: usize,
+    /// Treat the comments and attributes (e.g., `#[derive(Debug)]`) on the
+    /// lines right before a node as part of it: delete and duplicate them
+    /// with it. Replacing a node keeps them either way.
+    pub preserve_leading_trivia: bool,
+    /// Re-indent the lines of a spliced or inserted node to match the
+    /// indentation of the line it goes into, e.g., for Python or YAML.
+    pub reindent: bool,
     /// Re-parse the file after this many mutations.
     ///
-    /// When this is more than `inter_splices`, never re-parse.
+    /// When this is zero or more than `inter_splices`, never re-parse.
     pub reparse: usize,
+    /// Which nodes [`OperatorWeights::rule`] mutates, and how.
+    pub rules: Option<Arc<Rules>>,
+    /// Each test's random numbers come from this and the number of the test,
+    /// see [`test_seed`].
     pub seed: u64,
+    /// How to pick the size each test is grown or shrunk toward, see
+    /// [`Config::target_size`].
+    pub size_distribution: SizeDistribution,
+    /// Never mutate nodes of these kinds.
+    pub skip_kinds: HashSet<String>,
+    /// Instead of making up to `inter_splices` mutations, grow or shrink each
+    /// test toward a size up to this many bytes, picked with
+    /// `size_distribution`: half of the mutations are duplications and
+    /// insertions while it's smaller, and deletions while it's bigger. Stops
+    /// within 10% of the size, or after several mutations that don't get
+    /// closer.
+    pub target_size: Option<usize>,
+    /// Snippets that [`OperatorWeights::template`] splices in.
+    pub templates: Option<Arc<Templates>>,
+    /// Record a trace of each test, see [`Splicer::take_trace`].
+    pub trace: bool,
+    /// Only replace a node with candidates of the same type, if its type is
+    /// known. Chaotic splices ignore types.
+    pub type_hint: Option<Arc<dyn TypeHint>>,
+    /// Make the first mutation of each test target a node kind that wasn't
+    /// the first target of a previous test, if possible.
+    pub unique_kinds: bool,
+}
+
+impl Config {
+    /// The same defaults as the command-line tools
+    pub fn new(language: Language, node_types: NodeTypes) -> Self {
+        Config {
+            budget: None,
+            chaos: 5,
+            clusters: 0,
+            crossover: 0,
+            harvest: false,
+            identifier_kinds: HashSet::new(),
+            kind_weights: HashMap::new(),
+            language,
+            inter_splices: 16,
+            literal_kinds: HashSet::new(),
+            max_size: 1048576,
+            nest_depth: 16,
+            node_types,
+            focus: None,
+            operators: OperatorWeights::default(),
+            only_kinds: HashSet::new(),
+            per_seed: 0,
+            preserve_leading_trivia: false,
+            reindent: false,
+            reparse: 1,
+            rules: None,
+            seed: 0,
+            size_distribution: SizeDistribution::Uniform,
+            skip_kinds: HashSet::new(),
+            target_size: None,
+            templates: None,
+            trace: false,
+            type_hint: None,
+            unique_kinds: false,
+        }
+    }
 }
 
+/// How often to use each kind of mutation, relative to the others.
+///
+/// Each mutation picks one at random by weight. Chaotic mutations (see
+/// [`Config::chaos`]) use the same weights.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OperatorWeights {
+    /// Replace a node with a splice candidate of the same kind.
+    ///
+    /// Chaotic splices use a candidate of any kind.
+    pub splice: u32,
+    /// Delete a node.
+    ///
+    /// By default, deletes optional nodes, and the separators (e.g., `,`) of
+    /// elements of lists. Chaotic deletions delete any node.
+    pub delete: u32,
+    /// Duplicate a node, e.g., repeat a statement or an argument.
+    ///
+    /// By default, duplicates nodes in lists of children. Chaotic
+    /// duplications duplicate any node.
+    pub duplicate: u32,
+    /// Add a splice candidate to a list, e.g., a statement to a block.
+    ///
+    /// By default, only adds candidates of kinds that the list can contain.
+    /// Chaotic insertions add candidates of any kind.
+    pub insert: u32,
+    /// Swap two elements of a list, e.g., two statements in a block or two
+    /// arguments.
+    ///
+    /// Chaotic swaps swap a node with any of its named siblings.
+    pub swap: u32,
+    /// Rename an identifier everywhere it occurs in the file, to another
+    /// identifier of the same kind.
+    ///
+    /// Chaotic renames may use an identifier of any kind.
+    pub rename: u32,
+    /// Replace a literal with an edge case, e.g., `0`, `-1`, `NaN`, or a long
+    /// string.
+    ///
+    /// Chaotic literal mutations may use a literal of another kind, e.g.,
+    /// a string instead of a number.
+    pub literal: u32,
+    /// Edit the bytes and tokens of a node, e.g., flip bits, duplicate a
+    /// token, or truncate it.
+    pub havoc: u32,
+    /// Replace a node with another node of the same kind from the file being
+    /// mutated, rather than from any input.
+    pub intra: u32,
+    /// Replace a node that [`Config::rules`] captures, as they say.
+    pub rule: u32,
+    /// Replace a node with one of [`Config::templates`], with its holes
+    /// filled by splice candidates.
+    pub template: u32,
+    /// Nest a node in copies of the text around a node of the same kind in
+    /// an input, up to [`Config::nest_depth`] times, e.g., `((((x))))` or
+    /// `{ { { x } } }`, to stress recursion in the target.
+    pub nest: u32,
+    /// Replace an operator with another that can be in the same place, e.g.,
+    /// `a + b` with `a << b`, keeping the operands, see
+    /// [`NodeTypes::field_tokens`].
+    pub operator: u32,
+}
+
+impl Default for OperatorWeights {
+    /// The same defaults as the command-line tools
+    fn default() -> Self {
+        OperatorWeights {
+            splice: 95,
+            delete: 5,
+            duplicate: 0,
+            insert: 0,
+            swap: 0,
+            rename: 0,
+            literal: 0,
+            havoc: 0,
+            intra: 0,
+            rule: 0,
+            template: 0,
+            nest: 0,
+            operator: 0,
+        }
+    }
+}
+
+impl OperatorWeights {
+    /// Treat the weights of the other operators as percentages, and make
+    /// splices the rest, as the command-line tools do.
+    pub fn splice_rest(self) -> Self {
+        let other = self.delete
+            + self.duplicate
+            + self.insert
+            + self.swap
+            + self.rename
+            + self.literal
+            + self.havoc
+            + self.intra
+            + self.rule
+            + self.template
+            + self.nest
+            + self.operator;
+        OperatorWeights {
+            splice: 100u32.saturating_sub(other),
+            ..self
+        }
+    }
+
+    /// Splices come last, so that weights that add up to 100 pick the same
+    /// operators as the percentages of earlier versions
+    fn table(&self) -> [(Operation, u32); 13] {
+        [
+            (Operation::Delete, self.delete),
+            (Operation::Duplicate, self.duplicate),
+            (Operation::Insert, self.insert),
+            (Operation::Swap, self.swap),
+            (Operation::Rename, self.rename),
+            (Operation::Literal, self.literal),
+            (Operation::Havoc, self.havoc),
+            (Operation::Intra, self.intra),
+            (Operation::Rule, self.rule),
+            (Operation::Template, self.template),
+            (Operation::Nest, self.nest),
+            (Operation::Operator, self.operator),
+            (Operation::Splice, self.splice),
+        ]
+    }
+}
+
+/// A test and where it came from, see [`Splicer::next_with_metadata`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mutant<'a> {
+    pub bytes: Vec<u8>,
+    /// The file that the test was made from, if it's one of the files passed
+    /// to [`Splicer::new`]
+    pub input: Option<&'a str>,
+    /// The mutations that made the test
+    pub trace: Trace,
+    /// The number of the test, which with [`Config::seed`] determines its
+    /// random numbers, see [`Splicer::resume`]
+    pub index: usize,
+}
+
+/// A replacement for a node
+struct Mutation<'b, 'a> {
+    operation: Operation,
+    node: Node<'b>,
+    replace: Vec<u8>,
+    delta: isize,
+    /// The splice candidate the replacement came from
+    candidate: Option<Candidate<'a>>,
+}
+
+/// The text before and after nodes in nodes of the same kind, by kind
+type Nestings<'a> = HashMap<&'static str, Vec<(&'a [u8], &'a [u8])>>;
+
 #[derive(Debug)]
 pub struct Splicer<'a> {
     pub language: Language,
     branches: Branches<'a>,
+    budget: Option<Budget>,
     chaos: u8,
-    deletions: u8,
+    /// Indices into `trees`; empty if clustering is disabled
+    clusters: Vec<Vec<usize>>,
+    crossover: u8,
+    /// Index of the cluster of trees from [`Splicer::add_tree`]
+    discovered: Option<usize>,
+    /// Number of `trees` that are the files passed to [`Splicer::new`],
+    /// which are also the first of `sources`
+    files: usize,
+    /// Number of `trees` no bigger than [`Config::max_size`], which are the
+    /// ones to mutate
+    fitting: usize,
+    focus: Option<Focus>,
+    /// Where [`Config::focus`] is in the current version of the test
+    focused: Vec<Range<usize>>,
+    generated: usize,
+    harvest: bool,
+    identifier_kinds: HashSet<String>,
+    /// See [`Splicer::input`]
+    input: Option<&'a str>,
     kinds: Vec<&'static str>,
-    // intra_splices: usize,
     inter_splices: usize,
+    kind_weights: HashMap<String, u32>,
+    literal_kinds: HashSet<String>,
     max_size: usize,
+    nest_depth: usize,
+    /// See [`Splicer::nestings`]
+    nestings: Option<Nestings<'a>>,
     node_types: NodeTypes,
+    only_kinds: HashSet<String>,
+    operators: [(Operation, u32); 13],
+    per_seed: usize,
+    preserve_leading_trivia: bool,
+    primary_kinds: HashSet<&'static str>,
     trees: Vec<(&'a [u8], &'a Tree)>,
+    reindent: bool,
     reparse: usize,
-    rng: StdRng,
+    rules: Option<Arc<Rules>>,
+    /// Reseeded for each test, see [`test_seed`]
+    rng: ChaCha12Rng,
+    seed: u64,
+    size_distribution: SizeDistribution,
+    skip_kinds: HashSet<String>,
+    /// Names of the files and donors, to find where candidates came from
+    sources: Vec<(&'a str, &'a [u8])>,
+    stride: usize,
+    target_size: Option<usize>,
+    templates: Option<Arc<Templates>>,
+    /// Trace of the last test, if tracing is enabled
+    trace: Option<Trace>,
+    type_hint: Option<Arc<dyn TypeHint>>,
+    unique_kinds: bool,
 }
 
 impl<'a> Splicer<'a> {
@@ -121,40 +862,261 @@ impl<'a> Splicer<'a> {
             - isize::try_from(range.end - range.start).unwrap_or_default()
     }
 
+    /// Sort by path so that the output doesn't depend on hash order
+    pub(crate) fn sorted(
+        files: &'a HashMap<String, (Vec<u8>, Tree)>,
+    ) -> Vec<(&'a str, &'a [u8], &'a Tree)> {
+        let mut paths: Vec<_> = files.keys().collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|p| {
+                let (txt, tree) = &files[p];
+                (p.as_str(), txt.as_ref(), tree)
+            })
+            .collect()
+    }
+
     pub fn new(config: Config, files: &'a HashMap<String, (Vec<u8>, Tree)>) -> Self {
-        let trees: Vec<_> = files
-            .iter()
-            .map(|(_, (txt, tree))| (txt.as_ref(), tree))
-            .collect();
-        let branches = Branches::new(
-            files
-                .iter()
-                .map(|(_, (txt, tree))| (txt.as_ref(), tree))
-                .collect(),
-        );
-        let rng = rand::rngs::StdRng::seed_from_u64(config.seed);
-        let kinds = branches.0.keys().copied().collect();
+        Self::new_with_weighted_donors(config, files, &[])
+    }
+
+    /// Like [`Splicer::new`], but also take splice candidates from `donors`.
+    ///
+    /// Donors are never mutated themselves, only `files` are.
+    pub fn new_with_donors(
+        config: Config,
+        files: &'a HashMap<String, (Vec<u8>, Tree)>,
+        donors: &'a HashMap<String, (Vec<u8>, Tree)>,
+    ) -> Self {
+        Self::new_with_weighted_donors(config, files, &[(donors, 1)])
+    }
+
+    /// Like [`Splicer::new_with_donors`], but with several corpora of donors.
+    ///
+    /// Candidates from a corpus with weight `w` are chosen `w` times as often
+    /// as candidates from `files`, which have weight 1.
+    #[allow(clippy::type_complexity)]
+    pub fn new_with_weighted_donors(
+        config: Config,
+        files: &'a HashMap<String, (Vec<u8>, Tree)>,
+        donors: &[(&'a HashMap<String, (Vec<u8>, Tree)>, u32)],
+    ) -> Self {
+        let sorted = Self::sorted(files);
+        let mut sources: Vec<_> = sorted.iter().map(|(p, txt, _)| (*p, *txt)).collect();
+        let trees: Vec<_> = sorted.iter().map(|(_, txt, tree)| (*txt, *tree)).collect();
+        let mut all: Vec<_> = trees.iter().map(|(txt, tree)| (*txt, *tree, 1)).collect();
+        for (corpus, weight) in donors {
+            for (p, txt, tree) in Self::sorted(corpus) {
+                sources.push((p, txt));
+                all.push((txt, tree, *weight));
+            }
+        }
+        let branches = Branches::new(all);
+        Self::with_branches(config, trees, sources, branches)
+    }
+
+    /// Like [`Splicer::new_with_weighted_donors`], but take the splice
+    /// candidates from `index` rather than finding them again.
+    ///
+    /// `None` if `index` isn't the index of `files` and `donors`, see
+    /// `CorpusIndex::describes`.
+    #[allow(clippy::type_complexity)]
+    pub fn new_with_index(
+        config: Config,
+        files: &'a HashMap<String, (Vec<u8>, Tree)>,
+        donors: &[(&'a HashMap<String, (Vec<u8>, Tree)>, u32)],
+        index: &CorpusIndex,
+    ) -> Option<Self> {
+        if !index.describes(files, donors) {
+            return None;
+        }
+        let sorted = Self::sorted(files);
+        let mut sources: Vec<_> = sorted.iter().map(|(p, txt, _)| (*p, *txt)).collect();
+        let trees: Vec<_> = sorted.iter().map(|(_, txt, tree)| (*txt, *tree)).collect();
+        for (corpus, _) in donors {
+            sources.extend(Self::sorted(corpus).into_iter().map(|(p, txt, _)| (p, txt)));
+        }
+        let texts: Vec<_> = sources.iter().map(|(_, txt)| *txt).collect();
+        let branches = Branches::from_index(config.language, index, &texts)?;
+        Some(Self::with_branches(config, trees, sources, branches))
+    }
+
+    fn with_branches(
+        config: Config,
+        trees: Vec<(&'a [u8], &'a Tree)>,
+        sources: Vec<(&'a str, &'a [u8])>,
+        branches: Branches<'a>,
+    ) -> Self {
+        let rng = ChaCha12Rng::seed_from_u64(config.seed);
+        let mut kinds: Vec<_> = branches.candidates.keys().copied().collect();
+        kinds.sort_unstable();
+        let clusters = if config.clusters == 0 {
+            Vec::new()
+        } else {
+            let just_trees: Vec<_> = trees.iter().map(|(_, tree)| *tree).collect();
+            cluster(&just_trees, config.clusters)
+        };
         Splicer {
+            budget: config.budget,
             chaos: config.chaos,
-            deletions: config.deletions,
+            clusters,
+            crossover: config.crossover,
+            discovered: None,
+            files: trees.len(),
+            fitting: trees
+                .iter()
+                .filter(|(text, _)| text.len() <= config.max_size)
+                .count(),
+            focus: config.focus,
+            focused: Vec::new(),
+            generated: 0,
+            harvest: config.harvest,
+            identifier_kinds: config.identifier_kinds,
+            input: None,
             language: config.language,
             branches,
             kinds,
-            // intra_splices: config.intra_splices,
             inter_splices: config.inter_splices,
+            kind_weights: config.kind_weights,
+            literal_kinds: config.literal_kinds,
             max_size: config.max_size,
+            nest_depth: config.nest_depth,
+            nestings: None,
             node_types: config.node_types,
+            only_kinds: config.only_kinds,
+            operators: config.operators.table(),
+            per_seed: config.per_seed,
+            preserve_leading_trivia: config.preserve_leading_trivia,
+            primary_kinds: HashSet::new(),
+            reindent: config.reindent,
             reparse: config.reparse,
             rng,
+            rules: config.rules,
+            seed: config.seed,
+            size_distribution: config.size_distribution,
+            skip_kinds: config.skip_kinds,
+            sources,
+            stride: 1,
+            target_size: config.target_size,
+            templates: config.templates,
+            trace: config.trace.then(Trace::default),
             trees,
+            type_hint: config.type_hint,
+            unique_kinds: config.unique_kinds,
         }
     }
 
+    /// Add a file to mutate and take splice candidates from, e.g., a test
+    /// that found new coverage.
+    ///
+    /// With clustering, added files form their own cluster.
+    pub fn add_tree(&mut self, text: &'a [u8], tree: &'a Tree) {
+        let idx = self.trees.len();
+        self.trees.push((text, tree));
+        if text.len() <= self.max_size {
+            self.fitting += 1;
+        }
+        self.nestings = None;
+        self.branches.add(text, tree, 1);
+        if self.kinds.len() != self.branches.candidates.len() {
+            self.kinds = self.branches.candidates.keys().copied().collect();
+            self.kinds.sort_unstable();
+        }
+        if !self.clusters.is_empty() {
+            match self.discovered {
+                Some(c) => self.clusters[c].push(idx),
+                None => {
+                    self.discovered = Some(self.clusters.len());
+                    self.clusters.push(vec![idx]);
+                }
+            }
+        }
+    }
+
+    /// Add splice candidates that aren't nodes of a file, e.g., the tokens of
+    /// an AFL-style dictionary (see `dict::parse`), as candidates of
+    /// each of `kinds`. If `kinds` is empty, they're added to each identifier
+    /// kind that has candidates.
+    pub fn add_tokens(&mut self, kinds: &[String], tokens: &[&'a [u8]]) -> Result<(), String> {
+        let kinds: Vec<&'static str> = if kinds.is_empty() {
+            self.kinds
+                .iter()
+                .copied()
+                .filter(|k| self.is_identifier_kind(k))
+                .collect()
+        } else {
+            kinds
+                .iter()
+                .map(|kind| {
+                    let id = self.language.id_for_node_kind(kind, true);
+                    self.language
+                        .node_kind_for_id(id)
+                        .filter(|k| id != 0 && k == kind)
+                        .ok_or_else(|| format!("No named node kind {}", kind))
+                })
+                .collect::<Result<_, _>>()?
+        };
+        self.branches.fill_seen();
+        for kind in kinds {
+            for token in tokens {
+                self.branches.insert(kind, token, 1, None);
+            }
+        }
+        if self.kinds.len() != self.branches.candidates.len() {
+            self.kinds = self.branches.candidates.keys().copied().collect();
+            self.kinds.sort_unstable();
+        }
+        Ok(())
+    }
+
+    /// Only make every `stride`th test, starting with test number `offset`.
+    ///
+    /// This splits the work of making tests between several splicers (e.g.,
+    /// on different threads) without changing which inputs `per_seed` uses.
+    pub fn stride(mut self, offset: usize, stride: usize) -> Self {
+        self.generated = offset;
+        self.stride = stride.max(1);
+        self
+    }
+
+    /// Continue like a splicer with the same config that already made tests
+    /// up to test number `generated`, e.g., to make one test again.
+    ///
+    /// Files added with [`Splicer::add_tree`] aren't restored, so the tests
+    /// can differ if the earlier splicer had any.
+    pub fn resume(mut self, generated: usize) -> Self {
+        self.generated = generated;
+        self
+    }
+
+    /// The number of the next test, see [`Splicer::resume`]
+    pub fn next_index(&self) -> usize {
+        self.generated
+    }
+
+    /// Use the random numbers of test number `index` from now on, like
+    /// [`Iterator::next`] does before each test, e.g., before
+    /// [`Splicer::splice_tree`]
+    pub fn reseed(&mut self, index: usize) {
+        self.rng = ChaCha12Rng::seed_from_u64(test_seed(self.seed, index));
+    }
+
     fn pick_usize(&mut self, n: usize) -> usize {
         self.rng.gen_range(0..n)
     }
 
-    fn pick_idx<T>(&mut self, v: &Vec<T>) -> usize {
+    /// Pick a tree, evenly from each cluster if clustering is enabled
+    fn pick_tree_idx(&mut self) -> usize {
+        if self.clusters.is_empty() {
+            return self.pick_usize(self.trees.len());
+        }
+        let cluster = self.pick_usize(self.clusters.len());
+        let member = self.pick_usize(self.clusters[cluster].len());
+        self.clusters[cluster][member]
+    }
+
+    fn pick_idx<T>(&mut self, v: &[T]) -> usize {
         self.pick_usize(v.len())
     }
 
@@ -162,17 +1124,15 @@ impl<'a> Splicer<'a> {
         let mut all = Vec::with_capacity(16); // min
         let root = tree.root_node();
         let mut cursor = tree.walk();
-        let mut nodes: HashSet<_> = root.children(&mut cursor).collect();
+        let mut nodes: Vec<_> = root.children(&mut cursor).collect();
         while !nodes.is_empty() {
-            let mut next = HashSet::new();
+            let mut next = Vec::new();
             for node in nodes {
-                debug_assert!(!next.contains(&node));
                 all.push(node);
                 let mut child_cursor = tree.walk();
                 for child in node.children(&mut child_cursor) {
                     debug_assert!(child.id() != node.id());
-                    debug_assert!(!next.contains(&child));
-                    next.insert(child);
+                    next.push(child);
                 }
             }
             nodes = next;
@@ -180,116 +1140,1596 @@ impl<'a> Splicer<'a> {
         all
     }
 
-    fn pick_node<'b>(&mut self, tree: &'b Tree) -> Node<'b> {
-        let nodes = self.all_nodes(tree);
-        if nodes.is_empty() {
-            return tree.root_node();
+    /// How much more likely nodes of this kind are to be mutated, zero if
+    /// they're never mutated
+    fn kind_weight(&self, kind: &str) -> u32 {
+        if self.skip_kinds.contains(kind)
+            || (!self.only_kinds.is_empty() && !self.only_kinds.contains(kind))
+        {
+            return 0;
         }
-        *nodes.get(self.pick_idx(&nodes)).unwrap()
+        self.kind_weights.get(kind).copied().unwrap_or(1)
     }
 
-    fn delete_node(&mut self, _text: &[u8], tree: &Tree) -> (usize, Vec<u8>, isize) {
-        let chaotic = self.rng.gen_range(0..100) < self.chaos;
-        if chaotic {
-            let node = self.pick_node(tree);
-            return (node.id(), Vec::new(), Self::delta(node, &[]));
+    /// Nodes that may be mutated
+    fn targets<'b>(&self, tree: &'b Tree) -> Vec<Node<'b>> {
+        let mut nodes = self.all_nodes(tree);
+        if nodes.is_empty() {
+            nodes.push(tree.root_node());
         }
-        let nodes = self.all_nodes(tree);
-        if nodes.iter().all(|n| !self.node_types.optional_node(n)) {
-            let node = self.pick_node(tree);
-            return (node.id(), Vec::new(), Self::delta(node, &[]));
+        if !self.only_kinds.is_empty()
+            || !self.skip_kinds.is_empty()
+            || !self.kind_weights.is_empty()
+        {
+            nodes.retain(|n| self.kind_weight(n.kind()) > 0);
         }
-        let mut node = nodes.get(self.pick_idx(&nodes)).unwrap();
-        while !self.node_types.optional_node(node) {
-            node = nodes.get(self.pick_idx(&nodes)).unwrap();
+        if self.focus.is_some() {
+            nodes.retain(|n| self.in_focus(n));
         }
-        (node.id(), Vec::new(), Self::delta(*node, &[]))
+        nodes
     }
 
-    fn splice_node(&mut self, text: &[u8], tree: &Tree) -> (usize, Vec<u8>, isize) {
-        let chaotic = self.rng.gen_range(0..100) < self.chaos;
+    fn in_focus(&self, node: &Node<'_>) -> bool {
+        self.focus.is_none()
+            || self
+                .focused
+                .iter()
+                .any(|r| r.start <= node.start_byte() && node.end_byte() <= r.end)
+    }
 
-        let mut node = tree.root_node();
-        let mut candidates = Vec::new();
-        // When modified trees are re-parsed, their nodes may have novel kinds
-        // not in Branches (candidates.len() == 0). Also, avoid not mutating
-        // (candidates.len() == 1).
-        while candidates.len() <= 1 {
-            node = self.pick_node(tree);
-            candidates = if chaotic {
-                let kind_idx = self.rng.gen_range(0..self.kinds.len());
-                let kind = self.kinds.get(kind_idx).unwrap();
-                self.branches.0.get(kind).unwrap().clone()
-            } else {
-                self.branches
-                    .0
-                    .get(node.kind())
-                    .cloned()
-                    .unwrap_or_default()
-            };
+    /// Find where [`Config::focus`] is in `text`, which is `range` if it's a
+    /// range
+    fn focus_on(&mut self, text: &[u8], tree: &Tree, range: Option<&Range<usize>>) {
+        self.focused = match (&self.focus, range) {
+            (None, _) => Vec::new(),
+            (Some(Focus::Range(_)), Some(range)) => vec![range.clone()],
+            (Some(Focus::Range(range)), None) => vec![range.clone()],
+            (Some(Focus::Query(query)), _) => QueryCursor::new()
+                .matches(query, tree.root_node(), text)
+                .flat_map(|m| m.captures.iter().map(|c| c.node.byte_range()))
+                .collect(),
+        };
+    }
+
+    /// Where `range` is after `mutations` of the text it's in
+    fn moved(range: &Range<usize>, mutations: &[(Range<usize>, isize)]) -> Range<usize> {
+        let (mut start, mut end) = (range.start, range.end);
+        for (mutated, delta) in mutations {
+            if mutated.end <= range.start {
+                start = start.saturating_add_signed(*delta);
+            }
+            if mutated.start < range.end {
+                end = end.saturating_add_signed(*delta);
+            }
         }
+        start..end.max(start)
+    }
 
-        let idx = self.rng.gen_range(0..candidates.len());
-        let mut candidate = candidates.get(idx).unwrap();
-        // Try to avoid not mutating
-        let node_text = &text[node.byte_range()];
-        while candidates.len() > 1 && candidate == &node_text {
-            let idx = self.rng.gen_range(0..candidates.len());
-            candidate = candidates.get(idx).unwrap();
-        }
-        // eprintln!(
-        //     "Replacing '{}' with '{}'",
-        //     std::str::from_utf8(&text[node.byte_range()]).unwrap(),
-        //     std::str::from_utf8(candidate).unwrap(),
-        // );
-        let replace = Vec::from(*candidate);
-        let delta = Self::delta(node, replace.as_slice());
-        (node.id(), replace, delta)
+    /// Pick one of `targets` (which must be non-empty), respecting
+    /// `kind_weights`
+    fn pick_target<'b>(&mut self, targets: &[Node<'b>]) -> Node<'b> {
+        if self.kind_weights.is_empty() {
+            return targets[self.pick_idx(targets)];
+        }
+        let weights: Vec<_> = targets
+            .iter()
+            .map(|n| u64::from(self.kind_weight(n.kind())))
+            .collect();
+        let idx = WeightedIndex::new(&weights)
+            .expect("Weights should be positive")
+            .sample(&mut self.rng);
+        targets[idx]
     }
 
-    pub fn splice_tree(&mut self, text0: &[u8], mut tree: Tree) -> Option<Vec<u8>> {
-        // TODO: Assert that text0 and tree.root_node() are the same length?
-        let mut edits = Edits::default();
-        if self.inter_splices == 0 {
+    fn pick_node<'b>(&mut self, tree: &'b Tree) -> Option<Node<'b>> {
+        let targets = self.targets(tree);
+        if targets.is_empty() {
             return None;
         }
-        let splices = self.rng.gen_range(1..self.inter_splices);
-        let mut text = Vec::from(text0);
-        let mut sz = isize::try_from(text.len()).unwrap_or_default();
-        for i in 0..splices {
-            let (id, bytes, delta) = if self.rng.gen_range(0..100) < self.deletions {
-                self.delete_node(text.as_slice(), &tree)
-            } else {
-                self.splice_node(text.as_slice(), &tree)
-            };
-            sz += delta;
-            let sized_out = usize::try_from(sz).unwrap_or_default() >= self.max_size;
-            edits.0.insert(id, bytes);
-            if i % self.reparse == 0 || i + 1 == splices || sized_out {
-                let mut result = Vec::with_capacity(usize::try_from(sz).unwrap_or_default());
-                tree_sitter_edit::render(&mut result, &tree, text.as_slice(), &edits).ok()?;
-                text = result.clone();
-                tree = parse(self.language, &String::from_utf8_lossy(text.as_slice()));
-                edits = Edits::default();
-            }
-            if sized_out {
+        Some(self.pick_target(&targets))
+    }
+
+    fn delete(node: Node<'_>) -> Mutation<'_, 'a> {
+        Mutation {
+            operation: Operation::Delete,
+            node,
+            replace: Vec::new(),
+            delta: Self::delta(node, &[]),
+            candidate: None,
+        }
+    }
+
+    /// Whether `node` is a comment or an attribute, e.g., `#[derive(Debug)]`,
+    /// `@Override`, or a decorator
+    fn is_trivia(node: &Node<'_>) -> bool {
+        node.is_extra()
+            || ["annotation", "attribute", "decorator"]
+                .iter()
+                .any(|k| node.kind().contains(k))
+    }
+
+    /// The comments and attributes on the lines right before `node`, nearest
+    /// first, if [`Config::preserve_leading_trivia`] is set
+    fn leading_trivia<'b>(&self, text: &[u8], node: Node<'b>) -> Vec<Node<'b>> {
+        let mut trivia = Vec::new();
+        if !self.preserve_leading_trivia {
+            return trivia;
+        }
+        let mut next = node;
+        while let Some(prev) = next.prev_sibling() {
+            let gap = &text[prev.end_byte()..next.start_byte()];
+            let attached = gap.iter().filter(|b| **b == b'\n').count() <= 1;
+            // Not a comment at the end of the line of another node
+            let own_line = indentation(text, prev.start_byte()).len()
+                == prev.start_byte() - line_start(text, prev.start_byte());
+            if !Self::is_trivia(&prev) || !attached || !own_line {
                 break;
             }
+            trivia.push(prev);
+            next = prev;
         }
-        Some(text)
+        trivia
     }
-}
 
-impl<'a> Iterator for Splicer<'a> {
-    type Item = Vec<u8>;
+    /// Delete `node` and its leading trivia, see [`Splicer::leading_trivia`]
+    fn delete_with_trivia<'b>(&self, text: &[u8], node: Node<'b>) -> Vec<Mutation<'b, 'a>> {
+        std::iter::once(node)
+            .chain(self.leading_trivia(text, node))
+            .map(Self::delete)
+            .collect()
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut tree_idx: usize = self.pick_usize(self.trees.len());
-        let (mut text, mut tree) = *self.trees.get(tree_idx).unwrap();
-        while text.len() > self.max_size {
-            tree_idx = self.pick_usize(self.trees.len());
-            (text, tree) = *self.trees.get(tree_idx).unwrap();
-        }
-        self.splice_tree(text, tree.clone())
+    /// Delete `node`, its leading trivia, and its separator if it's in a
+    /// list, see [`NodeTypes::separator`]
+    fn delete_element<'b>(&self, text: &[u8], node: Node<'b>) -> Vec<Mutation<'b, 'a>> {
+        let mut mutations = self.delete_with_trivia(text, node);
+        mutations.extend(self.node_types.separator(&node).map(Self::delete));
+        mutations
+    }
+
+    fn delete_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Vec<Mutation<'b, 'a>> {
+        let chaotic = self.rng.gen_range(0..100) < self.chaos;
+        if chaotic {
+            return match self.pick_node(tree) {
+                Some(node) => self.delete_with_trivia(text, node),
+                None => Vec::new(),
+            };
+        }
+        let mut nodes = self.targets(tree);
+        if nodes.is_empty() {
+            return Vec::new();
+        }
+        if nodes.iter().all(|n| !self.node_types.can_delete(n)) {
+            return Vec::new();
+        }
+        if !self.kind_weights.is_empty() {
+            nodes.retain(|n| self.node_types.can_delete(n));
+        }
+        let mut node = self.pick_target(&nodes);
+        while !self.node_types.can_delete(&node) {
+            node = self.pick_target(&nodes);
+        }
+        self.delete_element(text, node)
+    }
+
+    /// Kinds of nodes that can be in lists of children of the parent of
+    /// `node`. Some grammars list kinds they never declare, so skip those.
+    fn list_kinds(&self, node: &Node<'_>) -> Vec<String> {
+        let Some(parent) = node.parent() else {
+            return Vec::new();
+        };
+        self.node_types
+            .list_types(&parent)
+            .iter()
+            .flat_map(|ty| self.node_types.get_subtypes(ty))
+            .collect()
+    }
+
+    /// Whether `node` is in a list of children of its parent, so that
+    /// repeating it or adding to the list is grammatical
+    fn repeatable(&self, node: &Node<'_>) -> bool {
+        self.list_kinds(node).iter().any(|k| k == node.kind())
+    }
+
+    /// Replace `node` with itself followed by `inserted`, separated like
+    /// `node` is from its neighbors.
+    ///
+    /// If `node` has no neighbors, separate them like the elements of another
+    /// list of the same kind in `tree`, e.g., with `, ` for arguments.
+    fn insert_after<'b>(
+        &self,
+        operation: Operation,
+        text: &[u8],
+        tree: &Tree,
+        node: Node<'b>,
+        inserted: &[u8],
+        candidate: Option<Candidate<'a>>,
+    ) -> Mutation<'b, 'a> {
+        let gap = match (node.next_named_sibling(), node.prev_named_sibling()) {
+            (Some(next), _) => &text[node.end_byte()..next.start_byte()],
+            (None, Some(prev)) => &text[prev.end_byte()..node.start_byte()],
+            (None, None) => node
+                .parent()
+                .and_then(|parent| {
+                    self.all_nodes(tree).into_iter().find_map(|n| {
+                        if n.parent()?.kind() != parent.kind() || !n.is_named() {
+                            return None;
+                        }
+                        let next = n.next_named_sibling()?;
+                        Some(&text[n.end_byte()..next.start_byte()])
+                    })
+                })
+                .unwrap_or(b" "),
+        };
+        let replace = [&text[node.byte_range()], gap, inserted].concat();
+        Mutation {
+            operation,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate,
+        }
+    }
+
+    /// Replace `node` with two copies of itself, the second with its leading
+    /// trivia, see [`Splicer::leading_trivia`]
+    fn duplicate<'b>(&self, text: &[u8], tree: &Tree, node: Node<'b>) -> Mutation<'b, 'a> {
+        let start = self
+            .leading_trivia(text, node)
+            .last()
+            .map_or(node.start_byte(), Node::start_byte);
+        self.insert_after(
+            Operation::Duplicate,
+            text,
+            tree,
+            node,
+            &text[start..node.end_byte()],
+            None,
+        )
+    }
+
+    fn duplicate_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let chaotic = self.rng.gen_range(0..100) < self.chaos;
+        if chaotic {
+            let node = self.pick_node(tree)?;
+            return Some(self.duplicate(text, tree, node));
+        }
+        let mut nodes = self.targets(tree);
+        nodes.retain(|n| self.repeatable(n));
+        if nodes.is_empty() {
+            return None;
+        }
+        let node = self.pick_target(&nodes);
+        Some(self.duplicate(text, tree, node))
+    }
+
+    /// Add a splice candidate to a list, after one of its elements
+    fn insert_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let chaotic = self.rng.gen_range(0..100) < self.chaos;
+        let mut nodes = self.targets(tree);
+        if !chaotic {
+            nodes.retain(|n| self.repeatable(n));
+        }
+        if nodes.is_empty() || self.kinds.is_empty() {
+            return None;
+        }
+        let node = self.pick_target(&nodes);
+        let kinds: Vec<&'static str> = if chaotic {
+            self.kinds.clone()
+        } else {
+            let mut kinds: Vec<_> = self
+                .list_kinds(&node)
+                .iter()
+                .filter_map(|k| self.kinds.binary_search(&k.as_str()).ok())
+                .map(|i| self.kinds[i])
+                .collect();
+            kinds.sort_unstable();
+            kinds.dedup();
+            kinds
+        };
+        if kinds.is_empty() {
+            return None;
+        }
+        let kind = kinds[self.pick_idx(&kinds)];
+        let candidate = self.pick_candidate(kind);
+        let source = self.branches.source(candidate.origin);
+        let inserted = self.reindented(text, node, candidate.text, source);
+        Some(self.insert_after(
+            Operation::Insert,
+            text,
+            tree,
+            node,
+            &inserted,
+            Some(candidate),
+        ))
+    }
+
+    /// Swap a node in a list with another element of the list, e.g., two
+    /// statements in a block.
+    ///
+    /// Chaotic swaps swap a node with any of its named siblings.
+    fn swap_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Vec<Mutation<'b, 'a>> {
+        let chaotic = self.rng.gen_range(0..100) < self.chaos;
+        let mut nodes = self.targets(tree);
+        if !chaotic {
+            nodes.retain(|n| self.repeatable(n));
+        }
+        if nodes.is_empty() {
+            return Vec::new();
+        }
+        let node = self.pick_target(&nodes);
+        let Some(parent) = node.parent() else {
+            return Vec::new();
+        };
+        let siblings: Vec<_> = (0..parent.named_child_count())
+            .filter_map(|i| parent.named_child(i))
+            .filter(|s| {
+                text[s.byte_range()] != text[node.byte_range()]
+                    && self.kind_weight(s.kind()) > 0
+                    && self.in_focus(s)
+                    && (chaotic || self.repeatable(s))
+            })
+            .collect();
+        if siblings.is_empty() {
+            return Vec::new();
+        }
+        let other = siblings[self.pick_idx(&siblings)];
+        [(node, other), (other, node)]
+            .into_iter()
+            .map(|(node, other)| {
+                let replace = text[other.byte_range()].to_vec();
+                Mutation {
+                    operation: Operation::Swap,
+                    node,
+                    delta: Self::delta(node, &replace),
+                    replace,
+                    candidate: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Whether nodes of `kind` are identifiers, e.g., `identifier` or
+    /// `type_identifier`, or one of [`Config::identifier_kinds`]
+    fn is_identifier_kind(&self, kind: &str) -> bool {
+        kind.ends_with("identifier") || self.identifier_kinds.contains(kind)
+    }
+
+    fn is_identifier(&self, node: &Node<'_>) -> bool {
+        node.is_named() && node.child_count() == 0 && self.is_identifier_kind(node.kind())
+    }
+
+    /// What kind of literal `node` is, if any
+    fn literal(&self, node: &Node<'_>, text: &[u8]) -> Option<Literal> {
+        self.literal_of(node.kind(), &text[node.byte_range()])
+    }
+
+    /// What kind of literal a node of kind `kind` with text `text` is, if any
+    fn literal_of(&self, kind: &str, text: &[u8]) -> Option<Literal> {
+        Literal::of(kind, text).or_else(|| {
+            self.literal_kinds
+                .contains(kind)
+                .then(|| Literal::guess(text))
+        })
+    }
+
+    /// Replace every occurrence of an identifier with another identifier.
+    ///
+    /// Ignores scope, but keeps a variable's definition and its uses
+    /// consistent. Chaotic renames may use an identifier of another kind.
+    fn rename_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Vec<Mutation<'b, 'a>> {
+        let chaotic = self.rng.gen_range(0..100) < self.chaos;
+        let mut nodes = self.targets(tree);
+        nodes.retain(|n| self.is_identifier(n) && (chaotic || self.branches.len(n.kind()) > 1));
+        if nodes.is_empty() {
+            return Vec::new();
+        }
+        let node = self.pick_target(&nodes);
+        let kind = if chaotic {
+            let kinds: Vec<_> = self
+                .kinds
+                .iter()
+                .copied()
+                .filter(|k| self.is_identifier_kind(k))
+                .collect();
+            if kinds.is_empty() {
+                return Vec::new();
+            }
+            kinds[self.pick_idx(&kinds)]
+        } else {
+            node.kind()
+        };
+        let name = &text[node.byte_range()];
+        let mut candidate = self.pick_candidate(kind);
+        while self.branches.len(kind) > 1 && candidate.text == name {
+            candidate = self.pick_candidate(kind);
+        }
+        self.all_nodes(tree)
+            .into_iter()
+            .filter(|n| self.is_identifier(n) && &text[n.byte_range()] == name && self.in_focus(n))
+            .map(|n| Mutation {
+                operation: Operation::Rename,
+                node: n,
+                replace: candidate.text.to_vec(),
+                delta: Self::delta(n, candidate.text),
+                candidate: Some(candidate),
+            })
+            .collect()
+    }
+
+    /// Replace a literal with an edge case, e.g., `0` or `""`.
+    ///
+    /// Chaotic literal mutations may use a literal of another kind.
+    fn literal_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let chaotic = self.rng.gen_range(0..100) < self.chaos;
+        let mut nodes = self.targets(tree);
+        nodes.retain(|n| self.literal(n, text).is_some());
+        if nodes.is_empty() {
+            return None;
+        }
+        let node = self.pick_target(&nodes);
+        let node_text = &text[node.byte_range()];
+        if self.harvest && !chaotic && self.rng.gen() {
+            let harvested: Vec<_> = self
+                .branches
+                .of_kind(node.kind())
+                .filter(|c| c.text != node_text)
+                .collect();
+            if !harvested.is_empty() {
+                let candidate = harvested[self.pick_idx(&harvested)];
+                return Some(Mutation {
+                    operation: Operation::Literal,
+                    node,
+                    delta: Self::delta(node, candidate.text),
+                    replace: candidate.text.to_vec(),
+                    candidate: Some(candidate),
+                });
+            }
+        }
+        let literal = if chaotic {
+            Literal::ALL[self.pick_idx(&Literal::ALL)]
+        } else {
+            self.literal(&node, text)?
+        };
+        let mut values = literal.values(node_text, self.max_size);
+        values.retain(|v| v != node_text);
+        if values.is_empty() {
+            return None;
+        }
+        let replace = values.swap_remove(self.pick_idx(&values));
+        Some(Mutation {
+            operation: Operation::Literal,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate: None,
+        })
+    }
+
+    fn splice_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let chaotic = self.rng.gen_range(0..100) < self.chaos;
+
+        // The loop below only terminates if some kind has several candidates.
+        if chaotic && self.branches.possible() == 0 {
+            return None;
+        }
+        let mut targets = self.targets(tree);
+        if targets.is_empty() {
+            return None;
+        }
+        if !chaotic {
+            if targets.iter().all(|n| self.branches.len(n.kind()) <= 1) {
+                return None;
+            }
+            if !self.kind_weights.is_empty() {
+                // Weights may make the loop below unlikely to terminate
+                targets.retain(|n| self.branches.len(n.kind()) > 1);
+            }
+        }
+
+        let mut node;
+        let mut kind;
+        // When modified trees are re-parsed, their nodes may have novel kinds
+        // not in Branches (no candidates). Also, avoid not mutating (one
+        // candidate).
+        loop {
+            node = self.pick_target(&targets);
+            kind = if chaotic {
+                let kind_idx = self.rng.gen_range(0..self.kinds.len());
+                *self.kinds.get(kind_idx).unwrap()
+            } else {
+                node.kind()
+            };
+            if self.branches.len(kind) > 1 {
+                break;
+            }
+        }
+
+        self.replace_node(text, node, kind, !chaotic)
+    }
+
+    fn havoc_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let node = self.pick_node(tree)?;
+        let replace = havoc(&mut self.rng, text, node);
+        Some(Mutation {
+            operation: Operation::Havoc,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate: None,
+        })
+    }
+
+    /// Pick an operator by weight
+    fn pick_operation(&mut self) -> Operation {
+        let total: u32 = self.operators.iter().map(|(_, w)| w).sum();
+        if total == 0 {
+            return Operation::Splice;
+        }
+        let mut roll = self.rng.gen_range(0..total);
+        for (op, weight) in self.operators {
+            if roll < weight {
+                return op;
+            }
+            roll -= weight;
+        }
+        unreachable!("Roll should be less than the total weight")
+    }
+
+    /// Replace a node that [`Config::rules`] captures, as they say
+    fn rule_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let rules = self.rules.clone()?;
+        let mut targets = rules.targets(text, tree);
+        targets.retain(|(node, _, _)| self.in_focus(node));
+        if targets.is_empty() {
+            return None;
+        }
+        let idx = self.pick_idx(&targets);
+        let (node, action, captured) = &targets[idx];
+        let node = *node;
+        let (replace, candidate) = match action {
+            Action::Templates(templates) => {
+                let template = &templates[self.pick_idx(templates)];
+                (fill(template, captured), None)
+            }
+            Action::Splice | Action::Kinds(_) => {
+                let kinds: Vec<&str> = match action {
+                    Action::Kinds(kinds) => kinds.iter().map(String::as_str).collect(),
+                    _ => vec![node.kind()],
+                };
+                let kinds: Vec<_> = kinds
+                    .into_iter()
+                    .filter(|k| self.branches.len(k) > 0)
+                    .collect();
+                if kinds.is_empty() {
+                    return None;
+                }
+                let kind = kinds[self.pick_idx(&kinds)];
+                let candidate = self.pick_candidate(kind);
+                let source = self.branches.source(candidate.origin);
+                (
+                    self.reindented(text, node, candidate.text, source),
+                    Some(candidate),
+                )
+            }
+        };
+        Some(Mutation {
+            operation: Operation::Rule,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate,
+        })
+    }
+
+    /// The text before and after each node that's inside a node of the same
+    /// kind, in the nearest such node, by kind, e.g., `(` and `)` around an
+    /// expression in parentheses. Found on first use.
+    fn nestings(&mut self) -> &Nestings<'a> {
+        self.nestings.get_or_insert_with(|| {
+            let mut nestings: HashMap<_, Vec<_>> = HashMap::new();
+            let mut seen = HashSet::new();
+            for (text, tree) in &self.trees {
+                let mut cursor = tree.walk();
+                // The nodes from the root to the current node
+                let mut path: Vec<Node<'a>> = Vec::new();
+                'walk: loop {
+                    let node = cursor.node();
+                    if let Some(outer) = path.iter().rev().find(|n| n.kind() == node.kind()) {
+                        let before = &text[outer.start_byte()..node.start_byte()];
+                        let after = &text[node.end_byte()..outer.end_byte()];
+                        if seen.insert((node.kind(), before, after)) {
+                            nestings
+                                .entry(node.kind())
+                                .or_default()
+                                .push((before, after));
+                        }
+                    }
+                    if cursor.goto_first_child() {
+                        path.push(node);
+                        continue;
+                    }
+                    loop {
+                        if cursor.goto_next_sibling() {
+                            break;
+                        }
+                        if !cursor.goto_parent() {
+                            break 'walk;
+                        }
+                        path.pop();
+                    }
+                }
+            }
+            nestings
+        })
+    }
+
+    /// Nest a node in copies of the text around nodes of the same kind, see
+    /// [`OperatorWeights::nest`]. Stops early rather than making the test
+    /// bigger than [`Config::max_size`].
+    fn nest_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let nestings = self.nestings();
+        if nestings.is_empty() {
+            return None;
+        }
+        let mut targets = self.targets(tree);
+        targets.retain(|n| self.nestings.as_ref().unwrap().contains_key(n.kind()));
+        if targets.is_empty() {
+            return None;
+        }
+        let node = self.pick_target(&targets);
+        let contexts = self.nestings.as_ref().unwrap()[node.kind()].clone();
+        let room = self.max_size.saturating_sub(text.len());
+        let depth = self.rng.gen_range(1..=self.nest_depth.max(1));
+        let mut levels = Vec::with_capacity(depth);
+        let mut added = 0;
+        for _ in 0..depth {
+            let (before, after) = contexts[self.pick_idx(&contexts)];
+            added += before.len() + after.len();
+            if added > room {
+                break;
+            }
+            levels.push((before, after));
+        }
+        if levels.is_empty() {
+            return None;
+        }
+        let mut replace = Vec::with_capacity(node.byte_range().len() + added);
+        for (before, _) in &levels {
+            replace.extend(*before);
+        }
+        replace.extend(&text[node.byte_range()]);
+        for (_, after) in levels.iter().rev() {
+            replace.extend(*after);
+        }
+        Some(Mutation {
+            operation: Operation::Nest,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate: None,
+        })
+    }
+
+    /// The tokens that could replace `node`, if it's an operator, see
+    /// [`OperatorWeights::operator`]
+    fn operators(&self, node: &Node<'_>) -> Vec<&str> {
+        if node.is_named() {
+            return Vec::new();
+        }
+        let Some(parent) = node.parent() else {
+            return Vec::new();
+        };
+        let mut cursor = parent.walk();
+        if !cursor.goto_first_child() {
+            return Vec::new();
+        }
+        loop {
+            if cursor.node().id() == node.id() {
+                return match cursor.field_name() {
+                    Some(field) => self.node_types.field_tokens(parent.kind(), field),
+                    None => Vec::new(),
+                };
+            }
+            if !cursor.goto_next_sibling() {
+                return Vec::new();
+            }
+        }
+    }
+
+    /// Replace an operator with another that can be in the same place
+    fn operator_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let mut targets = self.targets(tree);
+        targets.retain(|n| !self.operators(n).is_empty());
+        if targets.is_empty() {
+            return None;
+        }
+        let node = self.pick_target(&targets);
+        let node_text = &text[node.byte_range()];
+        let operators: Vec<_> = self
+            .operators(&node)
+            .into_iter()
+            .filter(|op| op.as_bytes() != node_text)
+            .map(String::from)
+            .collect();
+        if operators.is_empty() {
+            return None;
+        }
+        let replace = operators[self.pick_idx(&operators)].as_bytes().to_vec();
+        Some(Mutation {
+            operation: Operation::Operator,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate: None,
+        })
+    }
+
+    /// Replace a node with one of [`Config::templates`] for its kind
+    fn template_node<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let templates = self.templates.clone()?;
+        let mut targets = self.targets(tree);
+        targets.retain(|n| templates.templates.contains_key(n.kind()));
+        if targets.is_empty() {
+            return None;
+        }
+        let node = self.pick_target(&targets);
+        let candidates = &templates.templates[node.kind()];
+        let template = &candidates[self.pick_idx(candidates)];
+        let mut filled = Vec::new();
+        for segment in template {
+            match segment {
+                Segment::Text(t) => filled.extend(t.as_bytes()),
+                Segment::Hole(hole) => {
+                    let kinds: Vec<&'static str> = self
+                        .kinds
+                        .iter()
+                        .copied()
+                        .filter(|k| match hole {
+                            Hole::Ident => self.is_identifier_kind(k),
+                            Hole::Kinds(kinds) => kinds.iter().any(|kind| kind == k),
+                        })
+                        .collect();
+                    if kinds.is_empty() {
+                        return None;
+                    }
+                    let kind = kinds[self.pick_idx(&kinds)];
+                    filled.extend(self.pick_candidate(kind).text);
+                }
+            }
+        }
+        let replace = self.reindented(text, node, &filled, None);
+        Some(Mutation {
+            operation: Operation::Template,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate: None,
+        })
+    }
+
+    /// Replace a node with another node of the same kind from `text`, e.g.,
+    /// with another expression from the same function
+    fn splice_intra<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let branches = Branches::new(vec![(text, tree, 1)]);
+        let mut targets = self.targets(tree);
+        targets.retain(|n| branches.len(n.kind()) > 1);
+        if targets.is_empty() {
+            return None;
+        }
+        let node = self.pick_target(&targets);
+        let node_text = &text[node.byte_range()];
+        let candidates: Vec<_> = branches
+            .of_kind(node.kind())
+            .filter(|c| c.text != node_text)
+            .collect();
+        let candidate = candidates[self.pick_idx(&candidates)];
+        let source = branches.source(candidate.origin);
+        let replace = self.reindented(text, node, candidate.text, source);
+        Some(Mutation {
+            operation: Operation::Intra,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate: None,
+        })
+    }
+
+    fn pick_candidate(&mut self, kind: &str) -> Candidate<'a> {
+        let idx = if self.branches.weighted {
+            WeightedIndex::new(&self.branches.weights[kind])
+                .expect("Weights should be positive")
+                .sample(&mut self.rng)
+        } else {
+            self.rng.gen_range(0..self.branches.len(kind))
+        };
+        self.branches.get(kind, idx)
+    }
+
+    /// Other candidates of kind `kind` with the same type as `node_text`, if
+    /// [`Config::type_hint`] knows its type
+    fn typed_candidates(&self, kind: &str, node_text: &[u8]) -> Option<Vec<Candidate<'a>>> {
+        let hint = self.type_hint.as_ref()?;
+        let ty = hint.type_of(kind, node_text)?;
+        Some(
+            self.branches
+                .of_kind(kind)
+                .filter(|c| c.text != node_text && hint.type_of(kind, c.text).as_ref() == Some(&ty))
+                .collect(),
+        )
+    }
+
+    /// Replace `node` with a candidate of kind `kind`, of the same type if
+    /// `typed`
+    fn replace_node<'b>(
+        &mut self,
+        text: &[u8],
+        node: Node<'b>,
+        kind: &str,
+        typed: bool,
+    ) -> Option<Mutation<'b, 'a>> {
+        let node_text = &text[node.byte_range()];
+        let typed = if typed {
+            self.typed_candidates(kind, node_text)
+        } else {
+            None
+        };
+        let candidate = match typed {
+            Some(candidates) if candidates.is_empty() => return None,
+            Some(candidates) => candidates[self.pick_idx(&candidates)],
+            None => {
+                let mut candidate = self.pick_candidate(kind);
+                // Try to avoid not mutating
+                while self.branches.len(kind) > 1 && candidate.text == node_text {
+                    candidate = self.pick_candidate(kind);
+                }
+                candidate
+            }
+        };
+        let source = self.branches.source(candidate.origin);
+        let replace = self.reindented(text, node, candidate.text, source);
+        let delta = Self::delta(node, replace.as_slice());
+        Some(Mutation {
+            operation: Operation::Splice,
+            node,
+            replace,
+            delta,
+            candidate: Some(candidate),
+        })
+    }
+
+    /// Pick a node with a kind that hasn't been the first target of a test.
+    fn pick_fresh_node<'b>(&mut self, tree: &'b Tree) -> Option<Node<'b>> {
+        let nodes: Vec<_> = self
+            .targets(tree)
+            .into_iter()
+            .filter(|n| !self.primary_kinds.contains(n.kind()) && self.branches.len(n.kind()) > 1)
+            .collect();
+        if nodes.is_empty() {
+            return None;
+        }
+        Some(self.pick_target(&nodes))
+    }
+
+    /// Splice a node with a kind that hasn't been the first target of a test.
+    ///
+    /// Once every kind in the tree has been a target, start over.
+    fn splice_fresh_kind<'b>(&mut self, text: &[u8], tree: &'b Tree) -> Option<Mutation<'b, 'a>> {
+        let node = match self.pick_fresh_node(tree) {
+            Some(node) => node,
+            None => {
+                self.primary_kinds.clear();
+                self.pick_fresh_node(tree)?
+            }
+        };
+        self.primary_kinds.insert(node.kind());
+        self.replace_node(text, node, node.kind(), true)
+    }
+
+    /// Top-level items of a file, e.g., functions
+    fn items(tree: &Tree) -> Vec<Node<'_>> {
+        let mut cursor = tree.walk();
+        tree.root_node().children(&mut cursor).collect()
+    }
+
+    /// Replace the root of `tree` with alternate runs of its top-level items
+    /// and those of `other`, which is `sources[source]` if it's a file
+    fn cross_items<'b>(
+        &mut self,
+        text: &[u8],
+        tree: &'b Tree,
+        other_text: &'a [u8],
+        other: &Tree,
+        source: Option<usize>,
+    ) -> Option<Mutation<'b, 'a>> {
+        let items = Self::items(tree);
+        let other_items = Self::items(other);
+        if items.len() < 2 || other_items.len() < 2 {
+            return None;
+        }
+        let root = tree.root_node();
+        let mut replace = text[root.start_byte()..items[0].start_byte()].to_vec();
+        let mut ours = self.rng.gen::<bool>();
+        for i in 0..items.len().max(other_items.len()) {
+            let (text, items) = if (ours && i < items.len()) || i >= other_items.len() {
+                (text, &items)
+            } else {
+                (other_text, &other_items)
+            };
+            if i > 0 {
+                replace.extend(&text[items[i - 1].end_byte()..items[i].start_byte()]);
+            }
+            replace.extend(&text[items[i].byte_range()]);
+            if self.rng.gen() {
+                ours = !ours;
+            }
+        }
+        replace.extend(&text[items[items.len() - 1].end_byte()..root.end_byte()]);
+        Some(Mutation {
+            operation: Operation::Crossover,
+            node: root,
+            delta: Self::delta(root, &replace),
+            replace,
+            candidate: Some(Candidate {
+                text: other_text,
+                origin: source.map(|s| (s, 0)),
+            }),
+        })
+    }
+
+    /// Replace a node of `tree` with a node of the same kind from `other`,
+    /// which is `sources[source]` if it's a file
+    fn cross_subtree<'b>(
+        &mut self,
+        text: &[u8],
+        tree: &'b Tree,
+        other_text: &'a [u8],
+        other: &Tree,
+        source: Option<usize>,
+    ) -> Option<Mutation<'b, 'a>> {
+        let mut targets: HashMap<&str, Vec<Node<'b>>> = HashMap::new();
+        for node in self.targets(tree) {
+            targets.entry(node.kind()).or_default().push(node);
+        }
+        let donors: Vec<_> = self
+            .all_nodes(other)
+            .into_iter()
+            .filter(|n| targets.contains_key(n.kind()))
+            .collect();
+        if donors.is_empty() {
+            return None;
+        }
+        let donor = donors[self.pick_idx(&donors)];
+        let nodes = &targets[donor.kind()];
+        let node = nodes[self.pick_idx(nodes)];
+        let candidate = &other_text[donor.byte_range()];
+        let from = Some((other_text, donor.start_byte()));
+        let replace = self.reindented(text, node, candidate, from);
+        Some(Mutation {
+            operation: Operation::Crossover,
+            node,
+            delta: Self::delta(node, &replace),
+            replace,
+            candidate: Some(Candidate {
+                text: candidate,
+                origin: source.map(|s| (s, donor.start_byte())),
+            }),
+        })
+    }
+
+    /// Cross file number `idx` with another file, see [`Config::crossover`]
+    fn cross(&mut self, idx: usize) -> Option<Vec<u8>> {
+        let mut other = self.pick_usize(self.trees.len() - 1);
+        if other >= idx {
+            other += 1;
+        }
+        let (text, tree) = self.trees[idx];
+        let (other_text, other_tree) = self.trees[other];
+        let source = (other < self.files).then_some(other);
+        self.focus_on(text, tree, None);
+        // Interleaving items replaces the whole file
+        let items = if self.rng.gen() && self.focus.is_none() {
+            self.cross_items(text, tree, other_text, other_tree, source)
+        } else {
+            None
+        };
+        let mutation = match items {
+            Some(mutation) => mutation,
+            None => self.cross_subtree(text, tree, other_text, other_tree, source)?,
+        };
+        let range = mutation.node.byte_range();
+        if text.len().saturating_add_signed(mutation.delta) > self.max_size {
+            return None;
+        }
+        self.start(text);
+        if self.trace.is_some() {
+            let origin = mutation.candidate.and_then(|c| self.origin(c));
+            let edit = Edit {
+                operation: mutation.operation,
+                kind: mutation.node.kind().to_string(),
+                start: range.start,
+                end: range.end,
+                replacement: mutation.replace.as_slice().into(),
+                origin,
+            };
+            if let Some(trace) = &mut self.trace {
+                trace.steps.push(vec![edit]);
+            }
+        }
+        Some([&text[..range.start], &mutation.replace, &text[range.end..]].concat())
+    }
+
+    /// `candidate`, re-indented to replace `node` in `text` if
+    /// [`Config::reindent`] is set.
+    ///
+    /// The candidate's indentation is that of its line in `source`, the text
+    /// it came from and its offset there. Candidates without one, e.g., filled
+    /// templates, are left as they are.
+    fn reindented(
+        &self,
+        text: &[u8],
+        node: Node<'_>,
+        candidate: &[u8],
+        source: Option<(&[u8], usize)>,
+    ) -> Vec<u8> {
+        let Some((source, start)) = source.filter(|_| self.reindent) else {
+            return candidate.to_vec();
+        };
+        reindent(
+            candidate,
+            indentation(source, start),
+            indentation(text, node.start_byte()),
+        )
+    }
+
+    /// Where a splice candidate came from, if it's from a file or a donor
+    fn origin(&self, candidate: Candidate<'_>) -> Option<Origin> {
+        let (source, start) = candidate.origin?;
+        let (file, _) = self.sources.get(source)?;
+        Some(Origin {
+            file: file.to_string(),
+            start,
+            end: start + candidate.text.len(),
+        })
+    }
+
+    /// The edits that rendering applies, in order: an edit inside an earlier
+    /// one is dropped.
+    fn applied_edits(
+        tree: &Tree,
+        edits: &Edits,
+        traced: &HashMap<usize, (Operation, Option<Origin>)>,
+    ) -> Vec<Edit> {
+        edits
+            .outermost(tree)
+            .into_iter()
+            .map(|node| {
+                let (operation, origin) = traced[&node.id()].clone();
+                Edit {
+                    operation,
+                    kind: node.kind().to_string(),
+                    start: node.start_byte(),
+                    end: node.end_byte(),
+                    replacement: edits.0[&node.id()].as_slice().into(),
+                    origin,
+                }
+            })
+            .collect()
+    }
+
+    /// Make the next test, like [`Iterator::next`], and say where it came
+    /// from. Records its trace even if [`Config::trace`] is disabled.
+    pub fn next_with_metadata(&mut self) -> Option<Mutant<'a>> {
+        let index = self.generated;
+        let tracing = self.trace.is_some();
+        if !tracing {
+            self.trace = Some(Trace::default());
+        }
+        let bytes = self.next();
+        let trace = self.take_trace().unwrap_or_default();
+        if !tracing {
+            self.trace = None;
+        }
+        Some(Mutant {
+            bytes: bytes?,
+            input: self.input,
+            trace,
+            index,
+        })
+    }
+
+    /// The trace of the last test, if [`Config::trace`] is enabled
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.as_mut().map(std::mem::take)
+    }
+
+    /// Record which file `text0` is, and clear the trace
+    fn start(&mut self, text0: &[u8]) {
+        self.input = self
+            .sources
+            .iter()
+            .find(|(_, text)| text.as_ptr() == text0.as_ptr() && text.len() == text0.len())
+            .map(|(file, _)| *file);
+        if let Some(trace) = &mut self.trace {
+            trace.steps.clear();
+            trace.input = self.input.map(str::to_string);
+        }
+    }
+
+    /// The identifiers (including field names) and literals (e.g., strings
+    /// and numbers) of the inputs, donors, and added tokens, by kind. Renames
+    /// use these, and literal mutations with [`Config::harvest`].
+    pub fn harvest(&self) -> BTreeMap<&'static str, Vec<&'a [u8]>> {
+        self.branches
+            .candidates
+            .iter()
+            .filter_map(|(kind, candidates)| {
+                let pool: Vec<_> = candidates
+                    .iter()
+                    .copied()
+                    .filter(|c| self.is_identifier_kind(kind) || self.literal_of(kind, c).is_some())
+                    .collect();
+                (!pool.is_empty()).then_some((*kind, pool))
+            })
+            .collect()
+    }
+
+    /// The file that the last test was made from, if it's one of the files
+    /// passed to [`Splicer::new`]
+    pub fn input(&self) -> Option<&'a str> {
+        self.input
+    }
+
+    pub fn splice_tree(&mut self, text0: &[u8], mut tree: Tree) -> Option<Vec<u8>> {
+        // TODO: Assert that text0 and tree.root_node() are the same length?
+        let mut edits = Edits::default();
+        if self.inter_splices == 0 && self.budget.is_none() {
+            return None;
+        }
+        self.start(text0);
+        let mut traced = HashMap::new();
+        let budget = self.budget.map(|b| b.bytes(text0.len()));
+        let target = self.target_size.map(|max| {
+            let size = self.size_distribution.sample(&mut self.rng, max);
+            isize::try_from(size).unwrap_or(isize::MAX)
+        });
+        let splices = match (budget, target) {
+            (None, None) => self.rng.gen_range(1..=self.inter_splices),
+            _ => usize::MAX,
+        };
+        let mut spent = 0;
+        let mut misses = 0;
+        let mut text = Vec::from(text0);
+        let mut sz = isize::try_from(text.len()).unwrap_or_default();
+        let mut range = match &self.focus {
+            Some(Focus::Range(range)) => Some(range.clone()),
+            _ => None,
+        };
+        // Mutations since the last re-parse, which move `range`
+        let mut moves = Vec::new();
+        for i in 0..splices {
+            self.focus_on(text.as_slice(), &tree, range.as_ref());
+            let mut mutations: Vec<_> = if i == 0 && self.unique_kinds {
+                self.splice_fresh_kind(text.as_slice(), &tree)
+                    .into_iter()
+                    .collect()
+            } else {
+                let text = text.as_slice();
+                let operation = match target {
+                    Some(target) if self.rng.gen() => match (sz < target, self.rng.gen()) {
+                        (true, true) => Operation::Duplicate,
+                        (true, false) => Operation::Insert,
+                        (false, _) => Operation::Delete,
+                    },
+                    _ => self.pick_operation(),
+                };
+                match operation {
+                    Operation::Delete => self.delete_node(text, &tree),
+                    Operation::Duplicate => self.duplicate_node(text, &tree).into_iter().collect(),
+                    Operation::Insert => self.insert_node(text, &tree).into_iter().collect(),
+                    Operation::Swap => self.swap_node(text, &tree),
+                    Operation::Rename => self.rename_node(text, &tree),
+                    Operation::Literal => self.literal_node(text, &tree).into_iter().collect(),
+                    Operation::Havoc => self.havoc_node(text, &tree).into_iter().collect(),
+                    Operation::Intra => self.splice_intra(text, &tree).into_iter().collect(),
+                    Operation::Rule => self.rule_node(text, &tree).into_iter().collect(),
+                    Operation::Template => self.template_node(text, &tree).into_iter().collect(),
+                    Operation::Nest => self.nest_node(text, &tree).into_iter().collect(),
+                    Operation::Operator => self.operator_node(text, &tree).into_iter().collect(),
+                    Operation::Splice | Operation::Crossover | Operation::Embedded => {
+                        self.splice_node(text, &tree).into_iter().collect()
+                    }
+                }
+            };
+            if let Some(budget) = budget {
+                let cost: usize = mutations
+                    .iter()
+                    .map(|m| m.node.byte_range().len().max(m.replace.len()))
+                    .sum();
+                if cost == 0 || spent + cost > budget {
+                    misses += 1;
+                    mutations.clear();
+                } else {
+                    spent += cost;
+                    misses = 0;
+                }
+            }
+            let distance = target.map(|target| (target - sz).abs());
+            if let (Some(target), Some(distance)) = (target, distance) {
+                // Don't overshoot, e.g., by deleting or duplicating a huge node
+                let delta: isize = mutations.iter().map(|m| m.delta).sum();
+                if (target - sz - delta).abs() > distance.max(target / 10) {
+                    mutations.clear();
+                }
+            }
+            for mutation in mutations {
+                sz += mutation.delta;
+                if range.is_some() {
+                    moves.push((mutation.node.byte_range(), mutation.delta));
+                }
+                let id = mutation.node.id();
+                if self.trace.is_some() {
+                    let origin = mutation.candidate.and_then(|c| self.origin(c));
+                    traced.insert(id, (mutation.operation, origin));
+                }
+                edits.0.insert(id, mutation.replace);
+            }
+            let sized_out = usize::try_from(sz).unwrap_or_default() >= self.max_size;
+            let spent_budget =
+                budget.is_some_and(|budget| spent >= budget || misses >= BUDGET_MISSES);
+            let reached = match (target, distance) {
+                (Some(target), Some(distance)) => {
+                    if (target - sz).abs() < distance {
+                        misses = 0;
+                    } else if budget.is_none() {
+                        misses += 1;
+                    }
+                    (target - sz).abs() <= target / 10 || misses >= BUDGET_MISSES
+                }
+                _ => false,
+            };
+            let last = i + 1 == splices || sized_out || spent_budget || reached;
+
+            if last || (self.reparse != 0 && i % self.reparse == 0) {
+                let mut result = Vec::with_capacity(usize::try_from(sz).unwrap_or_default());
+                tree_sitter_edit::render(&mut result, &tree, text.as_slice(), &edits).ok()?;
+                if self.trace.is_some() {
+                    let step = Self::applied_edits(&tree, &edits, &traced);
+                    if let (Some(trace), false) = (&mut self.trace, step.is_empty()) {
+                        trace.steps.push(step);
+                    }
+                    traced.clear();
+                }
+                text = result;
+                range = range.map(|r| Self::moved(&r, &moves));
+                moves.clear();
+                // The tree of the finished test isn't needed
+                if !last {
+                    edits.apply(&mut tree);
+                    tree = reparse(self.language, text.as_slice(), &tree);
+                }
+                edits = Edits::default();
+            }
+            if last {
+                break;
+            }
+        }
+        Some(text)
+    }
+}
+
+impl<'a> Iterator for Splicer<'a> {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.fitting == 0 {
+            // There's nothing to mutate, don't look for it forever
+            return None;
+        }
+        let generated = self.generated;
+        self.generated += self.stride;
+        self.reseed(generated);
+        let tree_idx = if generated < self.per_seed * self.trees.len() {
+            generated % self.trees.len()
+        } else {
+            let mut tree_idx: usize = self.pick_tree_idx();
+            while self.trees[tree_idx].0.len() > self.max_size {
+                tree_idx = self.pick_tree_idx();
+            }
+            tree_idx
+        };
+        if self.crossover > 0 && self.trees.len() > 1 && self.rng.gen_range(0..100) < self.crossover
+        {
+            if let Some(child) = self.cross(tree_idx) {
+                return Some(child);
+            }
+        }
+        let (text, tree) = self.trees[tree_idx];
+        self.splice_tree(text, tree.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The kind and position of each node, in order
+    fn nodes(tree: &Tree) -> Vec<(&'static str, Range<usize>, Point, Point)> {
+        let mut nodes = Vec::new();
+        let mut cursor = tree.walk();
+        'walk: loop {
+            let node = cursor.node();
+            nodes.push((
+                node.kind(),
+                node.byte_range(),
+                node.start_position(),
+                node.end_position(),
+            ));
+            if cursor.goto_first_child() {
+                continue;
+            }
+            loop {
+                if cursor.goto_next_sibling() {
+                    break;
+                }
+                if !cursor.goto_parent() {
+                    break 'walk;
+                }
+            }
+        }
+        nodes
+    }
+
+    #[test]
+    fn incremental_reparse() {
+        let language = tree_sitter_rust::language();
+        let text = b"fn f(x: u8) -> u8 {\n    let y = x + 1;\n    y * 2\n}\n\nfn g() { f(3); }\n";
+        let mut tree = parse(language, text);
+        let root = tree.root_node();
+        let find = |source: &str| {
+            let start = text
+                .windows(source.len())
+                .position(|w| w == source.as_bytes())
+                .unwrap();
+            root.descendant_for_byte_range(start, start + source.len())
+                .unwrap()
+        };
+        let mut edits = Edits::default();
+        // Longer, over several lines, nested in another edit, and shorter
+        let replacements = [
+            ("x + 1", "{\n        x\n    } + 100"),
+            ("u8", "u16"),
+            ("y * 2", "y"),
+            ("2", "3"),
+            ("f(3)", "f(f(3))"),
+        ];
+        for (source, replacement) in replacements {
+            edits
+                .0
+                .insert(find(source).id(), replacement.as_bytes().to_vec());
+        }
+        let mut edited = Vec::new();
+        tree_sitter_edit::render(&mut edited, &tree, text, &edits).unwrap();
+        assert_eq!(
+            String::from_utf8(edited.clone()).unwrap(),
+            "fn f(x: u16) -> u8 {\n    let y = {\n        x\n    } + 100;\n    y\n}\n\nfn g() { f(f(3)); }\n"
+        );
+        edits.apply(&mut tree);
+        let incremental = reparse(language, &edited, &tree);
+        let fresh = parse(language, &edited);
+        assert!(!fresh.root_node().has_error());
+        assert_eq!(
+            incremental.root_node().to_sexp(),
+            fresh.root_node().to_sexp()
+        );
+        assert_eq!(nodes(&incremental), nodes(&fresh));
+    }
+
+    /// Make `n` tests from a small Rust file
+    fn generate(
+        builder: impl FnOnce(crate::SplicerBuilder) -> crate::SplicerBuilder,
+        n: usize,
+    ) -> Vec<Vec<u8>> {
+        let language = tree_sitter_rust::language();
+        let node_types = NodeTypes::new(tree_sitter_rust::NODE_TYPES).unwrap();
+        let text = b"fn f(x: u8) -> u8 {\n    let y = x + 1;\n    y * 2\n}\n".to_vec();
+        let tree = parse(language, &text);
+        let files = HashMap::from([(String::from("f.rs"), (text, tree))]);
+        let splicer = builder(crate::SplicerBuilder::new(language, node_types)).build(&files);
+        let tests: Vec<_> = splicer.take(n).collect();
+        assert_eq!(tests.len(), n);
+        tests
+    }
+
+    /// `text` as the only input
+    fn input(text: &str) -> HashMap<String, (Vec<u8>, Tree)> {
+        let tree = parse(tree_sitter_rust::language(), text.as_bytes());
+        HashMap::from([(String::from("f.rs"), (text.as_bytes().to_vec(), tree))])
+    }
+
+    fn rust<'a>() -> crate::SplicerBuilder<'a> {
+        let node_types = NodeTypes::new(tree_sitter_rust::NODE_TYPES).unwrap();
+        crate::SplicerBuilder::new(tree_sitter_rust::language(), node_types).chaos(0)
+    }
+
+    /// Has lists in each kind of node in [`LISTS`], and nodes that aren't in
+    /// lists, like the operands of `z * 2`
+    const LISTED: &str = "fn f(x: u8, y: u8) -> u8 {\n    let z = g(x, y);\n    z * 2\n}\n";
+
+    /// Kinds of the nodes in [`LISTED`] with lists of children
+    const LISTS: [&str; 4] = ["arguments", "block", "parameters", "source_file"];
+
+    #[test]
+    fn duplicate_in_lists() {
+        let files = input(LISTED);
+        let (text, tree) = &files["f.rs"];
+        let mut splicer = rust().build(&files);
+        let mut parents = HashSet::new();
+        for _ in 0..64 {
+            let m = splicer.duplicate_node(text, tree).unwrap();
+            assert_eq!(m.operation, Operation::Duplicate);
+            parents.insert(m.node.parent().unwrap().kind());
+        }
+        assert_eq!(parents, HashSet::from(LISTS));
+    }
+
+    #[test]
+    fn insert_in_lists() {
+        let files = input(LISTED);
+        let (text, tree) = &files["f.rs"];
+        let mut splicer = rust().build(&files);
+        let mut parents = HashSet::new();
+        for _ in 0..64 {
+            let Some(m) = splicer.insert_node(text, tree) else {
+                continue;
+            };
+            assert_eq!(m.operation, Operation::Insert);
+            assert!(m.replace.starts_with(&text[m.node.byte_range()]));
+            parents.insert(m.node.parent().unwrap().kind());
+        }
+        assert_eq!(parents, HashSet::from(LISTS));
+    }
+
+    #[test]
+    fn swap_in_lists() {
+        let files = input(LISTED);
+        let (text, tree) = &files["f.rs"];
+        let mut splicer = rust().build(&files);
+        let mut parents = HashSet::new();
+        for _ in 0..64 {
+            let swaps = splicer.swap_node(text, tree);
+            if swaps.is_empty() {
+                continue;
+            }
+            let [a, b] = swaps.as_slice() else {
+                panic!("Expected two mutations, found {}", swaps.len());
+            };
+            assert_eq!(a.operation, Operation::Swap);
+            assert_eq!(a.node.parent(), b.node.parent());
+            assert_eq!(a.replace, &text[b.node.byte_range()]);
+            assert_eq!(b.replace, &text[a.node.byte_range()]);
+            parents.insert(a.node.parent().unwrap().kind());
+        }
+        // `f` is the only item in the file, so there's nothing to swap it with
+        assert_eq!(parents, HashSet::from(["arguments", "block", "parameters"]));
+    }
+
+    #[test]
+    fn rename_every_use() {
+        let files = input("fn f(x: u8) -> u8 {\n    let y = x + 1;\n    y * x\n}\n");
+        let (text, tree) = &files["f.rs"];
+        let mut splicer = rust().build(&files);
+        let mut renamed = HashSet::new();
+        for _ in 0..64 {
+            let renames = splicer.rename_node(text, tree);
+            let name = &text[renames[0].node.byte_range()];
+            let mut uses: Vec<_> = nodes(tree)
+                .into_iter()
+                .filter(|(kind, range, _, _)| *kind == "identifier" && &text[range.clone()] == name)
+                .map(|(_, range, _, _)| range)
+                .collect();
+            let mut ranges: Vec<_> = renames.iter().map(|m| m.node.byte_range()).collect();
+            uses.sort_by_key(|r| r.start);
+            ranges.sort_by_key(|r| r.start);
+            assert_eq!(ranges, uses);
+            for m in &renames {
+                assert_eq!(m.operation, Operation::Rename);
+                assert_eq!(m.replace, renames[0].replace);
+                assert_ne!(m.replace, name);
+            }
+            renamed.insert(name);
+        }
+        assert_eq!(renamed.len(), 3);
+    }
+
+    #[test]
+    fn literal_only_literals() {
+        let files = input(
+            "fn f(x: u8) -> bool {\n    let s = \"s\";\n    x > 1 && 'c' != 'd' || true\n}\n",
+        );
+        let (text, tree) = &files["f.rs"];
+        let mut splicer = rust().build(&files);
+        let mut kinds = HashSet::new();
+        for _ in 0..64 {
+            let Some(m) = splicer.literal_node(text, tree) else {
+                continue;
+            };
+            assert_eq!(m.operation, Operation::Literal);
+            kinds.insert(m.node.kind());
+        }
+        // `true` is the token in a `boolean_literal`, and a literal of its
+        // own in other grammars, e.g., JSON
+        assert_eq!(
+            kinds,
+            HashSet::from([
+                "boolean_literal",
+                "char_literal",
+                "integer_literal",
+                "string_literal",
+                "true"
+            ])
+        );
+    }
+
+    #[test]
+    fn budget_in_focus() {
+        let text = concat!(
+            "fn f(x: u8) -> u8 {\n    x + 1\n}\n\n",
+            "fn g(y: u8) -> u8 {\n    let z = f(y);\n    g(z * 2)\n}\n\n",
+            "fn h() -> u8 {\n    f(g(3))\n}\n",
+        );
+        let start = text.find("fn g").unwrap();
+        let end = text.find("\n\nfn h").unwrap();
+        let files = input(text);
+        let operators = OperatorWeights {
+            delete: 15,
+            duplicate: 15,
+            insert: 15,
+            rename: 15,
+            literal: 15,
+            ..Default::default()
+        };
+        let splicer = rust()
+            .chaos(5)
+            .budget(Budget::Bytes(24))
+            .focus(Focus::Range(start..end))
+            .operators(operators.splice_rest())
+            .build(&files);
+        let tests: Vec<_> = splicer.take(64).collect();
+        for test in &tests {
+            assert!(test.starts_with(&text.as_bytes()[..start]));
+            assert!(test.ends_with(&text.as_bytes()[end..]));
+        }
+        assert!(tests.iter().any(|t| t != text.as_bytes()));
+    }
+
+    #[test]
+    fn same_seed_same_tests() {
+        let tests = generate(|b| b.seed(7), 16);
+        assert_eq!(tests, generate(|b| b.seed(7), 16));
+        assert_ne!(tests, generate(|b| b.seed(8), 16));
+    }
+
+    #[test]
+    fn one_inter_splice() {
+        generate(|b| b.inter_splices(1), 20);
+    }
+
+    #[test]
+    fn reparse_never() {
+        generate(|b| b.inter_splices(8).reparse(0), 20);
+    }
+
+    #[test]
+    fn nothing_to_mutate() {
+        let empty = HashMap::new();
+        assert_eq!(rust().build(&empty).next(), None);
+        let small = input("fn f() {}\n");
+        let (text, tree) = &small["f.rs"];
+        let files = input(LISTED);
+        let mut splicer = rust().max_size(text.len()).build(&files);
+        assert_eq!(splicer.next(), None);
+        splicer.add_tree(text, tree);
+        assert!(splicer.next().is_some());
     }
 }
@@ -0,0 +1,150 @@
+//! Use tests made by a [`Splicer`] in property-based tests with `proptest`.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use proptest::arbitrary::any;
+use proptest::strategy::{NewTree, Strategy, ValueTree};
+use proptest::test_runner::TestRunner;
+use tree_sitter::Tree;
+
+use crate::index::CorpusIndex;
+use crate::splice::{parse, Config, Splicer};
+
+/// A [`Strategy`] for programs made by splicing a small corpus, e.g., to
+/// check that a parser and a pretty-printer round-trip:
+///
+/// ```ignore
+/// proptest! {
+///     #[test]
+///     fn round_trip(src in SpliceStrategy::new(config, CORPUS)) {
+///         prop_assert_eq!(parse(&print(&parse(&src))), parse(&src));
+///     }
+/// }
+/// ```
+///
+/// The programs parse without errors, and are valid UTF-8. A failing program
+/// shrinks to the file in the corpus that it was made from, if that fails
+/// too.
+#[derive(Clone)]
+pub struct SpliceStrategy {
+    config: Config,
+    files: HashMap<String, (Vec<u8>, Tree)>,
+    index: CorpusIndex,
+    attempts: usize,
+}
+
+impl SpliceStrategy {
+    /// Splice `files`, which are parsed with [`Config::language`].
+    /// [`Config::seed`] is ignored, the [`TestRunner`] picks the seeds.
+    pub fn new(config: Config, files: impl IntoIterator<Item = impl AsRef<[u8]>>) -> Self {
+        let files: HashMap<_, _> = files
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let text = text.as_ref().to_vec();
+                let tree = parse(config.language, &text);
+                (i.to_string(), (text, tree))
+            })
+            .collect();
+        let index = CorpusIndex::new(&files, &[]);
+        SpliceStrategy {
+            config,
+            files,
+            index,
+            attempts: 16,
+        }
+    }
+
+    /// Make this many programs before giving up on finding one that parses,
+    /// which the runner counts as a rejection (default 16)
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+}
+
+impl fmt::Debug for SpliceStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpliceStrategy")
+            .field("config", &self.config)
+            .field("files", &self.files.len())
+            .field("attempts", &self.attempts)
+            .finish()
+    }
+}
+
+impl Strategy for SpliceStrategy {
+    type Tree = Spliced;
+    type Value = String;
+
+    fn new_tree(&self, runner: &mut TestRunner) -> NewTree<Self> {
+        let config = Config {
+            seed: any::<u64>().new_tree(runner)?.current(),
+            ..self.config.clone()
+        };
+        let mut splicer = Splicer::new_with_index(config, &self.files, &[], &self.index)
+            .expect("Index is of the files");
+        for _ in 0..self.attempts {
+            let Some(test) = splicer.next() else {
+                break;
+            };
+            if parse(self.config.language, &test).root_node().has_error() {
+                continue;
+            }
+            let Ok(test) = String::from_utf8(test) else {
+                continue;
+            };
+            let input = splicer
+                .input()
+                .and_then(|path| self.files.get(path))
+                .and_then(|(text, _)| String::from_utf8(text.clone()).ok())
+                .filter(|input| *input != test);
+            return Ok(Spliced {
+                test,
+                input,
+                simplified: false,
+                tried: false,
+            });
+        }
+        Err("No spliced program parsed".into())
+    }
+}
+
+/// A program made by [`SpliceStrategy`], which shrinks to its input
+#[derive(Clone, Debug)]
+pub struct Spliced {
+    test: String,
+    /// The file in the corpus the test was made from, if it's different
+    input: Option<String>,
+    /// Whether the current value is `input`
+    simplified: bool,
+    /// Whether `input` was tried
+    tried: bool,
+}
+
+impl ValueTree for Spliced {
+    type Value = String;
+
+    fn current(&self) -> String {
+        match (&self.input, self.simplified) {
+            (Some(input), true) => input.clone(),
+            _ => self.test.clone(),
+        }
+    }
+
+    fn simplify(&mut self) -> bool {
+        if self.tried || self.input.is_none() {
+            return false;
+        }
+        self.tried = true;
+        self.simplified = true;
+        true
+    }
+
+    fn complicate(&mut self) -> bool {
+        let changed = self.simplified;
+        self.simplified = false;
+        changed
+    }
+}
@@ -0,0 +1,132 @@
+//! Handwritten splice candidates with holes, see [`Templates`].
+
+use std::collections::HashMap;
+
+use crate::node_types::NodeTypes;
+
+/// A hole in a template
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Hole {
+    /// `$IDENT`, an identifier of any kind
+    Ident,
+    /// `$EXPR` or `${kind}`, a node of one of these kinds
+    Kinds(Vec<String>),
+}
+
+/// A piece of a template
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Segment {
+    Text(String),
+    Hole(Hole),
+}
+
+/// Snippets to splice in, besides the nodes of the corpus, with holes that
+/// are filled with nodes from the corpus at splice time:
+///
+/// - `$IDENT` is an identifier, see [`crate::Config::identifier_kinds`]
+/// - `$EXPR` is an expression, if the grammar has an `expression` or
+///   `_expression` kind
+/// - `${kind}` is a node of kind `kind`, or of a subtype if it's a
+///   supertype, e.g., `${_type}`
+/// - `$$` is `$`
+///
+/// Each hole is filled separately, so `$IDENT = $IDENT` may have two
+/// different identifiers.
+#[derive(Clone, Debug)]
+pub struct Templates {
+    /// The templates that can replace a node of each kind
+    pub(crate) templates: HashMap<String, Vec<Vec<Segment>>>,
+}
+
+impl Templates {
+    /// `templates` maps node kinds to snippets that can replace nodes of
+    /// that kind. Snippets for a supertype can replace any of its subtypes.
+    pub fn new(
+        node_types: &NodeTypes,
+        templates: impl IntoIterator<Item = (String, Vec<String>)>,
+    ) -> Result<Self, String> {
+        let mut by_kind: HashMap<String, Vec<Vec<Segment>>> = HashMap::new();
+        for (kind, snippets) in templates {
+            let kinds = kinds(node_types, &kind)?;
+            for snippet in snippets {
+                let segments = segments(node_types, &snippet)?;
+                for kind in &kinds {
+                    by_kind
+                        .entry(kind.clone())
+                        .or_default()
+                        .push(segments.clone());
+                }
+            }
+        }
+        Ok(Templates { templates: by_kind })
+    }
+
+    /// Number of templates, counting those for a supertype once per subtype
+    pub fn len(&self) -> usize {
+        self.templates.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// `kind` and its subtypes
+fn kinds(node_types: &NodeTypes, kind: &str) -> Result<Vec<String>, String> {
+    if !node_types.has_kind(kind) {
+        return Err(format!("No node kind {}", kind));
+    }
+    Ok(node_types.subtypes(&kind.to_string()).to_vec())
+}
+
+fn segments(node_types: &NodeTypes, snippet: &str) -> Result<Vec<Segment>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = snippet;
+    while let Some(start) = rest.find('$') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        let hole = if let Some(r) = rest.strip_prefix('$') {
+            text.push('$');
+            rest = r;
+            continue;
+        } else if let Some(r) = rest.strip_prefix("IDENT") {
+            rest = r;
+            Hole::Ident
+        } else if let Some(r) = rest.strip_prefix("EXPR") {
+            rest = r;
+            let kinds: Vec<_> = ["expression", "_expression"]
+                .into_iter()
+                .filter(|k| node_types.has_kind(k))
+                .flat_map(|k| node_types.subtypes(&k.to_string()).to_vec())
+                .collect();
+            if kinds.is_empty() {
+                return Err(format!(
+                    "No expression kind for $EXPR in {}, use ${{kind}}",
+                    snippet
+                ));
+            }
+            Hole::Kinds(kinds)
+        } else if let Some(r) = rest.strip_prefix('{') {
+            let end = r
+                .find('}')
+                .ok_or_else(|| format!("Unclosed ${{ in {}", snippet))?;
+            rest = &r[end + 1..];
+            Hole::Kinds(kinds(node_types, &r[..end])?)
+        } else {
+            return Err(format!(
+                "Expected $IDENT, $EXPR, ${{kind}}, or $$ in {}",
+                snippet
+            ));
+        };
+        if !text.is_empty() {
+            segments.push(Segment::Text(std::mem::take(&mut text)));
+        }
+        segments.push(Segment::Hole(hole));
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
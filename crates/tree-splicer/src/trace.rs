@@ -0,0 +1,144 @@
+//! Records of the mutations that made a test, to reproduce or explain it.
+
+use serde::{Deserialize, Serialize};
+
+/// Bytes, as a string if they're valid UTF-8
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Bytes {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Bytes {
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Bytes::Text(s) => s.as_bytes(),
+            Bytes::Binary(b) => b,
+        }
+    }
+}
+
+impl From<&[u8]> for Bytes {
+    fn from(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(s) => Bytes::Text(s.to_string()),
+            Err(_) => Bytes::Binary(bytes.to_vec()),
+        }
+    }
+}
+
+/// Where a replacement came from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Origin {
+    pub file: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    /// Replaced a node with a splice candidate
+    Splice,
+    /// Deleted a node
+    Delete,
+    /// Replaced a node with two copies of itself
+    Duplicate,
+    /// Added a splice candidate after a node
+    Insert,
+    /// Swapped a node with another element of the same list
+    Swap,
+    /// Replaced every occurrence of an identifier
+    Rename,
+    /// Replaced a literal with an edge case
+    Literal,
+    /// Edited the bytes and tokens of a node
+    Havoc,
+    /// Replaced a node with another node from the same file
+    Intra,
+    /// Replaced a node as [`Config::rules`](crate::Config::rules) say
+    Rule,
+    /// Replaced a node with one of [`Config::templates`](crate::Config::templates)
+    Template,
+    /// Nested a node in copies of the text around another node
+    Nest,
+    /// Replaced an operator with another
+    Operator,
+    /// Replaced a node with parts of another input
+    Crossover,
+    /// Replaced embedded code with a mutant, see [`crate::injection`]
+    Embedded,
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Operation::Splice => write!(f, "splice"),
+            Operation::Delete => write!(f, "delete"),
+            Operation::Duplicate => write!(f, "duplicate"),
+            Operation::Insert => write!(f, "insert"),
+            Operation::Swap => write!(f, "swap"),
+            Operation::Rename => write!(f, "rename"),
+            Operation::Literal => write!(f, "literal"),
+            Operation::Havoc => write!(f, "havoc"),
+            Operation::Intra => write!(f, "intra"),
+            Operation::Rule => write!(f, "rule"),
+            Operation::Template => write!(f, "template"),
+            Operation::Nest => write!(f, "nest"),
+            Operation::Operator => write!(f, "operator"),
+            Operation::Crossover => write!(f, "crossover"),
+            Operation::Embedded => write!(f, "embedded"),
+        }
+    }
+}
+
+/// One replacement of a node
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edit {
+    pub operation: Operation,
+    /// Kind of the replaced node
+    pub kind: String,
+    /// Byte range of the replaced node
+    pub start: usize,
+    pub end: usize,
+    pub replacement: Bytes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<Origin>,
+}
+
+/// The mutations that made a test
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trace {
+    /// The file that was mutated, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<String>,
+    /// Each step edits the result of the previous step (starting with the
+    /// input). The edits in a step are in order and don't overlap.
+    pub steps: Vec<Vec<Edit>>,
+}
+
+impl Trace {
+    /// Apply the steps to `input`.
+    ///
+    /// Returns `None` if an edit is out of bounds or out of order, e.g., if
+    /// `input` isn't the file that was mutated.
+    pub fn replay(&self, input: &[u8]) -> Option<Vec<u8>> {
+        let mut text = input.to_vec();
+        for step in &self.steps {
+            let mut result = Vec::with_capacity(text.len());
+            let mut start = 0;
+            for edit in step {
+                if edit.start < start || edit.end < edit.start || edit.end > text.len() {
+                    return None;
+                }
+                result.extend(&text[start..edit.start]);
+                result.extend(edit.replacement.as_bytes());
+                start = edit.end;
+            }
+            result.extend(&text[start..]);
+            text = result;
+        }
+        Some(text)
+    }
+}
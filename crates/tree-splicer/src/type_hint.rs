@@ -0,0 +1,59 @@
+//! Guesses at the types of nodes, to avoid splices that don't type-check.
+
+use std::fmt::Debug;
+
+/// Guesses the type of a node from its kind and text.
+///
+/// With [`crate::splice::Config::type_hint`], a non-chaotic splice only
+/// replaces a node of a known type with a candidate of the same type. Nodes
+/// of unknown type can be replaced with any candidate of the same kind.
+///
+/// This is called on every candidate of a kind for each splice, so it should
+/// be fast.
+pub trait TypeHint: Debug + Send + Sync {
+    /// The type of a node of kind `kind` with text `text`, if known
+    fn type_of(&self, kind: &str, text: &[u8]) -> Option<String>;
+}
+
+/// A type for nodes whose text matches a regular expression, e.g.,
+/// `int=^-?[0-9]+$`
+#[cfg(feature = "regex")]
+#[derive(Clone, Debug)]
+pub struct TypeRule {
+    pub ty: String,
+    pub regex: regex::bytes::Regex,
+}
+
+#[cfg(feature = "regex")]
+impl std::str::FromStr for TypeRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (ty, regex) = s
+            .split_once('=')
+            .ok_or_else(|| format!("Expected TYPE=REGEX, found {}", s))?;
+        let regex = regex::bytes::Regex::new(regex)
+            .map_err(|e| format!("Invalid regex {}: {}", regex, e))?;
+        Ok(TypeRule {
+            ty: ty.to_string(),
+            regex,
+        })
+    }
+}
+
+/// Guesses types with [`TypeRule`]s; the first rule that matches wins.
+#[cfg(feature = "regex")]
+#[derive(Clone, Debug, Default)]
+pub struct RegexTypeHint {
+    pub rules: Vec<TypeRule>,
+}
+
+#[cfg(feature = "regex")]
+impl TypeHint for RegexTypeHint {
+    fn type_of(&self, _kind: &str, text: &[u8]) -> Option<String> {
+        self.rules
+            .iter()
+            .find(|r| r.regex.is_match(text))
+            .map(|r| r.ty.clone())
+    }
+}
@@ -0,0 +1,136 @@
+//! Queries of `NodeTypes`, whose supertypes may be mutually recursive, and
+//! of which nodes of a Rust program can be deleted, and with which separators.
+
+use tree_sitter::{Node, Tree};
+use tree_splicer::node_types::{FieldSpec, NodeTypes};
+use tree_splicer::splice::parse;
+
+const CYCLIC: &str = include_str!("cyclic-node-types.json");
+
+/// A program with optional and required fields, and lists with and without
+/// separators
+const RUST: &str = "fn f<T>(x: u8) { g(x, 1, 2); h(); }\n";
+
+fn rust() -> (NodeTypes, Tree) {
+    let node_types = NodeTypes::new(tree_sitter_rust::NODE_TYPES).unwrap();
+    (
+        node_types,
+        parse(tree_sitter_rust::language(), RUST.as_bytes()),
+    )
+}
+
+/// The smallest node of kind `kind` around `text`, where it is in `context`
+fn find<'t>(tree: &'t Tree, context: &str, text: &str, kind: &str) -> Node<'t> {
+    let start = RUST.find(context).unwrap() + context.find(text).unwrap();
+    let mut node = tree
+        .root_node()
+        .descendant_for_byte_range(start, start + text.len())
+        .unwrap();
+    while node.kind() != kind {
+        node = node.parent().unwrap();
+    }
+    node
+}
+
+fn subtypes(node_types: &NodeTypes, kind: &str) -> Vec<String> {
+    let mut subtypes = node_types.subtypes(&kind.to_string()).to_vec();
+    assert_eq!(subtypes[0], kind);
+    subtypes.sort();
+    subtypes
+}
+
+#[test]
+fn cyclic_subtypes() {
+    let node_types = NodeTypes::new(CYCLIC).unwrap();
+    let all = ["_expression", "_primary", "binary", "identifier"];
+    assert_eq!(subtypes(&node_types, "_expression"), all);
+    assert_eq!(subtypes(&node_types, "_primary"), all);
+    assert_eq!(subtypes(&node_types, "binary"), ["binary"]);
+    assert_eq!(subtypes(&node_types, "identifier"), ["identifier"]);
+}
+
+#[test]
+fn malformed_node() {
+    let json = r#"[
+        { "type": "identifier", "named": true },
+        { "type": "binary", "named": true, "children": { "types": [] } }
+    ]"#;
+    let err = NodeTypes::new(json).unwrap_err().to_string();
+    assert!(err.starts_with("Malformed node 1 (binary): "), "{}", err);
+    assert!(NodeTypes::new(r#"{ "type": "identifier" }"#).is_err());
+}
+
+#[test]
+fn from_path() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("cyclic-node-types.json");
+    let node_types = NodeTypes::from_path(&path).unwrap();
+    assert!(node_types.has_kind("_primary"));
+    let err = NodeTypes::from_path(&path.with_extension("missing")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn queries() {
+    let node_types = NodeTypes::new(CYCLIC).unwrap();
+    assert!(node_types.is_supertype("_expression"));
+    assert!(!node_types.is_supertype("binary"));
+    assert!(!node_types.is_supertype("missing"));
+    assert_eq!(node_types.get_subtypes("missing"), Vec::<String>::new());
+    assert_eq!(
+        node_types.fields_of("binary"),
+        [FieldSpec {
+            name: "operator".to_string(),
+            multiple: false,
+            required: true,
+            types: vec!["+".to_string()],
+        }]
+    );
+    assert_eq!(node_types.required_children("binary"), ["_expression"]);
+    assert!(node_types.required_children("identifier").is_empty());
+}
+
+#[test]
+fn can_delete() {
+    let (node_types, tree) = rust();
+    let can_delete = |context, text, kind| node_types.can_delete(&find(&tree, context, text, kind));
+    // Optional and required fields
+    assert!(can_delete("f<T>", "<T>", "type_parameters"));
+    assert!(!can_delete("f<T>", "f", "identifier"));
+    assert!(!can_delete("(x: u8)", "(x: u8)", "parameters"));
+    // Elements of a list with a separator, and of one without
+    assert!(can_delete("g(x, 1, 2)", "x", "identifier"));
+    assert!(can_delete("g(x, 1, 2)", "2", "integer_literal"));
+    assert!(can_delete("h();", "h();", "expression_statement"));
+    // The expression of a statement is a required child
+    assert!(!can_delete("h();", "h()", "call_expression"));
+}
+
+#[test]
+fn separator() {
+    let (node_types, tree) = rust();
+    let separator = |context, text, kind| {
+        let node = find(&tree, context, text, kind);
+        let separator = node_types.separator(&node)?;
+        Some((separator.kind(), separator.start_byte()))
+    };
+    // The comma after an element, or before the last one
+    let args = RUST.find("g(x, 1, 2)").unwrap();
+    assert_eq!(
+        separator("g(x, 1, 2)", "x", "identifier"),
+        Some((",", args + 3))
+    );
+    assert_eq!(
+        separator("g(x, 1, 2)", "2", "integer_literal"),
+        Some((",", args + 6))
+    );
+    // Statements end in `;`, but it's part of each of them
+    assert_eq!(
+        separator("g(x, 1, 2);", "g(x, 1, 2);", "expression_statement"),
+        None
+    );
+    assert_eq!(separator("h();", "h();", "expression_statement"), None);
+    // The only parameter has no separator
+    assert_eq!(separator("x: u8", "x: u8", "parameter"), None);
+}